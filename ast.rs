@@ -0,0 +1,323 @@
+//The abstract syntax tree that stage3::Parser builds. Nodes keep the tokens
+//they were parsed from and their source span; expressions encode the
+//precedence of the operator table, so parentheses are not stored.
+//The operands of an expression and the bodies of loops, ifs and functions
+//are behind an Arc, so the parser's memo shares them instead of copying.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::diagnostics::Span;
+use crate::stage2_token::Token;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DataType {
+    Char,
+    Short,
+    Int,
+    Long,
+    UnsignedChar,
+    UnsignedShort,
+    UnsignedInt,
+    UnsignedLong,
+    Float,
+    Double,
+}
+
+impl DataType {
+    // the type named by an optional unsigned and a type keyword
+    pub fn from_keywords(unsigned: bool, keyword: &str) -> Option<DataType> {
+        let data_type = match (unsigned, keyword) {
+            (false, "char") => DataType::Char,
+            (false, "short") => DataType::Short,
+            (false, "int") => DataType::Int,
+            (false, "long") => DataType::Long,
+            (true, "char") => DataType::UnsignedChar,
+            (true, "short") => DataType::UnsignedShort,
+            (true, "int") => DataType::UnsignedInt,
+            (true, "long") => DataType::UnsignedLong,
+            (false, "float") => DataType::Float,
+            (false, "double") => DataType::Double,
+            _ => return None,
+        };
+        Some(data_type)
+    }
+
+    pub fn is_floating(&self) -> bool {
+        matches!(self, DataType::Float | DataType::Double)
+    }
+
+    pub fn is_unsigned(&self) -> bool {
+        matches!(
+            self,
+            DataType::UnsignedChar | DataType::UnsignedShort | DataType::UnsignedInt | DataType::UnsignedLong
+        )
+    }
+
+    // the size in bits, as on the usual 64-bit targets
+    pub fn bits(&self) -> u32 {
+        match self {
+            DataType::Char | DataType::UnsignedChar => 8,
+            DataType::Short | DataType::UnsignedShort => 16,
+            DataType::Int | DataType::UnsignedInt | DataType::Float => 32,
+            DataType::Long | DataType::UnsignedLong | DataType::Double => 64,
+        }
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            DataType::Char => "char",
+            DataType::Short => "short",
+            DataType::Int => "int",
+            DataType::Long => "long",
+            DataType::UnsignedChar => "unsigned char",
+            DataType::UnsignedShort => "unsigned short",
+            DataType::UnsignedInt => "unsigned int",
+            DataType::UnsignedLong => "unsigned long",
+            DataType::Float => "float",
+            DataType::Double => "double",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Program {
+    pub declarations: Vec<Declaration>,
+    pub main: MainDeclaration,
+    pub functions: Vec<FunctionDefinition>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub enum Declaration {
+    Variable(VariableDeclaration),
+    Function(FunctionDeclaration),
+}
+
+#[derive(Clone, Debug)]
+pub struct VariableDeclaration {
+    pub data_type: DataType,
+    pub name: Token,
+    pub initializer: Option<Expression>,
+    pub span: Span,
+}
+
+// a prototype: float Foo(int val);
+#[derive(Clone, Debug)]
+pub struct FunctionDeclaration {
+    pub return_type: DataType,
+    pub name: Token,
+    pub parameters: Vec<Parameter>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct MainDeclaration {
+    pub block: Block,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct FunctionDefinition {
+    pub return_type: DataType,
+    pub name: Token,
+    pub parameters: Vec<Parameter>,
+    pub block: Arc<Block>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct Parameter {
+    pub data_type: DataType,
+    pub name: Token,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct Block {
+    pub declarations: Vec<Declaration>,
+    pub statements: Vec<Statement>,
+    pub functions: Vec<FunctionDefinition>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+// assignments are expression statements whose expression is an Assign
+#[derive(Clone, Debug)]
+pub enum StatementKind {
+    Expression(Expression),
+    While(Expression, Arc<Block>),
+    If(Expression, Arc<Block>),
+    Return(Expression),
+}
+
+#[derive(Clone, Debug)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+    // the type typecheck::check_types gives the expression; None before
+    // that, and where it cannot be known, like for an undeclared name
+    pub ty: Option<DataType>,
+}
+
+// operators are kept as their tokens; operators::find gives their entry
+// in the operator table
+#[derive(Clone, Debug)]
+pub enum ExpressionKind {
+    // an IntConstant or FloatConstant token
+    Constant(Token),
+    Identifier(Token),
+    Call(Token, Vec<Arc<Expression>>),
+    Unary(Token, Arc<Expression>),
+    Binary(Token, Arc<Expression>, Arc<Expression>),
+    // target = value
+    Assign(Token, Arc<Expression>),
+}
+
+// fully parenthesized, so the dump of an expression shows how it was grouped
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ExpressionKind::Constant(token) | ExpressionKind::Identifier(token) => {
+                write!(f, "{}", token.text)
+            }
+            ExpressionKind::Call(name, arguments) => {
+                write!(f, "{}(", name.text)?;
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", argument)?;
+                }
+                write!(f, ")")
+            }
+            ExpressionKind::Unary(operator, operand) => write!(f, "({}{})", operator.text, operand),
+            ExpressionKind::Binary(operator, lhs, rhs) => {
+                write!(f, "({} {} {})", lhs, operator.text, rhs)
+            }
+            ExpressionKind::Assign(target, value) => write!(f, "({} = {})", target.text, value),
+        }
+    }
+}
+
+// the parameter list of a declaration, e.g. (int a, char b)
+fn block_definitions<'a>(block: &'a Block, res: &mut Vec<&'a FunctionDefinition>) {
+    for statement in block.statements.iter() {
+        if let StatementKind::While(_, body) | StatementKind::If(_, body) = &statement.kind {
+            block_definitions(body, res);
+        }
+    }
+    for function in block.functions.iter() {
+        res.push(function);
+        block_definitions(&function.block, res);
+    }
+}
+
+fn parameter_list(parameters: &[Parameter]) -> String {
+    let parameters: Vec<String> = parameters
+        .iter()
+        .map(|parameter| format!("{} {}", parameter.data_type, parameter.name.text))
+        .collect();
+    format!("({})", parameters.join(", "))
+}
+
+fn dump_line(depth: usize, text: &str, span: Span, res: &mut String) {
+    *res += &format!("{}{} [{}]\n", "  ".repeat(depth), text, span);
+}
+
+fn dump_declaration(declaration: &Declaration, depth: usize, res: &mut String) {
+    match declaration {
+        Declaration::Variable(variable) => {
+            let initializer = match &variable.initializer {
+                Some(initializer) => format!(" = {}", initializer),
+                None => String::new(),
+            };
+            let text = format!("VariableDeclaration {} {}{}", variable.data_type, variable.name.text, initializer);
+            dump_line(depth, &text, variable.span, res);
+        }
+        Declaration::Function(function) => {
+            let text = format!(
+                "FunctionDeclaration {} {}{}",
+                function.return_type,
+                function.name.text,
+                parameter_list(&function.parameters)
+            );
+            dump_line(depth, &text, function.span, res);
+        }
+    }
+}
+
+fn dump_function(function: &FunctionDefinition, depth: usize, res: &mut String) {
+    let text = format!(
+        "FunctionDefinition {} {}{}",
+        function.return_type,
+        function.name.text,
+        parameter_list(&function.parameters)
+    );
+    dump_line(depth, &text, function.span, res);
+    dump_block(&function.block, depth + 1, res);
+}
+
+fn dump_block(block: &Block, depth: usize, res: &mut String) {
+    dump_line(depth, "Block", block.span, res);
+    for declaration in block.declarations.iter() {
+        dump_declaration(declaration, depth + 1, res);
+    }
+    for statement in block.statements.iter() {
+        match &statement.kind {
+            StatementKind::Expression(expression) => {
+                dump_line(depth + 1, &format!("Expression {}", expression), statement.span, res)
+            }
+            StatementKind::While(condition, body) => {
+                dump_line(depth + 1, &format!("While {}", condition), statement.span, res);
+                dump_block(body, depth + 2, res);
+            }
+            StatementKind::If(condition, body) => {
+                dump_line(depth + 1, &format!("If {}", condition), statement.span, res);
+                dump_block(body, depth + 2, res);
+            }
+            StatementKind::Return(value) => dump_line(depth + 1, &format!("Return {}", value), statement.span, res),
+        }
+    }
+    for function in block.functions.iter() {
+        dump_function(function, depth + 1, res);
+    }
+}
+
+impl Program {
+    // the function definitions, nested ones included, each followed by the
+    // ones nested in it
+    pub(crate) fn definitions(&self) -> Vec<&FunctionDefinition> {
+        let mut res = Vec::new();
+        block_definitions(&self.main.block, &mut res);
+        for function in self.functions.iter() {
+            res.push(function);
+            block_definitions(&function.block, &mut res);
+        }
+        res
+    }
+
+    // an outline of the tree, one node per line indented by its depth and
+    // followed by its span; expressions are fully parenthesized
+    pub fn dump(&self) -> String {
+        let mut res = String::new();
+        dump_line(0, "Program", self.span, &mut res);
+        for declaration in self.declarations.iter() {
+            dump_declaration(declaration, 1, &mut res);
+        }
+        dump_line(1, "MainDeclaration", self.main.span, &mut res);
+        dump_block(&self.main.block, 2, &mut res);
+        for function in self.functions.iter() {
+            dump_function(function, 1, &mut res);
+        }
+        res
+    }
+}
@@ -0,0 +1,467 @@
+//Call graphs: which function calls which, for main and every function,
+//nested ones included. A prototype and its definition are one function, and
+//a nested function is named after the functions around it, like Bar.BarFoo.
+//The graph finds direct and mutual recursion (the strongly connected
+//components of the graph), the functions main never reaches and the longest
+//chain of calls from main, and prints itself as text or for Graphviz.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::ast::{Block, Declaration, Expression, ExpressionKind, Program, StatementKind};
+use crate::cfg::escape;
+use crate::diagnostics::Span;
+use crate::resolve::{SymbolKind, SymbolTable};
+
+// main is always the first function
+pub const MAIN: usize = 0;
+
+#[derive(Clone, Debug)]
+pub struct Function {
+    pub name: String,
+    // the names of the functions around it and its own, joined by dots
+    pub qualified: String,
+    // the name in the definition, or in the first prototype of a function
+    // that is never defined; the whole of main
+    pub span: Span,
+    pub defined: bool,
+    // the symbols of its prototypes and its definition; none for main
+    pub symbols: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Call {
+    pub caller: usize,
+    pub callee: usize,
+    // the called name
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CallGraph {
+    pub functions: Vec<Function>,
+    // in the order of the callers, then of the calls in them
+    pub calls: Vec<Call>,
+}
+
+// the called names in an expression, in order
+fn expression_calls<'a>(expression: &'a Expression, res: &mut Vec<&'a Expression>) {
+    match &expression.kind {
+        ExpressionKind::Constant(_) | ExpressionKind::Identifier(_) => {}
+        ExpressionKind::Call(_, arguments) => {
+            res.push(expression);
+            for argument in arguments.iter() {
+                expression_calls(argument, res);
+            }
+        }
+        ExpressionKind::Unary(_, operand) => expression_calls(operand, res),
+        ExpressionKind::Binary(_, lhs, rhs) => {
+            expression_calls(lhs, res);
+            expression_calls(rhs, res);
+        }
+        ExpressionKind::Assign(_, value) => expression_calls(value, res),
+    }
+}
+
+// the calls in a body, but not in the functions defined in it
+fn block_calls<'a>(block: &'a Block, res: &mut Vec<&'a Expression>) {
+    for declaration in block.declarations.iter() {
+        if let Declaration::Variable(variable) = declaration {
+            if let Some(initializer) = &variable.initializer {
+                expression_calls(initializer, res);
+            }
+        }
+    }
+    for statement in block.statements.iter() {
+        match &statement.kind {
+            StatementKind::Expression(expression) | StatementKind::Return(expression) => expression_calls(expression, res),
+            StatementKind::While(condition, body) | StatementKind::If(condition, body) => {
+                expression_calls(condition, res);
+                block_calls(body, res);
+            }
+        }
+    }
+}
+
+impl CallGraph {
+    // the call graph of a resolved program; calls of names that are not
+    // functions are left out
+    pub fn build(program: &Program, table: &SymbolTable) -> CallGraph {
+        let mut bodies: Vec<(Span, &Block)> = vec![(program.main.span, &program.main.block)];
+        let definitions = program.definitions();
+        let definition_names: HashSet<Span> = definitions.iter().map(|function| Span::of_token(&function.name)).collect();
+        bodies.extend(definitions.iter().map(|function| (function.span, &*function.block)));
+
+        let mut graph = CallGraph::default();
+        graph.functions.push(Function {
+            name: "main".to_string(),
+            qualified: "main".to_string(),
+            span: program.main.span,
+            defined: true,
+            symbols: Vec::new(),
+        });
+        // the function of every function symbol; a prototype and its
+        // definition have the same name and scope
+        let mut function_of = HashMap::new();
+        let mut by_name = HashMap::new();
+        // intrinsics are not part of the program
+        for (i, symbol) in table.symbols.iter().enumerate() {
+            if !symbol.kind.is_function() || symbol.kind == SymbolKind::Intrinsic {
+                continue;
+            }
+            let index = *by_name.entry((symbol.name.as_str(), symbol.scope)).or_insert_with(|| {
+                // the bodies around the first declaration, outermost first,
+                // but not the function's own
+                let mut qualified: Vec<&str> = bodies
+                    .iter()
+                    .enumerate()
+                    .filter(|&(index, (span, _))| {
+                        span.contains(&symbol.span) && (index == 0 || Span::of_token(&definitions[index - 1].name) != symbol.span)
+                    })
+                    .map(|(index, _)| match index {
+                        0 => "main",
+                        _ => definitions[index - 1].name.text.as_str(),
+                    })
+                    .collect();
+                qualified.push(&symbol.name);
+                graph.functions.push(Function {
+                    name: symbol.name.clone(),
+                    qualified: qualified.join("."),
+                    span: symbol.span,
+                    defined: false,
+                    symbols: Vec::new(),
+                });
+                graph.functions.len() - 1
+            });
+            let function = &mut graph.functions[index];
+            function.symbols.push(i);
+            if definition_names.contains(&symbol.span) {
+                function.defined = true;
+                function.span = symbol.span;
+            }
+            function_of.insert(i, index);
+        }
+
+        for (index, (_, body)) in bodies.iter().enumerate() {
+            let caller = match index {
+                0 => MAIN,
+                _ => {
+                    let name = Span::of_token(&definitions[index - 1].name);
+                    match table.symbols.iter().position(|symbol| symbol.span == name && symbol.kind.is_function()) {
+                        Some(symbol) => function_of[&symbol],
+                        None => continue,
+                    }
+                }
+            };
+            let mut calls = Vec::new();
+            block_calls(body, &mut calls);
+            for call in calls {
+                let ExpressionKind::Call(name, _) = &call.kind else {
+                    continue;
+                };
+                let span = Span::of_token(name);
+                if let Some(&callee) = table.uses.get(&span).and_then(|symbol| function_of.get(symbol)) {
+                    graph.calls.push(Call { caller, callee, span });
+                }
+            }
+        }
+        graph
+    }
+
+    // a function by its qualified name
+    pub fn find(&self, qualified: &str) -> Option<usize> {
+        self.functions.iter().position(|function| function.qualified == qualified)
+    }
+
+    // the functions a function calls, in the order of their first call
+    pub fn callees(&self, function: usize) -> Vec<usize> {
+        let mut res = Vec::new();
+        for call in self.calls.iter().filter(|call| call.caller == function) {
+            if !res.contains(&call.callee) {
+                res.push(call.callee);
+            }
+        }
+        res
+    }
+
+    // the functions that call a function, in order
+    pub fn callers(&self, function: usize) -> Vec<usize> {
+        let mut res: Vec<usize> = self.calls.iter().filter(|call| call.callee == function).map(|call| call.caller).collect();
+        res.sort();
+        res.dedup();
+        res
+    }
+
+    // which functions main calls, directly or through other functions
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reached = vec![false; self.functions.len()];
+        let mut work = vec![MAIN];
+        while let Some(function) = work.pop() {
+            if !std::mem::replace(&mut reached[function], true) {
+                work.extend(self.callees(function));
+            }
+        }
+        reached
+    }
+
+    // the functions main never reaches
+    pub fn unreachable(&self) -> Vec<usize> {
+        let reached = self.reachable();
+        (0..self.functions.len()).filter(|&function| !reached[function]).collect()
+    }
+
+    // the sets of functions that call each other, each sorted, in the order
+    // of their first function; a function that calls itself is a set of one
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        let components = self.components();
+        let mut res: Vec<Vec<usize>> = components
+            .into_iter()
+            .filter(|component| component.len() > 1 || self.callees(component[0]).contains(&component[0]))
+            .collect();
+        res.sort();
+        res
+    }
+
+    pub fn is_recursive(&self, function: usize) -> bool {
+        self.cycles().iter().any(|cycle| cycle.contains(&function))
+    }
+
+    // Tarjan's strongly connected components, each sorted
+    fn components(&self) -> Vec<Vec<usize>> {
+        struct Tarjan<'a> {
+            graph: &'a CallGraph,
+            index: Vec<Option<usize>>,
+            low: Vec<usize>,
+            stack: Vec<usize>,
+            on_stack: Vec<bool>,
+            next: usize,
+            res: Vec<Vec<usize>>,
+        }
+
+        impl Tarjan<'_> {
+            fn visit(&mut self, function: usize) {
+                self.index[function] = Some(self.next);
+                self.low[function] = self.next;
+                self.next += 1;
+                self.stack.push(function);
+                self.on_stack[function] = true;
+                for callee in self.graph.callees(function) {
+                    match self.index[callee] {
+                        None => {
+                            self.visit(callee);
+                            self.low[function] = self.low[function].min(self.low[callee]);
+                        }
+                        Some(index) if self.on_stack[callee] => self.low[function] = self.low[function].min(index),
+                        Some(_) => {}
+                    }
+                }
+                if Some(self.low[function]) == self.index[function] {
+                    let mut component = Vec::new();
+                    while let Some(member) = self.stack.pop() {
+                        self.on_stack[member] = false;
+                        component.push(member);
+                        if member == function {
+                            break;
+                        }
+                    }
+                    component.sort();
+                    self.res.push(component);
+                }
+            }
+        }
+
+        let n = self.functions.len();
+        let mut tarjan = Tarjan {
+            graph: self,
+            index: vec![None; n],
+            low: vec![0; n],
+            stack: Vec::new(),
+            on_stack: vec![false; n],
+            next: 0,
+            res: Vec::new(),
+        };
+        for function in 0..n {
+            if tarjan.index[function].is_none() {
+                tarjan.visit(function);
+            }
+        }
+        tarjan.res
+    }
+
+    // the longest chain of calls from main, as the functions on it; None if
+    // main reaches a recursive function, so that there is no longest one
+    pub fn max_depth(&self) -> Option<Vec<usize>> {
+        let reached = self.reachable();
+        if self.cycles().iter().flatten().any(|&function| reached[function]) {
+            return None;
+        }
+        // the graph main reaches has no cycles, so the chains can be memoized
+        fn longest(graph: &CallGraph, function: usize, memo: &mut HashMap<usize, Vec<usize>>) -> Vec<usize> {
+            if let Some(chain) = memo.get(&function) {
+                return chain.clone();
+            }
+            let mut res = vec![function];
+            for callee in graph.callees(function) {
+                let chain = longest(graph, callee, memo);
+                if chain.len() + 1 > res.len() {
+                    res = std::iter::once(function).chain(chain).collect();
+                }
+            }
+            memo.insert(function, res.clone());
+            res
+        }
+        Some(longest(self, MAIN, &mut HashMap::new()))
+    }
+
+    // the shortest chain of calls from main to a function, if main reaches it
+    pub fn path_to(&self, function: usize) -> Option<Vec<usize>> {
+        let mut previous: Vec<Option<usize>> = vec![None; self.functions.len()];
+        let mut queue = VecDeque::from([MAIN]);
+        let mut seen = vec![false; self.functions.len()];
+        seen[MAIN] = true;
+        while let Some(current) = queue.pop_front() {
+            if current == function {
+                let mut res = vec![current];
+                while let Some(before) = previous[*res.last().unwrap()] {
+                    res.push(before);
+                }
+                res.reverse();
+                return Some(res);
+            }
+            for callee in self.callees(current) {
+                if !std::mem::replace(&mut seen[callee], true) {
+                    previous[callee] = Some(current);
+                    queue.push_back(callee);
+                }
+            }
+        }
+        None
+    }
+
+    fn names(&self, functions: &[usize], separator: &str) -> String {
+        let names: Vec<&str> = functions.iter().map(|&function| self.functions[function].qualified.as_str()).collect();
+        names.join(separator)
+    }
+
+    // every function with the functions it calls, then the recursion, the
+    // functions main does not reach and the longest chain of calls
+    pub fn report(&self) -> String {
+        let mut res = String::new();
+        for (i, function) in self.functions.iter().enumerate() {
+            res += &function.qualified;
+            if !function.defined {
+                res += " (not defined)";
+            }
+            let callees = self.callees(i);
+            if !callees.is_empty() {
+                res += &format!(" -> {}", self.names(&callees, ", "));
+            }
+            res += "\n";
+        }
+        let cycles = self.cycles();
+        if cycles.is_empty() {
+            res += "recursion: none\n";
+        }
+        for cycle in cycles.iter() {
+            match cycle.len() {
+                1 => res += &format!("direct recursion: {}\n", self.names(cycle, ", ")),
+                _ => res += &format!("mutual recursion: {}\n", self.names(cycle, ", ")),
+            }
+        }
+        let unreachable = self.unreachable();
+        match unreachable.is_empty() {
+            true => res += "unreachable from main: none\n",
+            false => res += &format!("unreachable from main: {}\n", self.names(&unreachable, ", ")),
+        }
+        match self.max_depth() {
+            Some(chain) => {
+                res += &format!("maximum call depth: {} ({})\n", chain.len() - 1, self.names(&chain, " -> "));
+            }
+            None => {
+                // the nearest recursive function main reaches
+                let chain = cycles
+                    .iter()
+                    .flatten()
+                    .filter_map(|&function| self.path_to(function))
+                    .min_by_key(|chain| chain.len())
+                    .unwrap_or_default();
+                res += &format!("maximum call depth: unbounded ({} is recursive)\n", self.names(&chain, " -> "));
+            }
+        }
+        res
+    }
+
+    // a Graphviz digraph: a box per function, dashed if main does not reach
+    // it, and an edge per caller and callee, red between functions that call
+    // each other
+    pub fn dot(&self, title: &str) -> String {
+        let reached = self.reachable();
+        let cycles = self.cycles();
+        let mut res = format!("digraph \"{}\" {{\n", escape(title));
+        res += "    node [shape=box, fontname=\"monospace\"];\n";
+        for (i, function) in self.functions.iter().enumerate() {
+            let mut attributes = format!("label=\"{}\"", escape(&function.qualified));
+            if !reached[i] {
+                attributes += ", style=dashed";
+            }
+            res += &format!("    f{} [{}];\n", i, attributes);
+        }
+        for caller in 0..self.functions.len() {
+            for callee in self.callees(caller) {
+                let recursive = cycles.iter().any(|cycle| cycle.contains(&caller) && cycle.contains(&callee));
+                let attributes = if recursive { " [color=red]" } else { "" };
+                res += &format!("    f{} -> f{}{};\n", caller, callee, attributes);
+            }
+        }
+        res += "}\n";
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::test_support::parse;
+    use crate::resolve::resolve;
+
+    fn graph_of(source: &str) -> CallGraph {
+        let program = parse(source);
+        let (table, _) = resolve("test.x", &program);
+        CallGraph::build(&program, &table)
+    }
+
+    #[test]
+    fn examples() {
+        let graph = graph_of(&std::fs::read_to_string("example2.x").unwrap());
+        assert_eq!(
+            graph.report(),
+            "main -> Foo, Bar\nFoo\nBar -> Bar.BarFoo, Foo\nBar.BarFoo\nrecursion: none\nunreachable from main: none\nmaximum call depth: 2 (main -> Bar -> Bar.BarFoo)\n"
+        );
+        let bar = graph.find("Bar").unwrap();
+        assert_eq!(graph.callers(graph.find("Foo").unwrap()), [MAIN, bar]);
+        assert_eq!(graph.functions[bar].symbols.len(), 2);
+        assert_eq!(graph.functions[bar].span.start_line, 21);
+    }
+
+    #[test]
+    fn recursion() {
+        // fact calls itself, even and odd call each other, and main never
+        // reaches lonely or its nested helper
+        let source = "int fact(int n);\nint odd(int n);\nvoid main(){\nint x;\nx = fact(5) + odd(3);\n}\nint fact(int n) { return n * fact(n - 1); }\nint even(int n) { return odd(n - 1); }\nint odd(int n) { return even(n - 1); }\nint lonely(int n) {\nint helper(int k);\nreturn helper(n);\nint helper(int k) { return k; }\n}\n";
+        let graph = graph_of(source);
+        let names = |functions: &[usize]| functions.iter().map(|&f| graph.functions[f].qualified.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&graph.cycles().concat()), ["fact", "odd", "even"]);
+        assert_eq!(graph.cycles().len(), 2);
+        assert_eq!(names(&graph.unreachable()), ["lonely", "lonely.helper"]);
+        assert!(graph.is_recursive(graph.find("even").unwrap()));
+        assert!(!graph.is_recursive(MAIN));
+        assert_eq!(graph.max_depth(), None);
+        assert!(graph.report().ends_with(
+            "direct recursion: fact\nmutual recursion: odd, even\nunreachable from main: lonely, lonely.helper\nmaximum call depth: unbounded (main -> fact is recursive)\n"
+        ));
+
+        let dot = graph.dot("test.x");
+        assert!(dot.starts_with("digraph \"test.x\" {\n    node [shape=box, fontname=\"monospace\"];\n    f0 [label=\"main\"];\n"));
+        assert!(dot.contains("[label=\"lonely.helper\", style=dashed];"));
+        assert!(dot.contains("    f1 -> f1 [color=red];\n"));
+    }
+}
@@ -0,0 +1,404 @@
+//Control-flow graphs: one per function, main and nested functions included,
+//made of basic blocks of straight-line statements, the declarations with an
+//initializer of a block first, as they run when it is entered. An if or
+//while condition ends a block with a true and a false edge, except that a
+//while whose condition is a constant that is true never takes its false
+//edge; the body of a while goes back to its condition, and a return goes to
+//the exit block. Building the graph finds the functions whose body can end
+//without a return and the code that follows a return; dot() writes the
+//graphs for Graphviz.
+
+use crate::ast::{Block, DataType, Declaration, Expression, FunctionDefinition, Program, Statement, StatementKind, VariableDeclaration};
+use crate::consteval::value;
+use crate::diagnostics::{Diagnostic, Related, Severity, Span};
+use crate::printer::expression_source;
+
+pub const ENTRY: usize = 0;
+pub const EXIT: usize = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Always,
+    True,
+    False,
+}
+
+// what a basic block runs
+#[derive(Clone, Copy, Debug)]
+pub enum Item<'a> {
+    // a variable declaration with an initializer
+    Declaration(&'a VariableDeclaration),
+    // an expression or return statement
+    Statement(&'a Statement),
+}
+
+#[derive(Debug, Default)]
+pub struct BasicBlock<'a> {
+    // initialized declarations and expression and return statements, in order
+    pub statements: Vec<Item<'a>>,
+    // the if or while condition that ends the block
+    pub condition: Option<&'a Expression>,
+    pub successors: Vec<(usize, Edge)>,
+}
+
+#[derive(Debug)]
+pub struct Cfg<'a> {
+    pub name: String,
+    // None for main
+    pub return_type: Option<DataType>,
+    // the span of the function name, or of the whole of main
+    pub span: Span,
+    // ENTRY and EXIT come first
+    pub blocks: Vec<BasicBlock<'a>>,
+    // the end of the body, if a path reaches it without a return
+    pub falls_off: Option<Span>,
+    // the first statement or nested function after a return
+    pub unreachable: Vec<Unreachable>,
+}
+
+#[derive(Debug)]
+pub struct Unreachable {
+    // the statement, or the name of the function
+    pub span: Span,
+    // the name of a nested function defined after the return
+    pub function: Option<String>,
+    // the return
+    pub after: Span,
+}
+
+struct Builder<'a> {
+    blocks: Vec<BasicBlock<'a>>,
+    unreachable: Vec<Unreachable>,
+}
+
+// the span of the closing brace of a block
+fn closing_brace(block: &Block) -> Span {
+    Span {
+        start_line: block.span.end_line,
+        start_char: block.span.end_char - 1,
+        ..block.span
+    }
+}
+
+impl<'a> Builder<'a> {
+    fn new_block(&mut self) -> usize {
+        self.blocks.push(BasicBlock::default());
+        self.blocks.len() - 1
+    }
+
+    fn edge(&mut self, from: usize, to: usize, edge: Edge) {
+        self.blocks[from].successors.push((to, edge));
+    }
+
+    // the statements of a block from the basic block current, None where
+    // the code cannot be reached; returns the basic block the statements
+    // end in
+    fn block(&mut self, block: &'a Block, mut current: Option<usize>) -> Option<usize> {
+        // the declarations come before any statement, so current is a block
+        for declaration in block.declarations.iter() {
+            if let (Declaration::Variable(variable), Some(start)) = (declaration, current) {
+                if variable.initializer.is_some() {
+                    self.blocks[start].statements.push(Item::Declaration(variable));
+                }
+            }
+        }
+        // the return that ended the path, until the code after it is reported
+        let mut returned: Option<Span> = None;
+        for statement in block.statements.iter() {
+            let start = match current {
+                Some(start) => start,
+                None => {
+                    if let Some(after) = returned.take() {
+                        self.unreachable.push(Unreachable {
+                            span: statement.span,
+                            function: None,
+                            after,
+                        });
+                    }
+                    self.new_block()
+                }
+            };
+            current = match &statement.kind {
+                StatementKind::Expression(_) => {
+                    self.blocks[start].statements.push(Item::Statement(statement));
+                    Some(start)
+                }
+                StatementKind::Return(_) => {
+                    self.blocks[start].statements.push(Item::Statement(statement));
+                    self.edge(start, EXIT, Edge::Always);
+                    returned = Some(statement.span);
+                    None
+                }
+                StatementKind::If(condition, body) => {
+                    self.blocks[start].condition = Some(condition);
+                    let (then, after) = (self.new_block(), self.new_block());
+                    self.edge(start, then, Edge::True);
+                    self.edge(start, after, Edge::False);
+                    if let Some(end) = self.block(body, Some(then)) {
+                        self.edge(end, after, Edge::Always);
+                    }
+                    Some(after)
+                }
+                StatementKind::While(condition, body) => {
+                    let header = if self.blocks[start].statements.is_empty() && start != ENTRY {
+                        start
+                    } else {
+                        let header = self.new_block();
+                        self.edge(start, header, Edge::Always);
+                        header
+                    };
+                    self.blocks[header].condition = Some(condition);
+                    let (body_start, after) = (self.new_block(), self.new_block());
+                    self.edge(header, body_start, Edge::True);
+                    if !value(condition).is_some_and(|condition| condition.is_true()) {
+                        self.edge(header, after, Edge::False);
+                    }
+                    if let Some(end) = self.block(body, Some(body_start)) {
+                        self.edge(end, header, Edge::Always);
+                    }
+                    Some(after)
+                }
+            };
+        }
+        if let (Some(after), Some(function)) = (returned, block.functions.first()) {
+            self.unreachable.push(Unreachable {
+                span: Span::of_token(&function.name),
+                function: Some(function.name.text.clone()),
+                after,
+            });
+        }
+        current
+    }
+}
+
+fn build<'a>(name: &str, return_type: Option<DataType>, span: Span, block: &'a Block) -> Cfg<'a> {
+    let mut builder = Builder {
+        blocks: Vec::new(),
+        unreachable: Vec::new(),
+    };
+    builder.new_block();
+    builder.new_block();
+    let end = builder.block(block, Some(ENTRY));
+    if let Some(end) = end {
+        builder.edge(end, EXIT, Edge::Always);
+    }
+    let mut cfg = Cfg {
+        name: name.to_string(),
+        return_type,
+        span,
+        blocks: builder.blocks,
+        falls_off: None,
+        unreachable: builder.unreachable,
+    };
+    // the end after code that follows a return is not reached either
+    if end.is_some_and(|end| cfg.reachable()[end]) {
+        cfg.falls_off = Some(closing_brace(block));
+    }
+    cfg
+}
+
+fn functions<'a>(functions: &'a [FunctionDefinition], res: &mut Vec<Cfg<'a>>) {
+    for function in functions.iter() {
+        let span = Span::of_token(&function.name);
+        res.push(build(&function.name.text, Some(function.return_type), span, &function.block));
+        nested(&function.block, res);
+    }
+}
+
+// the functions defined in a block and the blocks in it
+fn nested<'a>(block: &'a Block, res: &mut Vec<Cfg<'a>>) {
+    for statement in block.statements.iter() {
+        if let StatementKind::While(_, body) | StatementKind::If(_, body) = &statement.kind {
+            nested(body, res);
+        }
+    }
+    functions(&block.functions, res);
+}
+
+// the graphs of main and of every function, in source order
+pub fn graphs(program: &Program) -> Vec<Cfg<'_>> {
+    let mut res = vec![build("main", None, program.main.span, &program.main.block)];
+    nested(&program.main.block, &mut res);
+    functions(&program.functions, &mut res);
+    res
+}
+
+// the functions that can end without returning a value and the code after
+// a return, sorted by position
+pub fn check_control_flow(xfilename: &str, program: &Program) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let diagnostic = |severity, code, message, span, related| Diagnostic {
+        severity,
+        code,
+        message,
+        file: xfilename.to_string(),
+        span,
+        related,
+    };
+    for cfg in graphs(program).iter() {
+        if let (Some(return_type), Some(end)) = (cfg.return_type, cfg.falls_off) {
+            let related = vec![Related {
+                span: end,
+                message: "the end of the body is reached here without a return".to_string(),
+            }];
+            let message = format!("'{}' returns {}, but a path through it does not return a value.", cfg.name, return_type);
+            diagnostics.push(diagnostic(Severity::Error, "X0401", message, cfg.span, related));
+        }
+        for unreachable in cfg.unreachable.iter() {
+            let related = vec![Related {
+                span: unreachable.after,
+                message: "the block returns here".to_string(),
+            }];
+            let message = match &unreachable.function {
+                Some(name) => format!("'{}' is defined after the return that ends its block; it only runs when it is called.", name),
+                None => "This code follows a return, so it is never reached.".to_string(),
+            };
+            diagnostics.push(diagnostic(Severity::Warning, "X0402", message, unreachable.span, related));
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.span.start_line, d.span.start_char));
+    diagnostics
+}
+
+// a string in a DOT label or name
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn item_label(item: &Item) -> String {
+    let statement = match item {
+        Item::Declaration(variable) => {
+            let initializer = variable.initializer.as_ref().map(expression_source).unwrap_or_default();
+            return format!("{} {} = {};", variable.data_type, variable.name.text, initializer);
+        }
+        Item::Statement(statement) => statement,
+    };
+    match &statement.kind {
+        StatementKind::Return(value) => format!("return {};", expression_source(value)),
+        StatementKind::Expression(expression) => format!("{};", expression_source(expression)),
+        StatementKind::While(..) | StatementKind::If(..) => String::new(),
+    }
+}
+
+impl Cfg<'_> {
+    // which blocks a path from the entry reaches
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reached = vec![false; self.blocks.len()];
+        let mut work = vec![ENTRY];
+        while let Some(index) = work.pop() {
+            if !std::mem::replace(&mut reached[index], true) {
+                work.extend(self.blocks[index].successors.iter().map(|&(to, _)| to));
+            }
+        }
+        reached
+    }
+
+    // the label of a basic block: its number or role, its statements and
+    // its condition, left aligned
+    fn label(&self, index: usize) -> String {
+        let mut lines = vec![match index {
+            ENTRY => "entry".to_string(),
+            EXIT => "exit".to_string(),
+            index => format!("B{}", index),
+        }];
+        let block = &self.blocks[index];
+        lines.extend(block.statements.iter().map(item_label));
+        if let Some(condition) = block.condition {
+            lines.push(format!("({})?", expression_source(condition)));
+        }
+        lines.iter().map(|line| escape(line) + "\\l").collect()
+    }
+}
+
+// the graphs as one Graphviz digraph, a cluster per function
+pub fn dot(graphs: &[Cfg], title: &str) -> String {
+    let mut res = format!("digraph \"{}\" {{\n", escape(title));
+    res += "    node [shape=box, fontname=\"monospace\"];\n";
+    for (i, cfg) in graphs.iter().enumerate() {
+        res += &format!("    subgraph cluster_{} {{\n", i);
+        res += &format!("        label=\"{}\";\n", escape(&cfg.name));
+        for index in 0..cfg.blocks.len() {
+            res += &format!("        f{}_b{} [label=\"{}\"];\n", i, index, cfg.label(index));
+        }
+        for (index, block) in cfg.blocks.iter().enumerate() {
+            for &(to, edge) in block.successors.iter() {
+                let attributes = match edge {
+                    Edge::Always => String::new(),
+                    Edge::True => " [label=\"true\"]".to_string(),
+                    Edge::False => " [label=\"false\"]".to_string(),
+                };
+                res += &format!("        f{}_b{} -> f{}_b{}{};\n", i, index, i, to, attributes);
+            }
+        }
+        res += "    }\n";
+    }
+    res += "}\n";
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::test_support::{codes, parse};
+
+    // the successors of every basic block
+    fn edges(cfg: &Cfg) -> Vec<Vec<(usize, Edge)>> {
+        cfg.blocks.iter().map(|block| block.successors.clone()).collect()
+    }
+
+    #[test]
+    fn graphs_of_examples() {
+        let program = parse(&std::fs::read_to_string("example1.x").unwrap());
+        let cfgs = graphs(&program);
+        let names: Vec<&str> = cfgs.iter().map(|cfg| cfg.name.as_str()).collect();
+        assert_eq!(names, ["main", "Foo"]);
+        // Foo: entry -> loop condition -> body -> condition, and the return
+        // after the loop
+        use Edge::*;
+        assert_eq!(
+            edges(&cfgs[1]),
+            [vec![(2, Always)], vec![], vec![(3, True), (4, False)], vec![(2, Always)], vec![(EXIT, Always)]]
+        );
+        assert_eq!(check_control_flow("test.x", &program), Vec::new());
+
+        // BarFoo is defined after the return of Bar
+        let program = parse(&std::fs::read_to_string("example2.x").unwrap());
+        let names: Vec<String> = graphs(&program).into_iter().map(|cfg| cfg.name).collect();
+        assert_eq!(names, ["main", "Foo", "Bar", "BarFoo"]);
+        let diagnostics = check_control_flow("test.x", &program);
+        assert_eq!(codes(&diagnostics), [("X0402", 35, 10)]);
+        assert_eq!(diagnostics[0].related[0].span.start_line, 33);
+    }
+
+    #[test]
+    fn missing_returns() {
+        // f returns only inside the if, g has code after its return but
+        // cannot reach its end, h returns in a loop that may not run, main
+        // needs no return
+        let source = "void main(){\n}\nint f(int a) {\nif (a) {\nreturn 1;\n}\n}\nint g(int a) {\nreturn a;\na = 1;\n}\nint h(int a) {\nwhile (a) {\nreturn a;\n}\n}\n";
+        let diagnostics = check_control_flow("test.x", &parse(source));
+        assert_eq!(codes(&diagnostics), [("X0401", 2, 4), ("X0402", 9, 0), ("X0401", 11, 4)]);
+
+        // a loop whose condition is always true only ends with the return in it
+        let source = "void main(){\n}\nint a(int n){ while (1) { if (n) { return 1; } } }\nint b(int n){ while (2 > 1) { n = n + 1; } }\n";
+        assert_eq!(check_control_flow("test.x", &parse(source)), Vec::new());
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "test.x:2:4-2:5: error[X0401]: 'f' returns int, but a path through it does not return a value.\ntest.x:6:0-6:1: note: the end of the body is reached here without a return"
+        );
+    }
+
+    #[test]
+    fn dot_output() {
+        let program = parse("int a(int n);\nvoid main(){\nint x = 0;\nwhile (x < 3) {\nint y = a(x);\nint z;\nx = x + y;\n}\n}\n");
+        let text = dot(&graphs(&program), "test.x");
+        assert!(text.starts_with("digraph \"test.x\" {\n"));
+        assert!(text.contains("label=\"main\";"));
+        // declarations with an initializer run like assignments, the others not at all
+        assert!(text.contains("f0_b0 [label=\"entry\\lint x = 0;\\l\"];"));
+        assert!(text.contains("f0_b2 [label=\"B2\\l(x < 3)?\\l\"];"));
+        assert!(text.contains("f0_b3 [label=\"B3\\lint y = a(x);\\lx = x + y;\\l\"];"));
+        assert!(text.contains("f0_b2 -> f0_b4 [label=\"false\"];"));
+        assert!(text.ends_with("    }\n}\n"));
+    }
+}
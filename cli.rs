@@ -0,0 +1,782 @@
+//The subcommands of the command line. lex, parse, highlight, check, lint,
+//fmt, cfg, calls, run, interpret, trace and coverage take one or more .x
+//files or glob patterns (for shells that do not expand them, e.g.
+//"tests/**/*.x"); the grammar and testing tools after them take their own
+//arguments. Every subcommand returns the exit code: 0 when every file is
+//fine, 1 when a file has errors (or is not formatted, for fmt --check) and
+//2 for a bad command line or a file that cannot be read or written.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::ast::DataType;
+use crate::callgraph::CallGraph;
+use crate::cfg::{dot, graphs};
+use crate::codegen::generate_file;
+use crate::consteval::{Constant, Value};
+use crate::coverage::Coverage;
+use crate::diagnostics::{check_source, read_source, to_json_lines, to_sarif, Diagnostic, Severity};
+use crate::fuzz;
+use crate::generator::generate;
+use crate::golden;
+use crate::grammar::{self, Grammar};
+use crate::grammar_analysis::analyze;
+use crate::interpreter::run_source;
+use crate::lint::{lint_source, Config};
+use crate::minimize::{command_succeeds, minimize, pipeline_panics, reports};
+use crate::printer::program_source;
+use crate::railroad::write_diagrams;
+use crate::resolve::resolve;
+use crate::stage2_scanner::Scanner;
+use crate::stage3::{bench, Parser};
+use crate::trace::Trace;
+use crate::xhtml_out::XHTML;
+
+pub const SUBCOMMANDS: [&str; 20] = [
+    "lex",
+    "parse",
+    "highlight",
+    "check",
+    "lint",
+    "fmt",
+    "cfg",
+    "calls",
+    "run",
+    "interpret",
+    "trace",
+    "coverage",
+    "gen-parser",
+    "analyze-grammar",
+    "railroad",
+    "generate",
+    "minimize",
+    "fuzz",
+    "golden",
+    "bench",
+];
+
+pub const USAGE: &str = "usage: proj2zhang <command> [options] <file.x or glob>...
+commands:
+  lex                               print the tokens with their positions
+  parse [--ast]                     check the syntax, or print the AST
+  highlight [--html] [-o path]      write name.xhtml next to each file, or to path
+                                    (a directory for several files, - for stdout)
+  check [--format text|json|sarif]  print all diagnostics
+  lint [--config path] [--format text|json|sarif]
+                                    print the findings of the lint rules, at the
+                                    levels of the config file (rule = level lines)
+  fmt [--check | --write]           print the formatted program, check that the
+                                    files are formatted, or format them in place
+  cfg                               print the control-flow graph of every function
+                                    as a Graphviz digraph
+  calls [--dot]                     print the call graph with its recursion, the
+                                    functions main does not reach and the deepest
+                                    chain of calls, or the graph for Graphviz
+  run [--input path] [--vars]       run main, reading the file or standard input,
+                                    then print the globals and the variables of
+                                    main with --vars; or print the runtime error
+  interpret --grammar path          parse with a grammar file loaded at runtime
+  trace [--format text|json] [--step]
+                                    print every rule the parser enters and leaves,
+                                    or stop at each one with --step
+  coverage [--format text|html]     print the grammar rules and alternatives the
+                                    files exercise
+grammar and testing tools:
+  gen-parser <grammar.ebnf> <out.rs>        write a Rust parser for the grammar
+  analyze-grammar <grammar.ebnf>            print FIRST/FOLLOW sets and conflicts
+  railroad <grammar.ebnf> <out_dir>         write a railroad diagram per rule
+  generate <seed> <max_depth> [--semantic]  print a random program
+  minimize <file.x> panic | diagnostic <text> | command <cmd> [args]...
+                                            print the smallest program that still
+                                            panics, reports text or passes cmd
+  fuzz <seed> <iterations>                  print a minimized input that panics
+  golden [--bless]                          compare tests/**/*.x with their golden
+                                            files, or write them
+  bench                                     time the parser on nested programs
+exit codes: 0 ok, 1 errors found, 2 bad command line or unreadable file";
+
+// the options of a subcommand: the flags that are set, the value of -o,
+// --format, --config, --input or --grammar, and the input patterns
+#[derive(Debug, Default)]
+struct Options {
+    flags: Vec<String>,
+    output: Option<String>,
+    format: Option<String>,
+    config: Option<String>,
+    input: Option<String>,
+    grammar: Option<String>,
+    inputs: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String], flags: &[&str], values: &[&str]) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut i = 0;
+        while i < args.len() {
+            let arg = &args[i];
+            if values.contains(&arg.as_str()) {
+                let value = args.get(i + 1).ok_or(format!("{} needs a value", arg))?.clone();
+                match arg.as_str() {
+                    "-o" => options.output = Some(value),
+                    "--config" => options.config = Some(value),
+                    "--input" => options.input = Some(value),
+                    "--grammar" => options.grammar = Some(value),
+                    _ => options.format = Some(value),
+                }
+                i += 2;
+                continue;
+            }
+            if flags.contains(&arg.as_str()) {
+                options.flags.push(arg.clone());
+            } else if arg.starts_with('-') && arg != "-" {
+                return Err(format!("unknown option '{}'", arg));
+            } else {
+                options.inputs.push(arg.clone());
+            }
+            i += 1;
+        }
+        if options.inputs.is_empty() {
+            return Err("no input files".to_string());
+        }
+        Ok(options)
+    }
+
+    fn has(&self, flag: &str) -> bool {
+        self.flags.iter().any(|f| f == flag)
+    }
+}
+
+// whether a file name matches a pattern of one path component with * and ?
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|i| matches(&pattern[1..], &name[i..])),
+        Some('?') => !name.is_empty() && matches(&pattern[1..], &name[1..]),
+        Some(c) => name.first() == Some(c) && matches(&pattern[1..], &name[1..]),
+    }
+}
+
+// the paths under dir that match the remaining components of a glob, where
+// ** is any number of directories
+fn expand_components(dir: &Path, components: &[&str], res: &mut Vec<PathBuf>) {
+    let Some((first, rest)) = components.split_first() else {
+        res.push(dir.to_path_buf());
+        return;
+    };
+    if *first == "**" {
+        expand_components(dir, rest, res);
+    }
+    if !first.contains(['*', '?']) {
+        let path = dir.join(first);
+        if path.exists() {
+            expand_components(&path, rest, res);
+        }
+        return;
+    }
+    let Ok(entries) = fs::read_dir(if dir.as_os_str().is_empty() { Path::new(".") } else { dir }) else {
+        return;
+    };
+    let pattern: Vec<char> = first.chars().collect();
+    let mut names: Vec<String> = entries.flatten().map(|entry| entry.file_name().to_string_lossy().to_string()).collect();
+    names.sort();
+    for name in names {
+        // like shells, * does not match hidden files
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = dir.join(&name);
+        if *first == "**" {
+            if path.is_dir() {
+                expand_components(&path, components, res);
+            }
+        } else if matches(&pattern, &name.chars().collect::<Vec<_>>()) {
+            expand_components(&path, rest, res);
+        }
+    }
+}
+
+// the files a pattern names: itself if it has no * or ?, otherwise the
+// matching files in sorted order
+pub fn expand(pattern: &str) -> Vec<PathBuf> {
+    if !pattern.contains(['*', '?']) {
+        return vec![PathBuf::from(pattern)];
+    }
+    let mut res = Vec::new();
+    let (root, relative) = match pattern.strip_prefix('/') {
+        Some(relative) => (Path::new("/"), relative),
+        None => (Path::new(""), pattern),
+    };
+    let components: Vec<&str> = relative.split('/').filter(|component| !component.is_empty()).collect();
+    expand_components(root, &components, &mut res);
+    res.retain(|path| path.is_file());
+    res.dedup();
+    res
+}
+
+// the input files of all patterns, or the first pattern that matches nothing
+fn input_files(patterns: &[String]) -> Result<Vec<String>, String> {
+    let mut res = Vec::new();
+    for pattern in patterns.iter() {
+        let files = expand(pattern);
+        if files.is_empty() {
+            return Err(format!("no files match '{}'", pattern));
+        }
+        res.extend(files.iter().map(|path| path.display().to_string()));
+    }
+    Ok(res)
+}
+
+// the diagnostic of a syntax error, or the program
+fn parse_source(xfilename: &str, source: &str) -> Result<crate::ast::Program, Diagnostic> {
+    let tokens = Scanner::from_source(xfilename, source).run().all_tokens.clone();
+    let mut parser = Parser::from_tokens(tokens);
+    parser
+        .parse()
+        .map_err(|e| Diagnostic::from_syntax_error(xfilename, &parser.all_tokens, &e))
+}
+
+fn lex(xfilename: &str, source: &str, out: &mut dyn Write) -> std::io::Result<i32> {
+    for token in Scanner::from_source(xfilename, source).run().all_tokens.iter() {
+        writeln!(out, "{}:{}:{} {:?} {}", xfilename, token.line_num, token.char_pos, token.token_type, token.text)?;
+    }
+    Ok(0)
+}
+
+fn parse(xfilename: &str, source: &str, options: &Options, out: &mut dyn Write, err: &mut dyn Write) -> std::io::Result<i32> {
+    match parse_source(xfilename, source) {
+        Ok(program) if options.has("--ast") => write!(out, "{}", program.dump())?,
+        Ok(_) => writeln!(out, "{}: Input program is syntactically correct.", xfilename)?,
+        Err(diagnostic) => {
+            writeln!(err, "{}", diagnostic)?;
+            return Ok(1);
+        }
+    }
+    Ok(0)
+}
+
+// name.xhtml next to the input, or in the directory given with -o; HTML5
+// with --html or for an output file named .html
+fn highlight(
+    xfilename: &str,
+    source: &str,
+    options: &Options,
+    several: bool,
+    out: &mut dyn Write,
+) -> std::io::Result<i32> {
+    let extension = if options.has("--html") { "html" } else { "xhtml" };
+    let output_file = match options.output.as_deref() {
+        Some("-") => None,
+        Some(dir) if several => {
+            fs::create_dir_all(dir)?;
+            let name = Path::new(xfilename).with_extension(extension);
+            Some(Path::new(dir).join(name.file_name().unwrap()))
+        }
+        Some(file) => Some(PathBuf::from(file)),
+        None => Some(Path::new(xfilename).with_extension(extension)),
+    };
+    let html = options.has("--html") || output_file.as_ref().is_some_and(|file| file.extension().is_some_and(|e| e == "html"));
+    let mut xhtml = XHTML::from_source(xfilename, source);
+    let res = if html { xhtml.render_html() } else { xhtml.render() };
+    match output_file {
+        Some(file) => fs::write(file, res)?,
+        None => write!(out, "{}", res)?,
+    }
+    Ok(0)
+}
+
+fn fmt(xfilename: &str, source: &str, options: &Options, out: &mut dyn Write, err: &mut dyn Write) -> std::io::Result<i32> {
+    let program = match parse_source(xfilename, source) {
+        Ok(program) => program,
+        Err(diagnostic) => {
+            writeln!(err, "{}", diagnostic)?;
+            return Ok(1);
+        }
+    };
+    let formatted = program_source(&program) + "\n";
+    // the printer works on the AST, which has no comments
+    let has_comments = !Scanner::from_source(xfilename, source).run().comments.is_empty();
+    if has_comments && (options.has("--check") || options.has("--write")) {
+        writeln!(err, "{}: has comments, which fmt does not keep", xfilename)?;
+        return Ok(1);
+    }
+    if options.has("--check") {
+        if formatted != source {
+            writeln!(err, "{}: not formatted", xfilename)?;
+            return Ok(1);
+        }
+    } else if options.has("--write") {
+        if formatted != source {
+            fs::write(xfilename, formatted)?;
+        }
+    } else {
+        write!(out, "{}", formatted)?;
+    }
+    Ok(0)
+}
+
+fn cfg(xfilename: &str, source: &str, out: &mut dyn Write, err: &mut dyn Write) -> std::io::Result<i32> {
+    match parse_source(xfilename, source) {
+        Ok(program) => write!(out, "{}", dot(&graphs(&program), xfilename))?,
+        Err(diagnostic) => {
+            writeln!(err, "{}", diagnostic)?;
+            return Ok(1);
+        }
+    }
+    Ok(0)
+}
+
+fn calls(xfilename: &str, source: &str, options: &Options, out: &mut dyn Write, err: &mut dyn Write) -> std::io::Result<i32> {
+    let program = match parse_source(xfilename, source) {
+        Ok(program) => program,
+        Err(diagnostic) => {
+            writeln!(err, "{}", diagnostic)?;
+            return Ok(1);
+        }
+    };
+    let (table, _) = resolve(xfilename, &program);
+    let graph = CallGraph::build(&program, &table);
+    if options.has("--dot") {
+        write!(out, "{}", graph.dot(xfilename))?;
+    } else {
+        write!(out, "{}:\n{}", xfilename, graph.report())?;
+    }
+    Ok(0)
+}
+
+// a value as the program holds it: a float with the digits of a float
+fn value_text(value: &Constant) -> String {
+    match (value.data_type, value.value) {
+        (DataType::Float, Value::Floating(floating)) if floating.is_finite() => format!("{:?}", floating as f32),
+        _ => value.to_string(),
+    }
+}
+
+// what the program writes, then the variables with --vars, or the errors
+// what the program writes goes to out as it runs
+fn run_program(
+    xfilename: &str,
+    source: &str,
+    options: &Options,
+    input: &mut (dyn BufRead + Send),
+    out: &mut (dyn Write + Send),
+    err: &mut dyn Write,
+) -> std::io::Result<i32> {
+    match run_source(xfilename, source, input, out) {
+        Ok(outcome) => {
+            if options.has("--vars") {
+                writeln!(out, "{}:", xfilename)?;
+                for (name, value) in outcome.globals.iter() {
+                    writeln!(out, "{} = {}", name, value_text(value))?;
+                }
+                for (name, value) in outcome.main.iter() {
+                    let text = value.as_ref().map_or("unassigned".to_string(), value_text);
+                    writeln!(out, "main: {} = {}", name, text)?;
+                }
+            }
+            Ok(0)
+        }
+        Err(diagnostics) => {
+            for diagnostic in diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error) {
+                writeln!(err, "{}", diagnostic)?;
+            }
+            Ok(1)
+        }
+    }
+}
+
+// the parse trace of one file; every rule with --step, which waits for a
+// key after each
+fn trace(xfilename: &str, source: &str, options: &Options, out: &mut dyn Write, err: &mut dyn Write) -> std::io::Result<i32> {
+    let mut parser = Parser::from_tokens(Scanner::from_source(xfilename, source).run().all_tokens.clone());
+    parser.trace = Some(Trace::new(options.has("--step")));
+    let res = parser.parse();
+    let trace = parser.trace.as_ref().unwrap();
+    if options.format.as_deref() == Some("json") {
+        write!(out, "{}", trace.to_json(&parser.all_tokens))?;
+    } else {
+        write!(out, "{}", trace.to_text(&parser.all_tokens))?;
+    }
+    if let Err(e) = res {
+        writeln!(err, "{}: {}", xfilename, e)?;
+        return Ok(1);
+    }
+    Ok(0)
+}
+
+fn load_grammar(path: &str, err: &mut dyn Write) -> Option<Grammar> {
+    match Grammar::from_file(path) {
+        Ok(loaded) => Some(loaded),
+        Err(e) => {
+            let _ = writeln!(err, "{}", e);
+            None
+        }
+    }
+}
+
+// the subcommands that do not take .x files: None for any other name
+fn tool(args: &[String], out: &mut dyn Write, err: &mut dyn Write) -> Option<i32> {
+    let rest: Vec<&str> = args[1..].iter().map(|arg| arg.as_str()).collect();
+    let numbers = |seed: &str, n: &str| seed.parse::<u64>().ok().zip(n.parse::<usize>().ok());
+    let res = match (args[0].as_str(), rest.as_slice()) {
+        ("gen-parser", [grammar_path, out_path]) => match generate_file(grammar_path, out_path) {
+            Ok(()) => Ok(0),
+            Err(e) => writeln!(err, "{}", e).map(|_| 2),
+        },
+        ("analyze-grammar", [grammar_path]) => match load_grammar(grammar_path, err) {
+            Some(loaded) => write!(out, "{}", analyze(&loaded).report()).map(|_| 0),
+            None => Ok(2),
+        },
+        ("railroad", [grammar_path, out_dir]) => match load_grammar(grammar_path, err) {
+            Some(loaded) => match write_diagrams(&loaded, out_dir) {
+                Ok(()) => Ok(0),
+                Err(e) => writeln!(err, "{}: {}", out_dir, e).map(|_| 2),
+            },
+            None => Ok(2),
+        },
+        ("generate", [seed, max_depth] | [seed, max_depth, "--semantic"]) => match numbers(seed, max_depth) {
+            Some((seed, max_depth)) => writeln!(out, "{}", generate(seed, max_depth, rest.len() == 3)).map(|_| 0),
+            None => writeln!(err, "{}", USAGE).map(|_| 2),
+        },
+        ("minimize", [xfilename, predicate, arguments @ ..]) => {
+            let source = match read_source(xfilename) {
+                Ok(source) => source,
+                Err(e) => return Some(writeln!(err, "{}: {}", xfilename, e).map_or(2, |_| 2)),
+            };
+            let command: Vec<String> = arguments.iter().map(|arg| arg.to_string()).collect();
+            let mut interesting: Box<dyn FnMut(&str) -> bool> = match (*predicate, arguments) {
+                ("panic", []) => Box::new(pipeline_panics),
+                ("diagnostic", [text]) => Box::new(move |source: &str| reports(source, text)),
+                ("command", [_, ..]) => Box::new(|source: &str| command_succeeds(&command, source)),
+                _ => return Some(writeln!(err, "{}", USAGE).map_or(2, |_| 2)),
+            };
+            match minimize(&source, &mut *interesting) {
+                Some(minimal) => writeln!(out, "{}", minimal).map(|_| 0),
+                None => writeln!(err, "{}: the predicate does not hold for the input", xfilename).map(|_| 1),
+            }
+        }
+        ("fuzz", [seed, iterations]) => match numbers(seed, iterations) {
+            Some((seed, iterations)) => match fuzz::fuzz(seed, iterations) {
+                Some(input) => {
+                    let source = String::from_utf8_lossy(&input);
+                    let minimal = minimize(&source, &mut pipeline_panics).unwrap_or(source.to_string());
+                    writeln!(out, "{}", minimal).map(|_| 1)
+                }
+                None => writeln!(out, "no panics in {} inputs", iterations).map(|_| 0),
+            },
+            None => writeln!(err, "{}", USAGE).map(|_| 2),
+        },
+        ("golden", [] | ["--bless"]) => {
+            let mismatches = golden::run(golden::TESTS_DIR, !rest.is_empty());
+            for mismatch in mismatches.iter() {
+                let _ = writeln!(err, "{}", mismatch);
+            }
+            Ok(if mismatches.is_empty() { 0 } else { 1 })
+        }
+        ("bench", []) => bench(out).map(|failures| {
+            for failure in failures.iter() {
+                let _ = writeln!(err, "{}", failure);
+            }
+            if failures.is_empty() { 0 } else { 1 }
+        }),
+        ("gen-parser" | "analyze-grammar" | "railroad" | "generate" | "minimize" | "fuzz" | "golden" | "bench", _) => {
+            writeln!(err, "{}", USAGE).map(|_| 2)
+        }
+        _ => return None,
+    };
+    Some(res.unwrap_or(2))
+}
+
+// runs a subcommand, args[0] is its name; out is Send because run writes
+// to it from the thread that runs the program
+pub fn run(args: &[String], out: &mut (dyn Write + Send), err: &mut dyn Write) -> i32 {
+    if args.is_empty() {
+        let _ = writeln!(err, "{}", USAGE);
+        return 2;
+    }
+    if let Some(code) = tool(args, out, err) {
+        return code;
+    }
+    let (flags, values, formats): (&[&str], &[&str], &[&str]) = match args[0].as_str() {
+        "lex" => (&[], &[], &[]),
+        "parse" => (&["--ast"], &[], &[]),
+        "highlight" => (&["--html"], &["-o"], &[]),
+        "check" => (&[], &["--format"], &["text", "json", "sarif"]),
+        "lint" => (&[], &["--format", "--config"], &["text", "json", "sarif"]),
+        "fmt" => (&["--check", "--write"], &[], &[]),
+        "cfg" => (&[], &[], &[]),
+        "calls" => (&["--dot"], &[], &[]),
+        "run" => (&["--vars"], &["--input"], &[]),
+        "interpret" => (&[], &["--grammar"], &[]),
+        "trace" => (&["--step"], &["--format"], &["text", "json"]),
+        "coverage" => (&[], &["--format"], &["text", "html"]),
+        _ => {
+            let _ = writeln!(err, "{}", USAGE);
+            return 2;
+        }
+    };
+    let options = match Options::parse(&args[1..], flags, values) {
+        Ok(options) if options.has("--check") && options.has("--write") => {
+            let _ = writeln!(err, "--check and --write cannot be used together\n{}", USAGE);
+            return 2;
+        }
+        Ok(options) => options,
+        Err(e) => {
+            let _ = writeln!(err, "{}\n{}", e, USAGE);
+            return 2;
+        }
+    };
+    let format = options.format.as_deref().unwrap_or("text");
+    if !formats.is_empty() && !formats.contains(&format) {
+        let (last, others) = formats.split_last().unwrap();
+        let _ = writeln!(err, "unknown output format '{}', expected {} or {}", format, others.join(", "), last);
+        return 2;
+    }
+    let loaded = match options.grammar.as_deref() {
+        Some(path) => match load_grammar(path, err) {
+            Some(loaded) => Some(loaded),
+            None => return 2,
+        },
+        None if args[0] == "interpret" => {
+            let _ = writeln!(err, "interpret needs --grammar\n{}", USAGE);
+            return 2;
+        }
+        None => None,
+    };
+    let config = match options.config.as_deref().map(Config::from_file) {
+        Some(Ok(config)) => config,
+        Some(Err(e)) => {
+            let _ = writeln!(err, "{}", e);
+            return 2;
+        }
+        None => Config::default(),
+    };
+    let files = match input_files(&options.inputs) {
+        Ok(files) => files,
+        Err(e) => {
+            let _ = writeln!(err, "{}", e);
+            return 2;
+        }
+    };
+
+    // the input of run, one for all files, so what a program leaves unread
+    // is read by the next one
+    let mut input: Box<dyn BufRead + Send> = match options.input.as_deref() {
+        Some(path) => match fs::File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => {
+                let _ = writeln!(err, "{}: {}", path, e);
+                return 2;
+            }
+        },
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+
+    let mut code = 0;
+    let mut all_diagnostics = Vec::new();
+    let mut corpus_coverage = Coverage::default();
+    for xfilename in files.iter() {
+        let source = match read_source(xfilename) {
+            Ok(source) => source,
+            Err(e) => {
+                let _ = writeln!(err, "{}: {}", xfilename, e);
+                code = 2;
+                continue;
+            }
+        };
+        let res = match args[0].as_str() {
+            "lex" => lex(xfilename, &source, out),
+            "parse" => parse(xfilename, &source, &options, out, err),
+            "highlight" => highlight(xfilename, &source, &options, files.len() > 1, out),
+            "fmt" => fmt(xfilename, &source, &options, out, err),
+            "cfg" => cfg(xfilename, &source, out, err),
+            "calls" => calls(xfilename, &source, &options, out, err),
+            "run" => run_program(xfilename, &source, &options, &mut input, out, err),
+            "trace" => trace(xfilename, &source, &options, out, err),
+            "interpret" => {
+                let tokens = Scanner::from_source(xfilename, &source).run().all_tokens.clone();
+                let res = grammar::Interpreter::new(loaded.as_ref().unwrap(), tokens).run();
+                writeln!(out, "{}: {}", xfilename, res).map(|_| 0)
+            }
+            "coverage" => {
+                corpus_coverage.add_source(xfilename, &source);
+                Ok(0)
+            }
+            "lint" => {
+                all_diagnostics.extend(lint_source(xfilename, &source, &config));
+                Ok(0)
+            }
+            _ => {
+                all_diagnostics.extend(check_source(xfilename, &source));
+                Ok(0)
+            }
+        };
+        match res {
+            Ok(file_code) => code = code.max(file_code),
+            Err(e) => {
+                let _ = writeln!(err, "{}: {}", xfilename, e);
+                code = 2;
+            }
+        }
+    }
+
+    if args[0] == "coverage" {
+        let text = match format {
+            "html" => corpus_coverage.to_html(&Grammar::x()),
+            _ => corpus_coverage.report(&Grammar::x()),
+        };
+        let _ = write!(out, "{}", text);
+    }
+    if args[0] == "check" || args[0] == "lint" {
+        let text = match format {
+            "json" => to_json_lines(&all_diagnostics),
+            "sarif" => to_sarif(&all_diagnostics),
+            _ => all_diagnostics.iter().map(|diagnostic| format!("{}\n", diagnostic)).collect(),
+        };
+        let _ = write!(out, "{}", text);
+        if all_diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error) {
+            code = code.max(1);
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_args(args: &[&str]) -> (i32, String, String) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let code = run(&args, &mut out, &mut err);
+        (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn globs() {
+        let chars = |text: &str| text.chars().collect::<Vec<_>>();
+        assert!(matches(&chars("example*.x"), &chars("example1.x")));
+        assert!(matches(&chars("*.?"), &chars("a.x")));
+        assert!(!matches(&chars("*.x"), &chars("a.xhtml")));
+
+        let examples = expand("example?.x");
+        assert!(examples.len() >= 2 && examples.contains(&PathBuf::from("example1.x")));
+        let golden = expand("tests/**/*.x");
+        assert!(golden.contains(&PathBuf::from("tests/examples/example1.x")));
+        assert!(golden.contains(&PathBuf::from("tests/errors/empty.x")));
+        assert!(golden.iter().all(|path| path.extension().unwrap() == "x"));
+        assert_eq!(expand("no_such_*.x"), Vec::<PathBuf>::new());
+    }
+
+    #[test]
+    fn subcommands() {
+        let (code, out, _) = run_args(&["lex", "example1.x"]);
+        assert_eq!(code, 0);
+        assert!(out.starts_with("example1.x:1:0 Keyword float\nexample1.x:1:6 Identifier Foo\n"));
+
+        let (code, out, err) = run_args(&["parse", "example1.x", "example3.x"]);
+        assert_eq!(code, 1);
+        assert_eq!(out, "example1.x: Input program is syntactically correct.\n");
+        assert!(err.starts_with("example3.x:1:0-1:4: error[X0100]: "));
+        let (code, out, _) = run_args(&["parse", "--ast", "example2.x"]);
+        assert_eq!((code, out.starts_with("Program [")), (0, true));
+
+        // the syntax errors of example3.x to example8.x, and the two shadowing
+        // warnings, the five lossy conversions, the read of R1 that may be
+        // unassigned and BarFoo after the return of Bar in example2.x
+        let (code, out, _) = run_args(&["check", "--format", "json", "example?.x"]);
+        assert_eq!(code, 1);
+        assert_eq!(out.lines().count(), 15);
+        assert_eq!(run_args(&["check", "example1.x", "example2.x"]).0, 0);
+
+        // example1.x and example2.x have no lint errors, but a config can
+        // make a rule an error
+        let (code, out, _) = run_args(&["lint", "example1.x", "example2.x"]);
+        assert_eq!(code, 0);
+        assert!(out.starts_with("example1.x:4:10-4:15: warning[L0001]: 'Value' is a local variable that is assigned, but never read.\n"));
+        assert_eq!(out.lines().count(), 22);
+        let (code, _, err) = run_args(&["lint", "--config", "no_such.conf", "example1.x"]);
+        assert!(code == 2 && err.starts_with("no_such.conf: "));
+
+        let (code, out, _) = run_args(&["cfg", "example1.x"]);
+        assert_eq!(code, 0);
+        assert!(out.starts_with("digraph \"example1.x\" {\n") && out.contains("label=\"Foo\";"));
+        assert_eq!(run_args(&["cfg", "example3.x"]).0, 1);
+
+        let (code, out, _) = run_args(&["calls", "example2.x"]);
+        assert_eq!(code, 0);
+        assert!(out.starts_with("example2.x:\nmain -> Foo, Bar\n"));
+        let (code, out, _) = run_args(&["calls", "--dot", "example1.x"]);
+        assert_eq!(code, 0);
+        assert!(out.contains("    f0 -> f1;\n"));
+
+        let (code, out, _) = run_args(&["run", "--vars", "example1.x"]);
+        assert_eq!((code, out.as_str()), (0, "example1.x:\nmain: Value = 5.0627596e18\n"));
+        let (code, out, _) = run_args(&["run", "--input", "tests/programs/io.in", "tests/programs/io.x"]);
+        assert_eq!((code, out.as_str()), (0, "6\n55\n-4\n5050\n1.625\n4\n"));
+        // the files share the input, each reads where the last one stopped
+        let dir = std::env::temp_dir();
+        let (echo, numbers) = (dir.join("proj2zhang_echo.x"), dir.join("proj2zhang_echo.in"));
+        fs::write(&echo, "void main(){\nint x;\nx = print_int(read_int());\n}\n").unwrap();
+        fs::write(&numbers, "1\n2\n").unwrap();
+        let (echo, numbers) = (echo.to_str().unwrap(), numbers.to_str().unwrap());
+        assert_eq!(run_args(&["run", "--input", numbers, echo, echo]), (0, "12".to_string(), String::new()));
+        let (code, _, err) = run_args(&["run", "--input", "no_such.in", "example1.x"]);
+        assert!(code == 2 && err.starts_with("no_such.in: "));
+        let (code, _, err) = run_args(&["run", "example2.x"]);
+        assert!(code == 1 && err.starts_with("example2.x:31:8-31:29: error[X0603]: "));
+
+        let (code, out, _) = run_args(&["fmt", "example1.x"]);
+        assert_eq!(code, 0);
+        assert!(out.starts_with("float Foo(int val);\nvoid main() {\n"));
+        assert_eq!(run_args(&["fmt", "--check", "example1.x"]).0, 1);
+
+        let (code, out, _) = run_args(&["highlight", "-o", "-", "--html", "example1.x"]);
+        assert_eq!(code, 0);
+        assert!(out.starts_with("<!DOCTYPE html>\n"));
+    }
+
+    #[test]
+    fn tools() {
+        let (code, out, _) = run_args(&["interpret", "--grammar", "x.ebnf", "example1.x"]);
+        assert_eq!((code, out.as_str()), (0, "example1.x: Input program is syntactically correct.\n"));
+
+        let (code, out, _) = run_args(&["trace", "example1.x"]);
+        assert!(code == 0 && out.starts_with("Program [0..59] ok "));
+        let (code, out, _) = run_args(&["trace", "--format", "json", "example1.x"]);
+        assert!(code == 0 && out.starts_with("[{\"rule\":\"Program\""));
+        assert_eq!(run_args(&["trace", "example3.x"]).0, 1);
+
+        let (code, out, _) = run_args(&["coverage", "example1.x", "example2.x"]);
+        assert!(code == 0 && out.starts_with("Grammar coverage over 2 files (2 parsed): "));
+
+        let (code, out, _) = run_args(&["generate", "1", "3", "--semantic"]);
+        assert!(code == 0 && out.contains("void main() {"));
+        let (code, out, _) = run_args(&["analyze-grammar", "x.ebnf"]);
+        assert!(code == 0 && out.starts_with("FIRST sets"));
+        let (code, out, _) = run_args(&["minimize", "example3.x", "diagnostic", "X0100"]);
+        assert!(code == 0 && !out.is_empty());
+        let (code, out, _) = run_args(&["fuzz", "1", "5"]);
+        assert_eq!((code, out.as_str()), (0, "no panics in 5 inputs\n"));
+    }
+
+    #[test]
+    fn bad_command_lines() {
+        assert_eq!(run_args(&[]).0, 2);
+        assert_eq!(run_args(&["compile", "example1.x"]).0, 2);
+        assert_eq!(run_args(&["lex"]).0, 2);
+        assert_eq!(run_args(&["parse", "--tokens", "example1.x"]).0, 2);
+        assert_eq!(run_args(&["check", "--format", "xml", "example1.x"]).0, 2);
+        assert_eq!(run_args(&["highlight", "example1.x", "-o"]).0, 2);
+        assert_eq!(run_args(&["fmt", "--check", "--write", "example1.x"]).0, 2);
+        let (code, _, err) = run_args(&["coverage", "--format", "json", "example1.x"]);
+        assert_eq!((code, err.as_str()), (2, "unknown output format 'json', expected text or html\n"));
+        assert_eq!(run_args(&["interpret", "example1.x"]).0, 2);
+        assert_eq!(run_args(&["generate", "1"]).0, 2);
+        assert_eq!(run_args(&["minimize", "example1.x", "diagnostic"]).0, 2);
+        assert_eq!(run_args(&["golden", "--write"]).0, 2);
+        let (code, _, err) = run_args(&["lex", "no_such_*.x"]);
+        assert_eq!((code, err.as_str()), (2, "no files match 'no_such_*.x'\n"));
+        assert_eq!(run_args(&["lex", "missing.x"]).0, 2);
+    }
+}
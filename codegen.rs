@@ -0,0 +1,495 @@
+//Generates Rust parser source from an EBNF grammar: one parse function and
+//one typed AST struct per nonterminal, with spans and the Identifier and
+//constant tokens they contain. The generated parser backtracks exactly like
+//grammar::Interpreter and the stage3 macros, and builds a concrete syntax
+//tree; it checks the grammar and does not replace stage3::Parser, whose AST
+//also applies operator precedence.
+//
+//Run it with cargo run -- gen-parser x.ebnf x_parser.rs, or call
+//generate_file from a build.rs.
+
+use std::fs;
+
+use crate::grammar::{Expr, Grammar, GrammarError, Rule};
+use crate::stage2_token::TokenType;
+
+// how often a nonterminal can occur in a rule
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Multiplicity {
+    // exactly once
+    One,
+    // at most once
+    Optional,
+    // any number of times
+    Many,
+}
+
+// a typed field of a generated AST struct
+struct Field {
+    // a nonterminal, or the token class of a Token field
+    rule: String,
+    name: String,
+    multiplicity: Multiplicity,
+    token_type: Option<TokenType>,
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "async", "await", "dyn",
+];
+
+// ParameterBlock -> parameter_block
+fn snake_case(name: &str) -> String {
+    let mut res = "".to_string();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                res.push('_');
+            }
+            res.extend(c.to_lowercase());
+        } else {
+            res.push(c);
+        }
+    }
+    res
+}
+
+// locals of the generated parse functions that fields must not shadow
+const RESERVED_NAMES: &[&str] = &["start", "tokens", "res", "mark", "e", "token", "node"];
+
+fn field_name(name: String) -> String {
+    if RUST_KEYWORDS.contains(&name.as_str()) {
+        return format!("r#{}", name);
+    }
+    if RESERVED_NAMES.contains(&name.as_str()) {
+        return format!("{}_", name);
+    }
+    name
+}
+
+impl Field {
+    // the type of one element: a node of the rule, or a Token
+    fn type_name(&self) -> &str {
+        match self.token_type {
+            Some(_) => "Token",
+            None => &self.rule,
+        }
+    }
+}
+
+fn add_field(rule: String, token_type: Option<TokenType>, multiplicity: Multiplicity, fields: &mut Vec<Field>) {
+    match fields.iter_mut().find(|field| field.rule == rule) {
+        // a second occurrence makes it a list
+        Some(field) => field.multiplicity = Multiplicity::Many,
+        None => fields.push(Field {
+            rule,
+            name: "".to_string(),
+            multiplicity,
+            token_type,
+        }),
+    }
+}
+
+fn collect_fields(expr: &Expr, multiplicity: Multiplicity, fields: &mut Vec<Field>) {
+    match expr {
+        Expr::NonTerminal(name) => add_field(name.clone(), None, multiplicity, fields),
+        // keywords and operators are only in tokens, but names and
+        // constants are fields of their own
+        Expr::Token(token_type) => add_field(format!("{:?}", token_type), Some(*token_type), multiplicity, fields),
+        Expr::Sequence(items) => {
+            for item in items {
+                collect_fields(item, multiplicity, fields);
+            }
+        }
+        Expr::Choice(alternatives) => {
+            let inner = if multiplicity == Multiplicity::Many {
+                Multiplicity::Many
+            } else {
+                Multiplicity::Optional
+            };
+            for alternative in alternatives {
+                collect_fields(alternative, inner, fields);
+            }
+        }
+        Expr::Optional(inner) => {
+            let inner_multiplicity = if multiplicity == Multiplicity::Many {
+                Multiplicity::Many
+            } else {
+                Multiplicity::Optional
+            };
+            collect_fields(inner, inner_multiplicity, fields);
+        }
+        Expr::Repetition(inner) => collect_fields(inner, Multiplicity::Many, fields),
+        _ => {}
+    }
+}
+
+fn fields_of(rule: &Rule) -> Vec<Field> {
+    let mut fields = Vec::new();
+    collect_fields(&rule.expr, Multiplicity::One, &mut fields);
+    for field in fields.iter_mut() {
+        field.name = match field.multiplicity {
+            Multiplicity::Many => field_name(format!("{}s", snake_case(&field.rule))),
+            _ => field_name(snake_case(&field.rule)),
+        };
+    }
+    fields
+}
+
+fn token_type_name(token_type: TokenType) -> &'static str {
+    match token_type {
+        TokenType::IntConstant => "TokenType::IntConstant",
+        TokenType::FloatConstant => "TokenType::FloatConstant",
+        TokenType::Keyword => "TokenType::Keyword",
+        TokenType::Operator => "TokenType::Operator",
+        TokenType::Identifier => "TokenType::Identifier",
+        TokenType::Invalid => "TokenType::Invalid",
+        TokenType::Comment => "TokenType::Comment",
+    }
+}
+
+// writes the parse function of one rule
+struct RuleWriter<'a> {
+    fields: &'a [Field],
+    labels: usize,
+}
+
+impl<'a> RuleWriter<'a> {
+    // a snapshot of the token index and everything parsed so far
+    fn mark(&self) -> String {
+        let mut parts = vec!["self.token_index".to_string(), "tokens.len()".to_string()];
+        for field in self.fields {
+            parts.push(match field.multiplicity {
+                Multiplicity::Many => format!("{}.len()", field.name),
+                _ => format!("{}.is_some()", field.name),
+            });
+        }
+        format!("({})", parts.join(", "))
+    }
+
+    // rolls back to a snapshot taken with mark()
+    fn restore(&self) -> String {
+        let mut res = "self.token_index = mark.0; tokens.truncate(mark.1);".to_string();
+        for (i, field) in self.fields.iter().enumerate() {
+            res += &match field.multiplicity {
+                Multiplicity::Many => format!(" {}.truncate(mark.{});", field.name, i + 2),
+                _ => format!(" {} = {}.filter(|_| mark.{});", field.name, field.name, i + 2),
+            };
+        }
+        res
+    }
+
+    fn label(&mut self) -> String {
+        self.labels += 1;
+        format!("'l{}", self.labels)
+    }
+
+    // an expression of type Result<(), SyntaxError> that parses expr
+    fn expr(&mut self, expr: &Expr, indent: &str) -> String {
+        let inner = format!("{}    ", indent);
+        match expr {
+            Expr::Keyword(text) => format!(
+                "self.terminal(TokenType::Keyword, {:?}, EBNF).map(|token| tokens.push(token))",
+                text
+            ),
+            Expr::Operator(text) => format!(
+                "self.terminal(TokenType::Operator, {:?}, EBNF).map(|token| tokens.push(token))",
+                text
+            ),
+            Expr::Token(token_type) => {
+                let field = self.fields.iter().find(|field| field.token_type == Some(*token_type)).unwrap();
+                let store = match field.multiplicity {
+                    Multiplicity::Many => format!("{}.push(token.clone())", field.name),
+                    _ => format!("{} = Some(token.clone())", field.name),
+                };
+                format!(
+                    "self.terminal({}, \"\", EBNF).map(|token| {{ {}; tokens.push(token) }})",
+                    token_type_name(*token_type),
+                    store
+                )
+            }
+            Expr::NonTerminal(name) => {
+                let field = self.fields.iter().find(|field| &field.rule == name).unwrap();
+                match field.multiplicity {
+                    Multiplicity::Many => format!(
+                        "self.parse_{}().map(|node| {}.push(node))",
+                        snake_case(name),
+                        field.name
+                    ),
+                    _ => format!(
+                        "self.parse_{}().map(|node| {} = Some(node))",
+                        snake_case(name),
+                        field.name
+                    ),
+                }
+            }
+            Expr::Sequence(items) => {
+                let label = self.label();
+                let mut res = format!("{}: {{\n", label);
+                for item in items {
+                    res += &format!(
+                        "{}if let Err(e) = ({}) {{\n{}    break {} Err(e);\n{}}}\n",
+                        inner,
+                        self.expr(item, &inner),
+                        inner,
+                        label,
+                        inner
+                    );
+                }
+                res += &format!("{}Ok(())\n{}}}", inner, indent);
+                res
+            }
+            Expr::Choice(alternatives) => {
+                let label = self.label();
+                let mut res = format!("{}: {{\n{}let mark = {};\n", label, inner, self.mark());
+                for alternative in alternatives {
+                    res += &format!(
+                        "{}if ({}).is_ok() {{\n{}    break {} Ok(());\n{}}}\n{}{}\n",
+                        inner,
+                        self.expr(alternative, &inner),
+                        inner,
+                        label,
+                        inner,
+                        inner,
+                        self.restore()
+                    );
+                }
+                res += &format!("{}Err(self.error_here(EBNF))\n{}}}", inner, indent);
+                res
+            }
+            Expr::Optional(optional) => format!(
+                "{{\n{}let mark = {};\n{}if ({}).is_err() {{\n{}    {}\n{}}}\n{}Ok::<(), SyntaxError>(())\n{}}}",
+                inner,
+                self.mark(),
+                inner,
+                self.expr(optional, &inner),
+                inner,
+                self.restore(),
+                inner,
+                inner,
+                indent
+            ),
+            Expr::Repetition(repeated) => {
+                let body = format!("{}    ", inner);
+                format!(
+                    "{{\n{}loop {{\n{}let mark = {};\n{}// stop on failure, or if nothing was consumed\n{}if ({}).is_err() || self.token_index == mark.0 {{\n{}    {}\n{}    break;\n{}}}\n{}}}\n{}Ok::<(), SyntaxError>(())\n{}}}",
+                    inner,
+                    body,
+                    self.mark(),
+                    body,
+                    body,
+                    self.expr(repeated, &body),
+                    body,
+                    self.restore(),
+                    body,
+                    body,
+                    inner,
+                    inner,
+                    indent
+                )
+            }
+        }
+    }
+}
+
+fn generate_struct(rule: &Rule, fields: &[Field]) -> String {
+    let mut res = format!("// {}\n#[derive(Clone, Debug, PartialEq)]\npub struct {} {{\n    pub span: Span,\n    // the terminals of this rule, in order\n    pub tokens: Vec<Token>,\n", rule.text, rule.name);
+    for field in fields {
+        res += &match (field.multiplicity, field.token_type) {
+            (Multiplicity::One, None) => format!("    pub {}: Box<{}>,\n", field.name, field.rule),
+            (Multiplicity::Optional, None) => format!("    pub {}: Option<Box<{}>>,\n", field.name, field.rule),
+            (Multiplicity::One, Some(_)) => format!("    pub {}: Token,\n", field.name),
+            (Multiplicity::Optional, Some(_)) => format!("    pub {}: Option<Token>,\n", field.name),
+            (Multiplicity::Many, _) => format!("    pub {}: Vec<{}>,\n", field.name, field.type_name()),
+        };
+    }
+    res += "}\n";
+    res
+}
+
+fn generate_function(rule: &Rule, fields: &[Field]) -> String {
+    let mut res = format!(
+        "    pub fn parse_{}(&mut self) -> Result<{}, SyntaxError> {{\n        const EBNF: &str = {:?};\n        let start = self.token_index;\n        let mut tokens: Vec<Token> = Vec::new();\n",
+        snake_case(&rule.name),
+        rule.name,
+        rule.text
+    );
+    for field in fields {
+        res += &match field.multiplicity {
+            Multiplicity::Many => format!("        let mut {}: Vec<{}> = Vec::new();\n", field.name, field.type_name()),
+            _ => format!("        let mut {}: Option<{}> = None;\n", field.name, field.type_name()),
+        };
+    }
+
+    let mut writer = RuleWriter { fields, labels: 0 };
+    res += &format!(
+        "        let res: Result<(), SyntaxError> = {};\n",
+        writer.expr(&rule.expr, "        ")
+    );
+    res += "        if let Err(e) = res {\n            self.token_index = start;\n            return Err(e);\n        }\n";
+
+    res += &format!(
+        "        Ok({} {{\n            span: self.span_from(start),\n            tokens,\n",
+        rule.name
+    );
+    for field in fields {
+        res += &match (field.multiplicity, field.token_type) {
+            (Multiplicity::One, None) => format!("            {}: Box::new({}.unwrap()),\n", field.name, field.name),
+            (Multiplicity::Optional, None) => format!("            {}: {}.map(Box::new),\n", field.name, field.name),
+            (Multiplicity::One, Some(_)) => format!("            {}: {}.unwrap(),\n", field.name, field.name),
+            (Multiplicity::Optional, Some(_)) | (Multiplicity::Many, _) => format!("            {},\n", field.name),
+        };
+    }
+    res += "        })\n    }\n";
+    res
+}
+
+const RUNTIME: &str = r#"
+/**
+ * A backtracking recursive-descent parser generated from the grammar.
+ */
+pub struct Parser {
+    pub all_tokens: Vec<Token>,
+    token_index: usize,
+}
+
+impl Parser {
+    pub fn new(all_tokens: Vec<Token>) -> Parser {
+        Parser {
+            all_tokens,
+            token_index: 0,
+        }
+    }
+
+    // an error at the current token, or at the last one past the end
+    fn error_here(&self, ebnf: &str) -> SyntaxError {
+        let (line_num, char_pos) = match self.all_tokens.get(self.token_index).or(self.all_tokens.last()) {
+            Some(token) => (token.line_num, token.char_pos),
+            None => (0, 0),
+        };
+        SyntaxError {
+            line_num,
+            char_pos,
+            ebnf: ebnf.to_string(),
+        }
+    }
+
+    fn terminal(&mut self, token_type: TokenType, text: &str, ebnf: &str) -> Result<Token, SyntaxError> {
+        match self.all_tokens.get(self.token_index) {
+            Some(token)
+                if token.token_type == token_type
+                    && (text.is_empty() || token.text == text) =>
+            {
+                self.token_index += 1;
+                Ok(token.clone())
+            }
+            _ => Err(self.error_here(ebnf)),
+        }
+    }
+
+    // the span of the tokens consumed since start
+    fn span_from(&self, start: usize) -> Span {
+        if self.token_index <= start {
+            return match self.all_tokens.get(start) {
+                Some(token) => Span {
+                    start_line: token.line_num,
+                    start_char: token.char_pos,
+                    end_line: token.line_num,
+                    end_char: token.char_pos,
+                },
+                None => Span::default(),
+            };
+        }
+        let first = Span::of_token(&self.all_tokens[start]);
+        let last = Span::of_token(&self.all_tokens[self.token_index - 1]);
+        Span {
+            start_line: first.start_line,
+            start_char: first.start_char,
+            end_line: last.end_line,
+            end_char: last.end_char,
+        }
+    }
+"#;
+
+// the complete Rust source of a parser module for the grammar
+pub fn generate(grammar: &Grammar) -> String {
+    let start = grammar.start_rule();
+    let mut res = "// @generated by codegen.rs from the X grammar, do not edit by hand.\n\n#![allow(dead_code, unused_labels, unused_mut, unused_parens, unused_variables)]\n\nuse crate::diagnostics::Span;\nuse crate::stage2_token::{Token, TokenType};\nuse crate::stage3::SyntaxError;\n\n".to_string();
+
+    for rule in grammar.rules.iter() {
+        res += &generate_struct(rule, &fields_of(rule));
+        res += "\n";
+    }
+
+    res += RUNTIME;
+    res += &format!(
+        "
+    // parses the whole token stream
+    pub fn parse(&mut self) -> Result<{}, SyntaxError> {{
+        self.token_index = 0;
+        let tree = self.parse_{}()?;
+        if self.token_index < self.all_tokens.len() {{
+            return Err(self.error_here({:?}));
+        }}
+        Ok(tree)
+    }}
+",
+        start.name,
+        snake_case(&start.name),
+        start.text
+    );
+    for rule in grammar.rules.iter() {
+        res += "\n";
+        res += &generate_function(rule, &fields_of(rule));
+    }
+    res += "}\n";
+    res
+}
+
+// for build scripts: reads a grammar file and writes the generated parser
+pub fn generate_file(grammar_path: &str, out_path: &str) -> Result<(), GrammarError> {
+    let grammar = Grammar::from_file(grammar_path)?;
+    fs::write(out_path, generate(&grammar)).map_err(|e| GrammarError {
+        line_num: 0,
+        message: format!("cannot write {}: {}", out_path, e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_parser() {
+        assert_eq!(snake_case("ParameterBlock"), "parameter_block");
+
+        // the checked-in x_parser.rs must match the grammar
+        assert_eq!(
+            generate(&Grammar::x()),
+            include_str!("x_parser.rs"),
+            "x_parser.rs is out of date, regenerate it with gen-parser x.ebnf x_parser.rs"
+        );
+
+        let grammar = Grammar::parse("List := '(' [ Item { , Item } ] ')'\nItem := Identifier").unwrap();
+        let fields = fields_of(grammar.rule("List").unwrap());
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].name, "items");
+        assert_eq!(fields[0].multiplicity, Multiplicity::Many);
+        let fields = fields_of(grammar.rule("Item").unwrap());
+        assert_eq!((fields[0].name.as_str(), fields[0].type_name()), ("identifier", "Token"));
+
+        let mut parser = crate::x_parser::Parser::new(
+            crate::stage2_scanner::Scanner::new("example1.x").run().all_tokens.clone(),
+        );
+        let program = parser.parse().unwrap();
+        assert_eq!(program.declarations.len(), 1);
+        assert_eq!(program.function_definitions.len(), 1);
+        assert_eq!(program.main_declaration.tokens[0].text, "void");
+        assert_eq!(program.declarations[0].declaration_type.identifier.text, "Foo");
+        assert_eq!(program.function_definitions[0].parameter_block.parameters[0].identifier.text, "val");
+        assert_eq!(program.span.start_line, 1);
+        assert_eq!(program.span.end_line, 17);
+    }
+}
@@ -0,0 +1,519 @@
+//Constant expressions: the value of an expression of constants and
+//operators, computed in the type C gives it. Integers wrap around to the
+//width of their type, which is defined for unsigned types and reported as an
+//overflow for signed ones, and a division by the constant zero is reported
+//wherever it appears, as is an integer constant that does not fit in
+//unsigned long, the widest type. check_constants requires global initializers to be
+//constant and warns about constant conditions; fold replaces the constant
+//parts of a program's expressions by their values.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ast::{Block, DataType, Declaration, Expression, ExpressionKind, FunctionDefinition, Program, StatementKind};
+use crate::diagnostics::{Diagnostic, Severity, Span};
+use crate::stage2_token::{Token, TokenType};
+use crate::typecheck::{constant_type, promote, usual_arithmetic_conversions};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Integer(i128),
+    Floating(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Constant {
+    pub data_type: DataType,
+    pub value: Value,
+}
+
+// an integer reduced to the width and signedness of a type
+fn wrap(value: i128, data_type: DataType) -> i128 {
+    let modulus = 1i128 << data_type.bits();
+    let value = value.rem_euclid(modulus);
+    if !data_type.is_unsigned() && value >= modulus / 2 {
+        value - modulus
+    } else {
+        value
+    }
+}
+
+impl Constant {
+    // a value converted to a type: integers wrap around, floating values
+    // are truncated toward zero or rounded to float
+    pub fn new(data_type: DataType, value: Value) -> Constant {
+        let value = match (data_type, value) {
+            (DataType::Float, value) => Value::Floating(value.as_f64() as f32 as f64),
+            (DataType::Double, value) => Value::Floating(value.as_f64()),
+            (data_type, Value::Integer(value)) => Value::Integer(wrap(value, data_type)),
+            (data_type, Value::Floating(value)) => Value::Integer(wrap(value.trunc() as i128, data_type)),
+        };
+        Constant { data_type, value }
+    }
+
+    // the value of an IntConstant or FloatConstant token, None for an
+    // integer that does not fit in unsigned long
+    pub fn of_token(token: &Token) -> Option<Constant> {
+        let value = match token.token_type {
+            TokenType::IntConstant => Value::Integer(token.text.parse::<u64>().ok()? as i128),
+            _ => Value::Floating(token.text.parse().ok()?),
+        };
+        Some(Constant::new(constant_type(token), value))
+    }
+
+    fn boolean(value: bool) -> Constant {
+        Constant {
+            data_type: DataType::Int,
+            value: Value::Integer(value as i128),
+        }
+    }
+
+    pub fn convert(self, to: DataType) -> Constant {
+        Constant::new(to, self.value)
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.value.as_f64()
+    }
+
+    // true unless zero, like a C condition
+    pub fn is_true(&self) -> bool {
+        self.as_f64() != 0.0
+    }
+}
+
+impl Value {
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            Value::Integer(value) => value as f64,
+            Value::Floating(value) => value,
+        }
+    }
+}
+
+// as X source would write the value; floating values keep a fraction so
+// that they read back as floating constants
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value {
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::Floating(value) if value.fract() == 0.0 && value.is_finite() => write!(f, "{:.1}", value),
+            Value::Floating(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+// evaluates expressions, collecting the divisions by zero and the signed
+// overflows it finds
+pub struct Evaluator<'a> {
+    xfilename: &'a str,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(xfilename: &'a str) -> Evaluator<'a> {
+        Evaluator {
+            xfilename,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, severity: Severity, code: &'static str, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            code,
+            message,
+            file: self.xfilename.to_string(),
+            span,
+            related: Vec::new(),
+        });
+    }
+
+    // the result of an operator, reported if it overflowed; infinite and
+    // not-a-number results are not constants
+    fn checked(&mut self, res: Option<(Constant, bool)>, span: Span) -> Option<Constant> {
+        let (res, overflow) = res?;
+        if overflow {
+            let message = format!("The constant expression overflows {}; its value wraps around to {}.", res.data_type, res);
+            self.report(Severity::Warning, "X0501", message, span);
+        }
+        Some(res).filter(|res| res.as_f64().is_finite())
+    }
+
+    fn constant(&mut self, token: &Token) -> Option<Constant> {
+        let constant = Constant::of_token(token);
+        if constant.is_none() && token.token_type == TokenType::IntConstant {
+            let message = format!("The integer constant {} does not fit in unsigned long, the widest integer type.", token.text);
+            self.report(Severity::Error, "X0504", message, Span::of_token(token));
+        }
+        constant
+    }
+
+    // the integer constants out of range in an operand that is not evaluated
+    fn constants(&mut self, expression: &Expression) {
+        match &expression.kind {
+            ExpressionKind::Constant(token) => {
+                self.constant(token);
+            }
+            ExpressionKind::Identifier(_) => {}
+            ExpressionKind::Call(_, arguments) => {
+                for argument in arguments.iter() {
+                    self.constants(argument);
+                }
+            }
+            ExpressionKind::Assign(_, operand) | ExpressionKind::Unary(_, operand) => self.constants(operand),
+            ExpressionKind::Binary(_, lhs, rhs) => {
+                self.constants(lhs);
+                self.constants(rhs);
+            }
+        }
+    }
+
+    // the value of an expression, None if it is not constant; the parts of
+    // it that are evaluated are checked either way
+    pub fn evaluate(&mut self, expression: &Expression) -> Option<Constant> {
+        match &expression.kind {
+            ExpressionKind::Constant(token) => self.constant(token),
+            ExpressionKind::Identifier(_) => None,
+            ExpressionKind::Call(_, arguments) => {
+                for argument in arguments.iter() {
+                    self.evaluate(argument);
+                }
+                None
+            }
+            ExpressionKind::Assign(_, value) => {
+                self.evaluate(value);
+                None
+            }
+            ExpressionKind::Unary(operator, operand) => {
+                let operand = self.evaluate(operand)?;
+                self.checked(unary(&operator.text, operand), expression.span)
+            }
+            ExpressionKind::Binary(operator, lhs, rhs) => {
+                let text = operator.text.as_str();
+                let left = self.evaluate(lhs);
+                if text == "&&" || text == "||" {
+                    // the right operand is not evaluated once the left one decides
+                    if let Some(left) = left.filter(|left| left.is_true() == (text == "||")) {
+                        self.constants(rhs);
+                        return Some(Constant::boolean(left.is_true()));
+                    }
+                    let right = self.evaluate(rhs)?;
+                    return left.map(|_| Constant::boolean(right.is_true()));
+                }
+                let divisor = self.evaluate(rhs);
+                if (text == "/" || text == "%") && divisor.is_some_and(|divisor| !divisor.is_true()) {
+                    // the type of a dividend that is not constant is known once it is type checked
+                    let dividend_type = left.map(|left| left.data_type).or(lhs.ty);
+                    let floating = divisor.unwrap().data_type.is_floating() || dividend_type.is_some_and(|ty| ty.is_floating());
+                    if floating {
+                        let message = format!("The divisor of '{}' is zero, so the result is infinite or not a number.", text);
+                        self.report(Severity::Warning, "X0500", message, expression.span);
+                    } else {
+                        let message = format!("The divisor of '{}' is zero, which is undefined for integers.", text);
+                        self.report(Severity::Error, "X0500", message, expression.span);
+                    }
+                    return None;
+                }
+                self.checked(binary(text, left?, divisor?), expression.span)
+            }
+        }
+    }
+}
+
+// an integer result in a type, and whether it overflowed: a signed result
+// that does not fit wraps around like an unsigned one
+fn integer(exact: i128, data_type: DataType) -> (Constant, bool) {
+    let res = Constant::new(data_type, Value::Integer(exact));
+    (res, !data_type.is_unsigned() && res.value != Value::Integer(exact))
+}
+
+// the result of a unary operator in the promoted type of its operand, and
+// whether it overflowed; None for ~ of a floating value
+pub fn unary(operator: &str, operand: Constant) -> Option<(Constant, bool)> {
+    let data_type = promote(operand.data_type);
+    let operand = operand.convert(data_type);
+    match (operator, operand.value) {
+        ("!", _) => Some((Constant::boolean(!operand.is_true()), false)),
+        ("-", Value::Integer(value)) => Some(integer(-value, data_type)),
+        ("-", Value::Floating(value)) => Some((Constant::new(data_type, Value::Floating(-value)), false)),
+        ("~", Value::Integer(value)) => Some((Constant::new(data_type, Value::Integer(!value)), false)),
+        _ => None,
+    }
+}
+
+// the result of a binary operator in the type of the usual arithmetic
+// conversions, and whether it overflowed; None for && and ||, an operator
+// that does not take floating operands and an integer division by zero
+pub fn binary(operator: &str, lhs: Constant, rhs: Constant) -> Option<(Constant, bool)> {
+    let data_type = usual_arithmetic_conversions(lhs.data_type, rhs.data_type);
+    let (lhs, rhs) = (lhs.convert(data_type).value, rhs.convert(data_type).value);
+    match (lhs, rhs) {
+        (Value::Integer(a), Value::Integer(b)) => {
+            let exact = match operator {
+                "+" => a + b,
+                "-" => a - b,
+                // only two unsigned long values can overflow i128, and
+                // they wrap around anyway
+                "*" => a.checked_mul(b).unwrap_or_else(|| (a as u128).wrapping_mul(b as u128) as i128),
+                "/" => a.checked_div(b)?,
+                "%" => a.checked_rem(b)?,
+                "&" => a & b,
+                "|" => a | b,
+                "^" => a ^ b,
+                _ => return Some((Constant::boolean(compare(operator, a.cmp(&b))?), false)),
+            };
+            Some(integer(exact, data_type))
+        }
+        (Value::Floating(a), Value::Floating(b)) => {
+            let res = match operator {
+                "+" => a + b,
+                "-" => a - b,
+                "*" => a * b,
+                "/" => a / b,
+                // not a number is unordered, so only != holds for it
+                _ => {
+                    let res = match a.partial_cmp(&b) {
+                        Some(ordering) => compare(operator, ordering)?,
+                        None => compare(operator, std::cmp::Ordering::Equal).map(|_| operator == "!=")?,
+                    };
+                    return Some((Constant::boolean(res), false));
+                }
+            };
+            Some((Constant::new(data_type, Value::Floating(res)), false))
+        }
+        _ => None,
+    }
+}
+
+// the value of a comparison operator, None for another operator
+fn compare(operator: &str, ordering: std::cmp::Ordering) -> Option<bool> {
+    use std::cmp::Ordering::*;
+    Some(match operator {
+        "==" => ordering == Equal,
+        "!=" => ordering != Equal,
+        "<" => ordering == Less,
+        ">" => ordering == Greater,
+        "<=" => ordering != Greater,
+        ">=" => ordering != Less,
+        _ => return None,
+    })
+}
+
+// the value of a constant expression, without its diagnostics
+pub fn value(expression: &Expression) -> Option<Constant> {
+    Evaluator::new("").evaluate(expression)
+}
+
+struct Checker<'a> {
+    evaluator: Evaluator<'a>,
+}
+
+impl Checker<'_> {
+    fn block(&mut self, block: &Block) {
+        for declaration in block.declarations.iter() {
+            if let Declaration::Variable(variable) = declaration {
+                if let Some(initializer) = &variable.initializer {
+                    self.evaluator.evaluate(initializer);
+                }
+            }
+        }
+        for statement in block.statements.iter() {
+            match &statement.kind {
+                StatementKind::Expression(expression) | StatementKind::Return(expression) => {
+                    self.evaluator.evaluate(expression);
+                }
+                StatementKind::While(condition, body) | StatementKind::If(condition, body) => {
+                    let is_while = matches!(statement.kind, StatementKind::While(..));
+                    // while (1) is how a loop that only ends by a return is written
+                    let message = match self.evaluator.evaluate(condition) {
+                        Some(value) if !value.is_true() && is_while => {
+                            Some("The condition is always false, so the loop never runs.")
+                        }
+                        Some(value) if !is_while => Some(if value.is_true() {
+                            "The condition is always true."
+                        } else {
+                            "The condition is always false, so the block never runs."
+                        }),
+                        _ => None,
+                    };
+                    if let Some(message) = message {
+                        self.evaluator.report(Severity::Warning, "X0503", message.to_string(), condition.span);
+                    }
+                    self.block(body);
+                }
+            }
+        }
+        for function in block.functions.iter() {
+            self.block(&function.block);
+        }
+    }
+}
+
+// the divisions by zero and signed overflows of a program, its global
+// initializers that are not constant and its constant conditions, sorted
+// by position
+pub fn check_constants(xfilename: &str, program: &Program) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        evaluator: Evaluator::new(xfilename),
+    };
+    for declaration in program.declarations.iter() {
+        if let Declaration::Variable(variable) = declaration {
+            if let Some(initializer) = &variable.initializer {
+                let errors = checker.evaluator.diagnostics.len();
+                let value = checker.evaluator.evaluate(initializer);
+                let reported = checker.evaluator.diagnostics[errors..].iter().any(|d| d.severity == Severity::Error);
+                if value.is_none() && !reported {
+                    let message = format!("The initializer of the global '{}' is not a constant expression.", variable.name.text);
+                    checker.evaluator.report(Severity::Error, "X0502", message, initializer.span);
+                }
+            }
+        }
+    }
+    checker.block(&program.main.block);
+    for function in program.functions.iter() {
+        checker.block(&function.block);
+    }
+    let mut diagnostics = checker.evaluator.diagnostics;
+    diagnostics.sort_by_key(|d| (d.span.start_line, d.span.start_char));
+    diagnostics
+}
+
+// a constant as an expression: a constant token, negated if it is below zero
+fn constant_expression(constant: Constant, ty: Option<DataType>, span: Span) -> Expression {
+    let text = constant.to_string();
+    let token_type = match constant.value {
+        Value::Integer(_) => TokenType::IntConstant,
+        Value::Floating(_) => TokenType::FloatConstant,
+    };
+    let expression = |kind| Expression { kind, span, ty };
+    match text.strip_prefix('-') {
+        Some(digits) => {
+            let operator = Token::new(TokenType::Operator, "-", span.start_line, span.start_char);
+            let digits = Token::new(token_type, digits, span.start_line, span.start_char + 1);
+            expression(ExpressionKind::Unary(operator, Arc::new(expression(ExpressionKind::Constant(digits)))))
+        }
+        None => expression(ExpressionKind::Constant(Token::new(token_type, &text, span.start_line, span.start_char))),
+    }
+}
+
+// the constant that fold replaces an expression by; None if it is not
+// constant, or a constant already
+pub fn folded(expression: &Expression) -> Option<Expression> {
+    if let ExpressionKind::Constant(_) = expression.kind {
+        return None;
+    }
+    let constant = value(expression).filter(|constant| constant.as_f64().is_finite())?;
+    Some(constant_expression(constant, expression.ty, expression.span))
+}
+
+fn fold_expression(expression: &mut Expression) {
+    if let Some(constant) = folded(expression) {
+        *expression = constant;
+        return;
+    }
+    match &mut expression.kind {
+        ExpressionKind::Constant(_) | ExpressionKind::Identifier(_) => {}
+        ExpressionKind::Call(_, arguments) => arguments.iter_mut().for_each(|argument| fold_expression(Arc::make_mut(argument))),
+        ExpressionKind::Unary(_, operand) => fold_expression(Arc::make_mut(operand)),
+        ExpressionKind::Binary(_, lhs, rhs) => {
+            fold_expression(Arc::make_mut(lhs));
+            fold_expression(Arc::make_mut(rhs));
+        }
+        ExpressionKind::Assign(_, value) => fold_expression(Arc::make_mut(value)),
+    }
+}
+
+fn fold_declarations(declarations: &mut [Declaration]) {
+    for declaration in declarations.iter_mut() {
+        if let Declaration::Variable(variable) = declaration {
+            variable.initializer.iter_mut().for_each(fold_expression);
+        }
+    }
+}
+
+fn fold_block(block: &mut Block) {
+    fold_declarations(&mut block.declarations);
+    for statement in block.statements.iter_mut() {
+        match &mut statement.kind {
+            StatementKind::Expression(expression) | StatementKind::Return(expression) => fold_expression(expression),
+            StatementKind::While(condition, body) | StatementKind::If(condition, body) => {
+                fold_expression(condition);
+                fold_block(Arc::make_mut(body));
+            }
+        }
+    }
+    block.functions.iter_mut().for_each(fold_function);
+}
+
+fn fold_function(function: &mut FunctionDefinition) {
+    fold_block(Arc::make_mut(&mut function.block));
+}
+
+// replaces every constant part of the program's expressions that is not a
+// constant already by its value; expressions keep their ty
+pub fn fold(program: &mut Program) {
+    fold_declarations(&mut program.declarations);
+    fold_block(&mut program.main.block);
+    program.functions.iter_mut().for_each(fold_function);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::test_support::parse;
+    use crate::printer::program_source;
+
+    // the value of the initializer of the only global
+    fn evaluate(initializer: &str) -> (Option<String>, Vec<&'static str>) {
+        let program = parse(&format!("int x = {};\nvoid main(){{\n}}\n", initializer));
+        let initializer = match &program.declarations[0] {
+            Declaration::Variable(variable) => variable.initializer.clone().unwrap(),
+            _ => unreachable!(),
+        };
+        let mut evaluator = Evaluator::new("test.x");
+        let value = evaluator.evaluate(&initializer).map(|constant| format!("{} {}", constant.data_type, constant));
+        (value, evaluator.diagnostics.iter().map(|d| d.code).collect())
+    }
+
+    #[test]
+    fn values() {
+        let value = |initializer| evaluate(initializer).0.unwrap();
+        assert_eq!(value("60 * 60 * 24"), "int 86400");
+        assert_eq!(value("-7 / 2 + -7 % 2"), "int -4");
+        assert_eq!(value("3000000000"), "long 3000000000");
+        assert_eq!(value("1 + 2.5"), "double 3.5");
+        assert_eq!(value("~0"), "int -1");
+        assert_eq!(value("(1 < 2) + (2.0 == 2)"), "int 2");
+        // the unsigned long wraps around, and -1 converts to its largest value
+        assert_eq!(value("18446744073709551615 + 2"), "unsigned long 1");
+        assert_eq!(value("-1 < 18446744073709551615"), "int 0");
+        assert_eq!(value("0 && 1 / 0"), "int 0");
+
+        assert_eq!(evaluate("2147483647 + 1"), (Some("int -2147483648".to_string()), vec!["X0501"]));
+        assert_eq!(evaluate("1 / (2 - 2)"), (None, vec!["X0500"]));
+        assert_eq!(evaluate("1 / 0.0"), (None, vec!["X0500"]));
+        assert_eq!(evaluate("x + 1"), (None, vec![]));
+        // no integer type holds the constant, even where it is not evaluated
+        assert_eq!(evaluate("18446744073709551616"), (None, vec!["X0504"]));
+        assert_eq!(evaluate("1 || 99999999999999999999"), (Some("int 1".to_string()), vec!["X0504"]));
+    }
+
+    #[test]
+    fn checks() {
+        // b reads a global, c and d divide by zero, the if and the first
+        // while have constant conditions, the second while is fine
+        let source = "int a = 1;\nint b = a + 1;\nint c = 10 % 0;\nvoid main(){\nint d = a / 0;\nif (1) {\n}\nwhile (0) {\n}\nwhile (1) {\na = 2;\n}\n}\n";
+        let diagnostics = check_constants("test.x", &parse(source));
+        let codes: Vec<(&str, i32)> = diagnostics.iter().map(|d| (d.code, d.span.start_line)).collect();
+        assert_eq!(codes, [("X0502", 1), ("X0500", 2), ("X0500", 4), ("X0503", 5), ("X0503", 7)]);
+        assert_eq!(diagnostics[0].message, "The initializer of the global 'b' is not a constant expression.");
+    }
+
+    #[test]
+    fn folding() {
+        let mut program = parse("long day = 60 * 60 * 24;\nvoid main(){\nday = day - 2 * 3 + -(4 - 5) * 0.5 + (1 - 8);\n}\n");
+        fold(&mut program);
+        assert_eq!(program_source(&program), "long day = 86400;\nvoid main() {\n    day = day - 6 + 0.5 + - 7;\n}");
+    }
+}
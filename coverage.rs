@@ -0,0 +1,251 @@
+//Grammar coverage: how often stage3::Parser tried and matched each rule and
+//took each alternative of an ebnf_or!, summed over a corpus of X files, with
+//the rules and alternatives of x.ebnf that were never exercised.
+
+use std::collections::BTreeMap;
+use std::io;
+
+use crate::grammar::{Expr, Grammar};
+use crate::grammar_analysis::display;
+use crate::railroad::escape;
+use crate::diagnostics::read_source;
+use crate::stage2_scanner::Scanner;
+use crate::stage3::Parser;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Coverage {
+    // how often each rule was tried and how often it matched
+    pub tried: BTreeMap<&'static str, usize>,
+    pub matched: BTreeMap<&'static str, usize>,
+    // how often an alternative was taken, by rule and zero-based position
+    // in the rule's first choice in x.ebnf
+    pub alternatives: BTreeMap<(&'static str, usize), usize>,
+    // how many files were parsed and how many of them parsed
+    pub files: usize,
+    pub files_ok: usize,
+}
+
+// one rule of the grammar with the alternatives of its first choice
+struct Production {
+    name: String,
+    alternatives: Vec<String>,
+}
+
+fn first_choice(expr: &Expr) -> Option<&Vec<Expr>> {
+    match expr {
+        Expr::Choice(alternatives) => Some(alternatives),
+        Expr::Sequence(items) => items.iter().find_map(first_choice),
+        Expr::Optional(inner) | Expr::Repetition(inner) => first_choice(inner),
+        _ => None,
+    }
+}
+
+fn productions(grammar: &Grammar) -> Vec<Production> {
+    grammar
+        .rules
+        .iter()
+        .map(|rule| Production {
+            name: rule.name.clone(),
+            alternatives: first_choice(&rule.expr)
+                .map(|alternatives| alternatives.iter().map(display).collect())
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+impl Coverage {
+    pub fn rule(&mut self, rule: &'static str, matched: bool) {
+        *self.tried.entry(rule).or_insert(0) += 1;
+        if matched {
+            *self.matched.entry(rule).or_insert(0) += 1;
+        }
+    }
+
+    pub fn alternative(&mut self, rule: &'static str, alternative: usize) {
+        *self.alternatives.entry((rule, alternative)).or_insert(0) += 1;
+    }
+
+    // parses a file and adds what it exercised
+    pub fn add_file(&mut self, xfilename: &str) -> io::Result<()> {
+        self.add_source(xfilename, &read_source(xfilename)?);
+        Ok(())
+    }
+
+    pub fn add_source(&mut self, xfilename: &str, source: &str) {
+        let mut parser = Parser::from_tokens(Scanner::from_source(xfilename, source).run().all_tokens.clone());
+        parser.coverage = Some(std::mem::take(self));
+        let ok = parser.parse().is_ok();
+        *self = parser.coverage.take().unwrap();
+        self.files += 1;
+        if ok {
+            self.files_ok += 1;
+        }
+    }
+
+    fn times_matched(&self, rule: &str) -> usize {
+        self.matched.get(rule).copied().unwrap_or(0)
+    }
+
+    fn times_tried(&self, rule: &str) -> usize {
+        self.tried.get(rule).copied().unwrap_or(0)
+    }
+
+    fn times_taken(&self, rule: &str, alternative: usize) -> usize {
+        self.alternatives
+            .iter()
+            .find(|((name, i), _)| *name == rule && *i == alternative)
+            .map_or(0, |(_, n)| *n)
+    }
+
+    // the rules never matched and the alternatives never taken, e.g.
+    // "Statement alternative 3: IfStatement"
+    pub fn missed(&self, grammar: &Grammar) -> Vec<String> {
+        let mut res = Vec::new();
+        for production in productions(grammar) {
+            if self.times_matched(&production.name) == 0 {
+                res.push(format!("rule {}", production.name));
+            }
+            for (i, alternative) in production.alternatives.iter().enumerate() {
+                if self.times_taken(&production.name, i) == 0 {
+                    res.push(format!("{} alternative {}: {}", production.name, i + 1, alternative));
+                }
+            }
+        }
+        res
+    }
+
+    // (covered, total) for rules and for alternatives
+    fn totals(&self, grammar: &Grammar) -> ((usize, usize), (usize, usize)) {
+        let mut rules = (0, 0);
+        let mut alternatives = (0, 0);
+        for production in productions(grammar) {
+            rules.1 += 1;
+            if self.times_matched(&production.name) > 0 {
+                rules.0 += 1;
+            }
+            for i in 0..production.alternatives.len() {
+                alternatives.1 += 1;
+                if self.times_taken(&production.name, i) > 0 {
+                    alternatives.0 += 1;
+                }
+            }
+        }
+        (rules, alternatives)
+    }
+
+    pub fn report(&self, grammar: &Grammar) -> String {
+        let ((rules, all_rules), (alternatives, all_alternatives)) = self.totals(grammar);
+        let mut res = format!(
+            "Grammar coverage over {} files ({} parsed): {}/{} rules, {}/{} alternatives\n\n",
+            self.files, self.files_ok, rules, all_rules, alternatives, all_alternatives
+        );
+
+        res += "Rules (tried / matched)\n";
+        for production in productions(grammar) {
+            res += &format!(
+                "    {:<20} {:>6} {:>6}\n",
+                production.name,
+                self.times_tried(&production.name),
+                self.times_matched(&production.name)
+            );
+            for (i, alternative) in production.alternatives.iter().enumerate() {
+                res += &format!(
+                    "        {:>2} {:<40} {:>6}\n",
+                    i + 1,
+                    alternative,
+                    self.times_taken(&production.name, i)
+                );
+            }
+        }
+
+        let missed = self.missed(grammar);
+        res += &format!("\nNever exercised: {}\n", missed.len());
+        for line in missed {
+            res += &format!("    {}\n", line);
+        }
+        res
+    }
+
+    pub fn to_html(&self, grammar: &Grammar) -> String {
+        let ((rules, all_rules), (alternatives, all_alternatives)) = self.totals(grammar);
+        let mut res = r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd">
+<html xmlns="http://www.w3.org/1999/xhtml" xml:lang="en">
+<head>
+<title>X Grammar Coverage</title>
+</head>
+<body bgcolor="navy" text="orange" link="white" vlink="white">
+<font face="Courier New">
+<h1>X Grammar Coverage</h1>
+"#
+        .to_string();
+        res += &format!(
+            "<p>{} files ({} parsed): {}/{} rules, {}/{} alternatives</p>\n",
+            self.files, self.files_ok, rules, all_rules, alternatives, all_alternatives
+        );
+
+        // rows never exercised are red
+        let row = |cells: &[String], hit: bool| -> String {
+            let color = if hit { "" } else { " bgcolor=\"red\"" };
+            let cells: Vec<String> = cells.iter().map(|cell| format!("<td>{}</td>", cell)).collect();
+            format!("<tr{}>{}</tr>\n", color, cells.join(""))
+        };
+        res += "<table border=\"1\">\n<tr><th>rule</th><th>alternative</th><th>tried</th><th>matched or taken</th></tr>\n";
+        for production in productions(grammar) {
+            let matched = self.times_matched(&production.name);
+            res += &row(
+                &[
+                    escape(&production.name),
+                    "".to_string(),
+                    self.times_tried(&production.name).to_string(),
+                    matched.to_string(),
+                ],
+                matched > 0,
+            );
+            for (i, alternative) in production.alternatives.iter().enumerate() {
+                let taken = self.times_taken(&production.name, i);
+                res += &row(
+                    &[
+                        "".to_string(),
+                        format!("{}. {}", i + 1, escape(alternative)),
+                        "".to_string(),
+                        taken.to_string(),
+                    ],
+                    taken > 0,
+                );
+            }
+        }
+        res += "</table>\n</font>\n</body>\n</html>\n";
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corpus_coverage() {
+        let grammar = Grammar::x();
+        let mut coverage = Coverage::default();
+        coverage.add_file("example1.x").unwrap();
+        coverage.add_file("example2.x").unwrap();
+        assert_eq!((coverage.files, coverage.files_ok), (2, 2));
+
+        // both examples are full programs
+        assert_eq!(coverage.times_matched("Program"), 2);
+        // example2 has an if statement, neither example uses a prefix operator
+        assert!(coverage.times_taken("Statement", 2) > 0);
+        let missed = coverage.missed(&grammar);
+        assert!(missed.contains(&"rule PrefixOperator".to_string()));
+        assert!(missed.contains(&"BinaryOperator alternative 2: ||".to_string()));
+        assert!(missed.contains(&"Statement alternative 5: Expression ;".to_string()));
+        assert!(!missed.iter().any(|line| line.starts_with("Statement alternative 3")));
+
+        let report = coverage.report(&grammar);
+        assert!(report.starts_with("Grammar coverage over 2 files (2 parsed): 22/23 rules, "));
+        assert!(report.contains(&format!("\n        12 {:<40} {:>6}\n", ">=", 1)));
+        let html = coverage.to_html(&grammar);
+        assert!(html.contains("<tr bgcolor=\"red\"><td></td><td>2. ||</td><td></td><td>0</td></tr>"));
+        assert!(html.contains("<td>9. &lt;</td>"));
+    }
+}
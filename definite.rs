@@ -0,0 +1,250 @@
+//Definite assignment: a local variable declared without an initializer must
+//be assigned before it is read. The pass walks each function body forward
+//with the variables that may still be unassigned and those that surely are.
+//A condition splits the path in two: the body of an if or while runs where
+//it is true, and the paths meet again after the body, so an assignment in
+//the right operand of && counts in the body but not after it. Nothing is
+//unassigned after a return. Globals are
+//zero-initialized like in C, and a nested function can read the variables
+//of the blocks around it, which are taken to be assigned when it is called.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{Block, Declaration, Expression, ExpressionKind, FunctionDefinition, Program, StatementKind};
+use crate::diagnostics::{Diagnostic, Related, Severity, Span};
+use crate::resolve::{SymbolKind, SymbolTable};
+
+// the locals that are not yet assigned on some path and on every path; None
+// where the code cannot be reached
+#[derive(Clone, Default)]
+struct State {
+    maybe: HashSet<usize>,
+    surely: HashSet<usize>,
+}
+
+impl State {
+    fn assign(&mut self, symbol: usize) {
+        self.maybe.remove(&symbol);
+        self.surely.remove(&symbol);
+    }
+}
+
+// the state where two paths join
+fn merge(lhs: Option<State>, rhs: Option<State>) -> Option<State> {
+    match (lhs, rhs) {
+        (Some(lhs), Some(rhs)) => Some(State {
+            maybe: lhs.maybe.union(&rhs.maybe).copied().collect(),
+            surely: lhs.surely.intersection(&rhs.surely).copied().collect(),
+        }),
+        (state, None) | (None, state) => state,
+    }
+}
+
+struct Checker<'a> {
+    xfilename: &'a str,
+    table: &'a SymbolTable,
+    // the local symbol of each declared name, by the span of the name
+    locals: HashMap<Span, usize>,
+    // a variable is reported at its first bad read only
+    reported: HashSet<usize>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    fn read(&mut self, symbol: usize, span: Span, state: &State) {
+        if !state.maybe.contains(&symbol) || !self.reported.insert(symbol) {
+            return;
+        }
+        let name = &self.table.symbols[symbol].name;
+        let message = if state.surely.contains(&symbol) {
+            format!("'{}' is read before it is assigned.", name)
+        } else {
+            format!("'{}' may be read before it is assigned, on a path that skips its assignments.", name)
+        };
+        let related = vec![Related {
+            span: self.table.symbols[symbol].span,
+            message: format!("'{}' is declared here without an initializer", name),
+        }];
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            code: "X0400",
+            message,
+            file: self.xfilename.to_string(),
+            span,
+            related,
+        });
+    }
+
+    fn expression(&mut self, expression: &Expression, state: &mut State) {
+        match &expression.kind {
+            ExpressionKind::Constant(_) => {}
+            ExpressionKind::Identifier(name) => {
+                let span = Span::of_token(name);
+                if let Some(&symbol) = self.table.uses.get(&span) {
+                    self.read(symbol, span, state);
+                }
+            }
+            ExpressionKind::Call(_, arguments) => {
+                for argument in arguments.iter() {
+                    self.expression(argument, state);
+                }
+            }
+            ExpressionKind::Unary(_, operand) => self.expression(operand, state),
+            ExpressionKind::Binary(operator, _, _) if operator.text == "&&" || operator.text == "||" => {
+                // the right operand may not be evaluated
+                let (when_true, when_false) = self.condition(expression, state);
+                *state = merge(Some(when_true), Some(when_false)).unwrap();
+            }
+            ExpressionKind::Binary(_, lhs, rhs) => {
+                self.expression(lhs, state);
+                self.expression(rhs, state);
+            }
+            ExpressionKind::Assign(target, value) => {
+                self.expression(value, state);
+                if let Some(&symbol) = self.table.uses.get(&Span::of_token(target)) {
+                    state.assign(symbol);
+                }
+            }
+        }
+    }
+
+    // the states where a condition is true and where it is false
+    fn condition(&mut self, condition: &Expression, state: &State) -> (State, State) {
+        match &condition.kind {
+            ExpressionKind::Binary(operator, lhs, rhs) if operator.text == "&&" || operator.text == "||" => {
+                let (lhs_true, lhs_false) = self.condition(lhs, state);
+                if operator.text == "&&" {
+                    let (rhs_true, rhs_false) = self.condition(rhs, &lhs_true);
+                    (rhs_true, merge(Some(lhs_false), Some(rhs_false)).unwrap())
+                } else {
+                    let (rhs_true, rhs_false) = self.condition(rhs, &lhs_false);
+                    (merge(Some(lhs_true), Some(rhs_true)).unwrap(), rhs_false)
+                }
+            }
+            ExpressionKind::Unary(operator, operand) if operator.text == "!" => {
+                let (when_true, when_false) = self.condition(operand, state);
+                (when_false, when_true)
+            }
+            _ => {
+                let mut state = state.clone();
+                self.expression(condition, &mut state);
+                (state.clone(), state)
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block, state: &mut Option<State>) {
+        for declaration in block.declarations.iter() {
+            if let (Declaration::Variable(variable), Some(state)) = (declaration, state.as_mut()) {
+                match &variable.initializer {
+                    Some(initializer) => self.expression(initializer, state),
+                    None => {
+                        if let Some(&symbol) = self.locals.get(&Span::of_token(&variable.name)) {
+                            state.maybe.insert(symbol);
+                            state.surely.insert(symbol);
+                        }
+                    }
+                }
+            }
+        }
+        for statement in block.statements.iter() {
+            // unreachable statements are not checked
+            let current = match state.as_mut() {
+                Some(current) => current,
+                None => break,
+            };
+            match &statement.kind {
+                StatementKind::Expression(expression) => self.expression(expression, current),
+                StatementKind::While(condition, body) | StatementKind::If(condition, body) => {
+                    let (when_true, when_false) = self.condition(condition, current);
+                    let mut after_body = Some(when_true);
+                    self.block(body, &mut after_body);
+                    *state = merge(Some(when_false), after_body);
+                }
+                StatementKind::Return(value) => {
+                    self.expression(value, current);
+                    *state = None;
+                }
+            }
+        }
+        for function in block.functions.iter() {
+            self.function(function);
+        }
+    }
+
+    fn function(&mut self, function: &FunctionDefinition) {
+        self.block(&function.block, &mut Some(State::default()));
+    }
+}
+
+// warnings for the local variables of a resolved program that may be read
+// before they are assigned, sorted by position
+pub fn check_assignments(xfilename: &str, program: &Program, table: &SymbolTable) -> Vec<Diagnostic> {
+    let locals = table
+        .symbols
+        .iter()
+        .enumerate()
+        .filter(|(_, symbol)| symbol.kind == SymbolKind::Local)
+        .map(|(i, symbol)| (symbol.span, i))
+        .collect();
+    let mut checker = Checker {
+        xfilename,
+        table,
+        locals,
+        reported: HashSet::new(),
+        diagnostics: Vec::new(),
+    };
+    checker.block(&program.main.block, &mut Some(State::default()));
+    for function in program.functions.iter() {
+        checker.function(function);
+    }
+    let mut diagnostics = checker.diagnostics;
+    diagnostics.sort_by_key(|d| (d.span.start_line, d.span.start_char));
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::test_support::{codes, parse};
+    use crate::resolve::resolve;
+
+    fn check_source(source: &str) -> Vec<Diagnostic> {
+        let program = parse(source);
+        let (table, _) = resolve("test.x", &program);
+        check_assignments("test.x", &program, &table)
+    }
+
+    #[test]
+    fn examples() {
+        assert_eq!(check_source(&std::fs::read_to_string("example1.x").unwrap()), Vec::new());
+
+        // R1 is only assigned in the if blocks of Bar
+        let diagnostics = check_source(&std::fs::read_to_string("example2.x").unwrap());
+        assert_eq!(codes(&diagnostics), [("X0400", 33, 16)]);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "test.x:33:16-33:18: warning[X0400]: 'R1' may be read before it is assigned, on a path that skips its assignments.\ntest.x:22:8-22:10: note: 'R1' is declared here without an initializer"
+        );
+    }
+
+    #[test]
+    fn paths() {
+        // a and b are read unassigned, c only in the right operand of &&, d
+        // is assigned on every path before its read, e is read after a loop
+        // that may not run, and nothing is checked after a return
+        let source = "int g;\nvoid main(){\nint a;\nint b;\nint c;\nint d;\nint e;\na = a + b;\nb = 1;\nif (g && (c = 1)) {\n}\ng = c;\nif (g) {\nd = 1;\n}\nd = 2;\ng = d;\nwhile (g) {\ne = g;\n}\ng = e;\nreturn g;\ng = a;\n}\n";
+        let diagnostics = check_source(source);
+        assert_eq!(codes(&diagnostics), [("X0400", 7, 4), ("X0400", 7, 8), ("X0400", 11, 4), ("X0400", 20, 4)]);
+        assert!(diagnostics[0].message.starts_with("'a' is read before"));
+        assert!(diagnostics[2].message.starts_with("'c' may be read before"));
+
+        // an initializer reads like any other expression
+        let source = "void main(){\nint a;\nint b = a + 1;\n}\n";
+        assert_eq!(codes(&check_source(source)), [("X0400", 2, 8)]);
+
+        // a nested function reads the variables around it as assigned
+        let source = "void main(){\n}\nint f(int p) {\nint x;\nint g(int q);\nreturn g(p);\nint g(int q) { return x + q; }\n}\n";
+        assert_eq!(check_source(source), Vec::new());
+    }
+}
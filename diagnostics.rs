@@ -0,0 +1,393 @@
+//Diagnostics reported by the scanner, the parser and the semantic passes,
+//and the writers that serialise them for CI tools: JSON lines and SARIF 2.1.0.
+
+use std::fmt;
+use std::fs;
+use std::io;
+
+use crate::cfg::check_control_flow;
+use crate::consteval::check_constants;
+use crate::definite::check_assignments;
+use crate::lint::RULES;
+use crate::resolve::resolve;
+use crate::stage2_scanner::Scanner;
+use crate::stage2_token::{Token, TokenType};
+use crate::stage3::{Parser, SyntaxError};
+use crate::typecheck::check_types;
+
+// every diagnostic code the toolchain can report, with a short description
+// (the SARIF rules table is built from this list and the lint rules)
+pub const CODES: &[(&str, &str)] = &[
+    ("X0001", "invalid token"),
+    ("X0100", "syntax error"),
+    ("X0200", "undeclared identifier"),
+    ("X0201", "duplicate declaration"),
+    ("X0202", "shadowed declaration"),
+    ("X0203", "conflicting function declaration"),
+    ("X0204", "undefined function"),
+    ("X0205", "call of a variable"),
+    ("X0206", "function used as a variable"),
+    ("X0207", "wrong number of arguments"),
+    ("X0300", "lossy implicit conversion"),
+    ("X0301", "invalid operand type"),
+    ("X0302", "return value from void function"),
+    ("X0303", "suspicious condition"),
+    ("X0400", "uninitialized variable"),
+    ("X0401", "missing return"),
+    ("X0402", "unreachable code"),
+    ("X0500", "division by zero"),
+    ("X0501", "integer overflow"),
+    ("X0502", "non-constant global initializer"),
+    ("X0503", "constant condition"),
+    ("X0504", "integer constant out of range"),
+    ("X0600", "division by zero at run time"),
+    ("X0601", "signed overflow at run time"),
+    ("X0602", "read of an unassigned variable"),
+    ("X0603", "floating value out of integer range"),
+    ("X0604", "call depth exceeded"),
+    ("X0605", "invalid or missing input"),
+    ("X0606", "step limit exceeded"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+// a source range; lines and characters are zero-based like in Token,
+// and the end character is exclusive
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start_line: i32,
+    pub start_char: i32,
+    pub end_line: i32,
+    pub end_char: i32,
+}
+
+impl Span {
+    pub fn of_token(token: &Token) -> Span {
+        Span {
+            start_line: token.line_num,
+            start_char: token.char_pos,
+            end_line: token.line_num,
+            end_char: token.char_pos + token.text.chars().count() as i32,
+        }
+    }
+
+    // whether inner lies within the span
+    pub(crate) fn contains(&self, inner: &Span) -> bool {
+        (self.start_line, self.start_char) <= (inner.start_line, inner.start_char)
+            && (inner.end_line, inner.end_char) <= (self.end_line, self.end_char)
+    }
+
+    // the span of the token starting at (line_num, char_pos), or an empty
+    // span there if no such token exists
+    pub fn at(tokens: &[Token], line_num: i32, char_pos: i32) -> Span {
+        match tokens
+            .iter()
+            .find(|token| token.line_num == line_num && token.char_pos == char_pos)
+        {
+            Some(token) => Span::of_token(token),
+            None => Span {
+                start_line: line_num,
+                start_char: char_pos,
+                end_line: line_num,
+                end_char: char_pos,
+            },
+        }
+    }
+}
+
+// start_line:start_char-end_line:end_char, zero-based
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}-{}:{}", self.start_line, self.start_char, self.end_line, self.end_char)
+    }
+}
+
+// another place in the same file that a diagnostic refers to, e.g. the
+// first declaration of a duplicate
+#[derive(Clone, Debug, PartialEq)]
+pub struct Related {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub file: String,
+    pub span: Span,
+    pub related: Vec<Related>,
+}
+
+// file:span: severity[code]: message, then file:span: note: message for
+// every related span
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}[{}]: {}",
+            self.file,
+            self.span,
+            self.severity.as_str(),
+            self.code,
+            self.message
+        )?;
+        for related in self.related.iter() {
+            write!(f, "\n{}:{}: note: {}", self.file, related.span, related.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl Diagnostic {
+    pub fn from_syntax_error(xfilename: &str, tokens: &[Token], error: &SyntaxError) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code: "X0100",
+            message: format!("The syntax should be: {}.", error.ebnf),
+            file: xfilename.to_string(),
+            span: Span::at(tokens, error.line_num, error.char_pos),
+            related: Vec::new(),
+        }
+    }
+}
+
+// one diagnostic per token the scanner could not classify
+pub fn lexer_diagnostics(xfilename: &str, tokens: &[Token]) -> Vec<Diagnostic> {
+    tokens
+        .iter()
+        .filter(|token| token.token_type == TokenType::Invalid)
+        .map(|token| Diagnostic {
+            severity: Severity::Error,
+            code: "X0001",
+            message: format!("Invalid token '{}'.", token.text),
+            file: xfilename.to_string(),
+            span: Span::of_token(token),
+            related: Vec::new(),
+        })
+        .collect()
+}
+
+// the contents of a .x file; bytes that are not UTF-8 become U+FFFD, which
+// the scanner reports as an invalid token
+pub fn read_source(xfilename: &str) -> io::Result<String> {
+    Ok(String::from_utf8_lossy(&fs::read(xfilename)?).to_string())
+}
+
+// scans and parses a .x file and returns everything that went wrong, or the
+// error that kept it from being read
+pub fn check_file(xfilename: &str) -> io::Result<Vec<Diagnostic>> {
+    Ok(check_source(xfilename, &read_source(xfilename)?))
+}
+
+// check_file on the contents of a file that need not exist
+pub fn check_source(xfilename: &str, contents: &str) -> Vec<Diagnostic> {
+    let tokens = Scanner::from_source(xfilename, contents).run().all_tokens.clone();
+    let mut diagnostics = lexer_diagnostics(xfilename, &tokens);
+
+    let mut parser = Parser::from_tokens(tokens.clone());
+    match parser.parse() {
+        Ok(mut program) => {
+            let (table, mut semantic) = resolve(xfilename, &program);
+            semantic.extend(check_types(xfilename, &mut program, &table));
+            semantic.extend(check_assignments(xfilename, &program, &table));
+            semantic.extend(check_control_flow(xfilename, &program));
+            semantic.extend(check_constants(xfilename, &program));
+            semantic.sort_by_key(|d| (d.span.start_line, d.span.start_char));
+            diagnostics.extend(semantic);
+        }
+        Err(e) => diagnostics.push(Diagnostic::from_syntax_error(xfilename, &tokens, &e)),
+    }
+
+    diagnostics
+}
+
+// quotes and escapes a string as a JSON string literal
+pub fn json_string(text: &str) -> String {
+    let mut res = "\"".to_string();
+    for c in text.chars() {
+        match c {
+            '"' => res += "\\\"",
+            '\\' => res += "\\\\",
+            '\n' => res += "\\n",
+            '\r' => res += "\\r",
+            '\t' => res += "\\t",
+            c if (c as u32) < 0x20 => res += &format!("\\u{:04x}", c as u32),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+fn json_span(span: &Span) -> String {
+    format!(
+        "{{\"start\":{{\"line\":{},\"char\":{}}},\"end\":{{\"line\":{},\"char\":{}}}}}",
+        span.start_line, span.start_char, span.end_line, span.end_char
+    )
+}
+
+// one JSON object per line, positions are zero-based as in Token; related
+// spans are listed only if there are any
+pub fn to_json_lines(diagnostics: &[Diagnostic]) -> String {
+    let mut res = "".to_string();
+    for d in diagnostics {
+        res += &format!(
+            "{{\"severity\":{},\"code\":{},\"message\":{},\"file\":{},\"span\":{}",
+            json_string(d.severity.as_str()),
+            json_string(d.code),
+            json_string(&d.message),
+            json_string(&d.file),
+            json_span(&d.span),
+        );
+        if !d.related.is_empty() {
+            let related: Vec<String> = d
+                .related
+                .iter()
+                .map(|related| {
+                    format!("{{\"message\":{},\"span\":{}}}", json_string(&related.message), json_span(&related.span))
+                })
+                .collect();
+            res += &format!(",\"related\":[{}]", related.join(","));
+        }
+        res += "}\n";
+    }
+    res
+}
+
+fn sarif_location(file: &str, span: &Span) -> String {
+    format!(
+        "\"physicalLocation\":{{\"artifactLocation\":{{\"uri\":{}}},\"region\":{{\"startLine\":{},\"startColumn\":{},\"endLine\":{},\"endColumn\":{}}}}}",
+        json_string(file),
+        span.start_line + 1,
+        span.start_char + 1,
+        span.end_line + 1,
+        span.end_char + 1,
+    )
+}
+
+// a SARIF 2.1.0 log with a single run; SARIF regions are one-based
+pub fn to_sarif(diagnostics: &[Diagnostic]) -> String {
+    let rules: Vec<String> = CODES
+        .iter()
+        .copied()
+        .chain(RULES.iter().map(|rule| (rule.id, rule.description)))
+        .map(|(code, description)| {
+            format!(
+                "{{\"id\":{},\"shortDescription\":{{\"text\":{}}}}}",
+                json_string(code),
+                json_string(description)
+            )
+        })
+        .collect();
+
+    let results: Vec<String> = diagnostics
+        .iter()
+        .map(|d| {
+            let mut res = format!(
+                "{{\"ruleId\":{},\"level\":{},\"message\":{{\"text\":{}}},\"locations\":[{{{}}}]",
+                json_string(d.code),
+                json_string(d.severity.as_str()),
+                json_string(&d.message),
+                sarif_location(&d.file, &d.span),
+            );
+            if !d.related.is_empty() {
+                let related: Vec<String> = d
+                    .related
+                    .iter()
+                    .enumerate()
+                    .map(|(i, related)| {
+                        format!(
+                            "{{\"id\":{},\"message\":{{\"text\":{}}},{}}}",
+                            i,
+                            json_string(&related.message),
+                            sarif_location(&d.file, &related.span)
+                        )
+                    })
+                    .collect();
+                res += &format!(",\"relatedLocations\":[{}]", related.join(","));
+            }
+            res + "}"
+        })
+        .collect();
+
+    format!(
+        "{{\"$schema\":\"https://json.schemastore.org/sarif-2.1.0.json\",\"version\":\"2.1.0\",\"runs\":[{{\"tool\":{{\"driver\":{{\"name\":\"proj2zhang\",\"rules\":[{}]}}}},\"results\":[{}]}}]}}\n",
+        rules.join(","),
+        results.join(",")
+    )
+}
+
+// helpers shared by the tests of the checkers
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::Diagnostic;
+    use crate::ast::Program;
+    use crate::stage2_scanner::Scanner;
+    use crate::stage3::Parser;
+
+    // a parser over the tokens of source, as the file test.x
+    pub(crate) fn parser_of(source: &str) -> Parser {
+        Parser::from_tokens(Scanner::from_source("test.x", source).run().all_tokens.clone())
+    }
+
+    // the program of a syntactically correct source
+    pub(crate) fn parse(source: &str) -> Program {
+        parser_of(source).parse().unwrap()
+    }
+
+    // the code and start of every diagnostic, in order
+    pub(crate) fn codes(diagnostics: &[Diagnostic]) -> Vec<(&'static str, i32, i32)> {
+        diagnostics.iter().map(|d| (d.code, d.span.start_line, d.span.start_char)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostics_output() {
+        assert_eq!(check_file("example1.x").unwrap(), vec![]);
+        assert!(check_file("no_such_file.x").is_err());
+        assert_eq!(to_json_lines(&[]), "");
+        assert_eq!(json_string("a \"b\"\n"), "\"a \\\"b\\\"\\n\"");
+
+        let d = Diagnostic {
+            severity: Severity::Error,
+            code: "X0100",
+            message: "The syntax should be: MainDeclaration := void main ( ) Block.".to_string(),
+            file: "example3.x".to_string(),
+            span: Span {
+                start_line: 1,
+                start_char: 0,
+                end_line: 1,
+                end_char: 5,
+            },
+            related: Vec::new(),
+        };
+        assert_eq!(
+            to_json_lines(std::slice::from_ref(&d)),
+            "{\"severity\":\"error\",\"code\":\"X0100\",\"message\":\"The syntax should be: MainDeclaration := void main ( ) Block.\",\"file\":\"example3.x\",\"span\":{\"start\":{\"line\":1,\"char\":0},\"end\":{\"line\":1,\"char\":5}}}\n"
+        );
+        assert!(to_sarif(&[d]).contains(
+            "\"region\":{\"startLine\":2,\"startColumn\":1,\"endLine\":2,\"endColumn\":6}"
+        ));
+    }
+}
@@ -0,0 +1,3 @@
+float y = 1.5;
+Void main() {
+}
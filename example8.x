@@ -0,0 +1,4 @@
+unsigned int n;
+{
+    n = 1;
+}
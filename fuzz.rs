@@ -0,0 +1,196 @@
+//Fuzzing of the whole pipeline: CStream, Scanner, Parser, the diagnostics
+//and their writers, and the printer. The invariant is that no input panics,
+//every failure is a diagnostic, and a program that parses prints back to
+//source that parses to the same program. The structured fuzzer mutates
+//programs of the generator at the token level, with the keywords and
+//operators of x.ebnf, and at the byte level; the cargo-fuzz targets in
+//fuzz/ feed libFuzzer's inputs to the same check. A program that checks
+//without errors also runs in the interpreter, under a step limit so that
+//a loop that never ends is a runtime error and not a hang.
+
+use std::fs;
+
+use crate::diagnostics::{check_source, to_json_lines, to_sarif, Diagnostic};
+use crate::generator::{Generator, Rng};
+use crate::grammar::{Expr, Grammar};
+use crate::interpreter::run_source_limited;
+use crate::minimize::panics;
+use crate::printer::{layout, program_source};
+use crate::stage2_scanner::Scanner;
+use crate::stage3::Parser;
+
+// inputs that once panicked and seeds for libFuzzer, one file each
+pub const CORPUS_DIR: &str = "fuzz/corpus/pipeline";
+
+// the most expressions a fuzzed program may evaluate
+pub const MAX_STEPS: u64 = 100_000;
+
+// bytes the byte-level mutations insert: line ends, tabs, a NUL, and the
+// starts of multi-byte and invalid UTF-8
+const BYTES: &[u8] = b"\n\r\t \0.;(){}=<>!_9aZ\xc3\xa9\xff";
+
+// runs the pipeline on arbitrary bytes and returns the diagnostics
+pub fn check_bytes(data: &[u8]) -> Vec<Diagnostic> {
+    let source = String::from_utf8_lossy(data);
+    let diagnostics = check_source("fuzz.x", &source);
+    to_json_lines(&diagnostics);
+    to_sarif(&diagnostics);
+
+    let tokens = Scanner::from_source("fuzz.x", &source).run().all_tokens.clone();
+    if let Ok(program) = Parser::from_tokens(tokens).parse() {
+        let printed = program_source(&program);
+        let tokens = Scanner::from_source("fuzz.x", &printed).run().all_tokens.clone();
+        let reprinted = Parser::from_tokens(tokens).parse().map(|program| program_source(&program));
+        assert_eq!(reprinted.ok().as_ref(), Some(&printed), "the printed program does not parse back");
+    }
+    diagnostics
+}
+
+// runs arbitrary bytes in the interpreter, with no input and under
+// MAX_STEPS; a program with check errors is not run
+pub fn run_bytes(data: &[u8]) -> Result<String, Vec<Diagnostic>> {
+    let source = String::from_utf8_lossy(data);
+    let mut output = Vec::new();
+    run_source_limited("fuzz.x", &source, &mut "".as_bytes(), &mut output, Some(MAX_STEPS))
+        .map(|_| String::from_utf8_lossy(&output).into_owned())
+}
+
+// the keywords and operators of a grammar expression
+fn collect_terminals(expr: &Expr, terminals: &mut Vec<String>) {
+    match expr {
+        Expr::Keyword(text) | Expr::Operator(text) => {
+            if !terminals.contains(text) {
+                terminals.push(text.clone());
+            }
+        }
+        Expr::Token(_) | Expr::NonTerminal(_) => {}
+        Expr::Sequence(items) | Expr::Choice(items) => {
+            for item in items.iter() {
+                collect_terminals(item, terminals);
+            }
+        }
+        Expr::Optional(inner) | Expr::Repetition(inner) => collect_terminals(inner, terminals),
+    }
+}
+
+pub struct Fuzzer {
+    grammar: Grammar,
+    rng: Rng,
+    // the keywords and operators of the grammar, plus an identifier and constants
+    terminals: Vec<String>,
+}
+
+impl Fuzzer {
+    pub fn new(seed: u64) -> Fuzzer {
+        let grammar = Grammar::x();
+        let mut terminals: Vec<String> = ["x", "0", "1.5"].iter().map(|t| t.to_string()).collect();
+        for rule in grammar.rules.iter() {
+            collect_terminals(&rule.expr, &mut terminals);
+        }
+        Fuzzer {
+            grammar,
+            rng: Rng::new(seed),
+            terminals,
+        }
+    }
+
+    fn terminal(&mut self) -> String {
+        self.terminals[self.rng.below(self.terminals.len())].clone()
+    }
+
+    // a generated program with a few tokens deleted, duplicated, swapped,
+    // inserted or replaced
+    fn mutate_tokens(&mut self) -> Vec<String> {
+        let seed = self.rng.next_u64();
+        let max_depth = 1 + self.rng.below(8);
+        let semantic = self.rng.chance(50);
+        let mut tokens = Generator::new(&self.grammar, seed, max_depth, semantic).tokens();
+        for _ in 0..self.rng.below(4) {
+            let i = self.rng.below(tokens.len() + 1);
+            match self.rng.below(5) {
+                0 if i < tokens.len() => {
+                    tokens.remove(i);
+                }
+                1 if i < tokens.len() => tokens.insert(i, tokens[i].clone()),
+                2 if i + 1 < tokens.len() => tokens.swap(i, i + 1),
+                3 if i < tokens.len() => tokens[i] = self.terminal(),
+                _ => {
+                    let terminal = self.terminal();
+                    tokens.insert(i, terminal);
+                }
+            }
+        }
+        tokens
+    }
+
+    // the next input: a mutated program, sometimes with bytes flipped,
+    // inserted or cut off
+    pub fn input(&mut self) -> Vec<u8> {
+        let mut bytes = layout(&self.mutate_tokens()).into_bytes();
+        if self.rng.chance(40) {
+            for _ in 0..1 + self.rng.below(3) {
+                let i = self.rng.below(bytes.len() + 1);
+                match self.rng.below(3) {
+                    0 if i < bytes.len() => bytes[i] ^= 1 << self.rng.below(8),
+                    1 => bytes.truncate(i),
+                    _ => bytes.insert(i, BYTES[self.rng.below(BYTES.len())]),
+                }
+            }
+        }
+        bytes
+    }
+}
+
+// runs the pipeline on inputs of the structured fuzzer and returns the first
+// one that panics
+pub fn fuzz(seed: u64, iterations: usize) -> Option<Vec<u8>> {
+    let mut fuzzer = Fuzzer::new(seed);
+    (0..iterations)
+        .map(|_| fuzzer.input())
+        .find(|input| panics(|| {
+            check_bytes(input);
+            let _ = run_bytes(input);
+        }))
+}
+
+// the files of the regression corpus
+pub fn corpus() -> Vec<(String, Vec<u8>)> {
+    let mut res: Vec<(String, Vec<u8>)> = fs::read_dir(CORPUS_DIR)
+        .expect("error when reading the corpus!")
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            (path.display().to_string(), fs::read(&path).unwrap())
+        })
+        .collect();
+    res.sort();
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_panics() {
+        // a lone token is a syntax error at that token, an empty file one at its start
+        let diagnostics = check_bytes(b"x");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].code, diagnostics[0].span.start_char), ("X0100", 0));
+        assert_eq!(check_bytes(b"").len(), 1);
+        assert_eq!(check_bytes(b"void main(){\n}\n\n"), Vec::new());
+
+        // every input of the corpus, including the ones that used to panic
+        let corpus = corpus();
+        assert!(corpus.iter().any(|(path, _)| path.ends_with("empty.x")));
+        for (path, input) in corpus {
+            assert!(!panics(|| {
+                check_bytes(&input);
+                let _ = run_bytes(&input);
+            }), "{} panics", path);
+        }
+        assert_eq!(run_bytes(b"void main(){\nprint_int(6 * 7);\n}\n"), Ok("42".to_string()));
+        let diagnostics = run_bytes(b"void main(){\nwhile (1) {\n}\n}\n").unwrap_err();
+        assert_eq!(diagnostics[0].code, "X0606");
+        assert_eq!(fuzz(1, 300), None);
+    }
+}
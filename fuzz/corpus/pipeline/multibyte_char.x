@@ -0,0 +1,2 @@
+é
+}
\ No newline at end of file
@@ -0,0 +1,3 @@
+int	éx;
+void main(){
+}
\ No newline at end of file
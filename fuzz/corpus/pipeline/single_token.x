@@ -0,0 +1 @@
+v
\ No newline at end of file
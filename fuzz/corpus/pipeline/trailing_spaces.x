@@ -0,0 +1,3 @@
+void main(){
+x = 1; 
+}   
\ No newline at end of file
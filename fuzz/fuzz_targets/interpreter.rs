@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// arbitrary bytes that check without errors run in the interpreter, under a
+// step limit; a runtime error is fine, a panic is a bug
+fuzz_target!(|data: &[u8]| {
+    let _ = proj2zhang::fuzz::run_bytes(data);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// arbitrary bytes through the scanner, the parser, the diagnostics and the
+// printer; any panic is a bug
+fuzz_target!(|data: &[u8]| {
+    proj2zhang::fuzz::check_bytes(data);
+});
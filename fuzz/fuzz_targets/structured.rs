@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// the first eight bytes seed the grammar-aware fuzzer, which mutates a
+// generated program, so libFuzzer explores mostly well-formed programs
+fuzz_target!(|data: &[u8]| {
+    let mut seed = [0u8; 8];
+    for (i, byte) in data.iter().take(8).enumerate() {
+        seed[i] = *byte;
+    }
+    let mut fuzzer = proj2zhang::fuzz::Fuzzer::new(u64::from_le_bytes(seed));
+    proj2zhang::fuzz::check_bytes(&fuzzer.input());
+});
@@ -0,0 +1,547 @@
+//Random X programs for stress tests. The generator walks x.ebnf from its start
+//rule, taking random alternatives, optional parts and repetitions; past the
+//depth limit it takes the shortest way out of every rule. Expressions come
+//from the operator table instead of the flat Expression rule, so they respect
+//precedence, associativity and the Identifier left of =, and every program
+//parses with stage3::Parser. The semantic mode also declares variables and
+//functions before they are used, calls the functions it defines with as
+//many arguments as they have parameters, gives the operators that need
+//integers only integer operands, returns a value only from functions,
+//ends the body of every function with a return, initializes globals with
+//constant expressions and never divides by a constant zero.
+
+use std::collections::HashMap;
+
+use crate::grammar::{Expr, Grammar};
+use crate::operators::{Associativity, Fixity, Operator, OPERATORS};
+use crate::printer::layout;
+use crate::stage2_token::TokenType;
+
+// the precedence of an expression without an operator on top
+const ATOM: u8 = u8::MAX;
+
+// the identifiers of the syntactic mode, which are never declared
+const NAMES: &[&str] = &["a", "b", "x", "count", "Foo", "val"];
+
+// a small deterministic random number generator (SplitMix64), so that a seed
+// always gives the same program
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // a number in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    pub fn chance(&mut self, percent: u64) -> bool {
+        self.next_u64() % 100 < percent
+    }
+}
+
+// the names a block declares in the semantic mode
+#[derive(Default)]
+struct Scope {
+    // name and whether its type is floating
+    variables: Vec<(String, bool)>,
+    // name, number of parameters and whether the return type is floating
+    functions: Vec<(String, usize, bool)>,
+    // whether the block is the body of a function, which ends with a return
+    body: bool,
+}
+
+// the operators whose operands typecheck requires to be integers
+fn needs_integer(operator: &Operator) -> bool {
+    matches!(operator.text, "%" | "&" | "|" | "^" | "~")
+}
+
+// the operators whose result is floating if an operand is; the others give int
+fn keeps_type(operator: &Operator) -> bool {
+    matches!(operator.text, "=" | "+" | "-" | "*" | "/")
+}
+
+pub struct Generator<'g> {
+    grammar: &'g Grammar,
+    rng: Rng,
+    max_depth: usize,
+    semantic: bool,
+    // the height of the shortest derivation of each rule
+    heights: HashMap<&'g str, usize>,
+    tokens: Vec<String>,
+    // the visible names, innermost block last
+    scopes: Vec<Scope>,
+    // the name of the last DeclarationType and the parameters of the last
+    // ParameterBlock, with whether their types are floating
+    declared: (String, bool),
+    parameters: Vec<(String, bool)>,
+    // the parameters of a FunctionDefinition, declared in its Block
+    block_parameters: Vec<(String, bool)>,
+    // whether the next Block is the body of a FunctionDefinition
+    body_next: bool,
+    // whether the expressions must be constant, in a global initializer
+    constant: bool,
+    // whether each MainDeclaration or FunctionDefinition around can return
+    // a value: main returns void
+    returns_value: Vec<bool>,
+    // how many names were made up so far
+    names: usize,
+}
+
+// the height of the shortest derivation of an expression, None while unknown
+fn height(expr: &Expr, heights: &HashMap<&str, usize>) -> Option<usize> {
+    match expr {
+        Expr::Keyword(_) | Expr::Operator(_) | Expr::Token(_) => Some(0),
+        Expr::NonTerminal(name) => heights.get(name.as_str()).copied(),
+        Expr::Sequence(items) => items
+            .iter()
+            .map(|item| height(item, heights))
+            .try_fold(0, |max, h| h.map(|h| max.max(h))),
+        Expr::Choice(alternatives) => alternatives.iter().filter_map(|a| height(a, heights)).min(),
+        Expr::Optional(_) | Expr::Repetition(_) => Some(0),
+    }
+}
+
+fn heights(grammar: &Grammar) -> HashMap<&str, usize> {
+    let mut heights: HashMap<&str, usize> = HashMap::new();
+    loop {
+        let mut changed = false;
+        for rule in grammar.rules.iter() {
+            if let Some(h) = height(&rule.expr, &heights) {
+                if heights.get(rule.name.as_str()).is_none_or(|old| h + 1 < *old) {
+                    heights.insert(rule.name.as_str(), h + 1);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return heights;
+        }
+    }
+}
+
+impl<'g> Generator<'g> {
+    pub fn new(grammar: &'g Grammar, seed: u64, max_depth: usize, semantic: bool) -> Generator<'g> {
+        Generator {
+            grammar,
+            rng: Rng::new(seed),
+            max_depth,
+            semantic,
+            heights: heights(grammar),
+            tokens: Vec::new(),
+            scopes: Vec::new(),
+            declared: (String::new(), false),
+            parameters: Vec::new(),
+            block_parameters: Vec::new(),
+            body_next: false,
+            constant: false,
+            returns_value: Vec::new(),
+            names: 0,
+        }
+    }
+
+    // the tokens of the next random program
+    pub fn tokens(&mut self) -> Vec<String> {
+        self.tokens.clear();
+        self.scopes = vec![Scope::default()];
+        self.names = 0;
+        let grammar = self.grammar;
+        self.rule(&grammar.start_rule().name, 0);
+        std::mem::take(&mut self.tokens)
+    }
+
+    pub fn program(&mut self) -> String {
+        layout(&self.tokens())
+    }
+
+    fn rule(&mut self, name: &'g str, depth: usize) {
+        if name == "Expression" {
+            let (tokens, _) = self.expression(self.max_depth.saturating_sub(depth), false);
+            self.tokens.extend(tokens);
+            return;
+        }
+
+        match name {
+            "Block" => self.scopes.push(Scope {
+                variables: std::mem::take(&mut self.block_parameters),
+                functions: Vec::new(),
+                body: std::mem::take(&mut self.body_next),
+            }),
+            "ParameterBlock" => self.parameters.clear(),
+            "MainDeclaration" | "FunctionDefinition" => self.returns_value.push(name == "FunctionDefinition"),
+            "VariableDeclaration" => self.constant = self.semantic && self.scopes.len() == 1,
+            _ => {}
+        }
+        let grammar = self.grammar;
+        let rule = grammar.rule(name).unwrap_or_else(|| panic!("the grammar has no rule {}", name));
+        self.walk(&rule.expr, name, depth + 1);
+        match name {
+            "Block" => {
+                self.scopes.pop();
+            }
+            "MainDeclaration" | "FunctionDefinition" => {
+                self.returns_value.pop();
+            }
+            "VariableDeclaration" => {
+                self.constant = false;
+                let name = self.declared.clone();
+                self.scopes.last_mut().unwrap().variables.push(name);
+            }
+            // a function is visible from its parameters on, so it can call itself
+            "ParameterBlock" => {
+                let function = (self.declared.0.clone(), self.parameters.len(), self.declared.1);
+                self.scopes.last_mut().unwrap().functions.push(function);
+                self.block_parameters = self.parameters.clone();
+                self.body_next = true;
+            }
+            // a prototype is never defined, so it is not called
+            "FunctionDeclaration" => {
+                self.scopes.last_mut().unwrap().functions.pop();
+                self.block_parameters.clear();
+                self.body_next = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn walk(&mut self, expr: &'g Expr, rule: &'g str, depth: usize) {
+        let limit = depth >= self.max_depth;
+        match expr {
+            Expr::Keyword(text) | Expr::Operator(text) => self.tokens.push(text.clone()),
+            Expr::Token(token_type) => {
+                let token = self.token(*token_type, rule);
+                self.tokens.push(token);
+            }
+            Expr::NonTerminal(name) => self.rule(name, depth),
+            Expr::Sequence(items) => {
+                for item in items.iter() {
+                    self.walk(item, rule, depth);
+                }
+            }
+            Expr::Choice(alternatives) => {
+                let mut feasible: Vec<&'g Expr> = alternatives.iter().filter(|a| self.feasible(a)).collect();
+                if limit {
+                    let shortest = |a: &&Expr| height(a, &self.heights).unwrap_or(usize::MAX);
+                    let min = feasible.iter().map(shortest).min().unwrap();
+                    feasible.retain(|a| shortest(a) == min);
+                }
+                let alternative = feasible[self.rng.below(feasible.len())];
+                self.walk(alternative, rule, depth);
+            }
+            Expr::Optional(inner) => {
+                if !limit && self.rng.chance(50) {
+                    self.walk(inner, rule, depth);
+                }
+            }
+            Expr::Repetition(inner) => {
+                while !limit && self.rng.chance(50) {
+                    self.walk(inner, rule, depth);
+                }
+                // the statements of a function body end with a return, so
+                // every path through it returns a value
+                let statements = matches!(&**inner, Expr::NonTerminal(name) if name == "Statement");
+                if self.semantic && statements && self.scopes.last().is_some_and(|scope| scope.body) {
+                    self.rule("ReturnStatement", depth);
+                }
+            }
+        }
+    }
+
+    // an Assignment needs a declared variable in the semantic mode, and a
+    // ReturnStatement a function around it
+    fn feasible(&self, alternative: &Expr) -> bool {
+        match alternative {
+            Expr::NonTerminal(name) if name == "Assignment" => self.has_variable(false),
+            Expr::NonTerminal(name) if name == "ReturnStatement" => !self.semantic || self.returns_value.last() == Some(&true),
+            _ => true,
+        }
+    }
+
+    // whether the DataType just generated is float or double
+    fn floating_type(&self) -> bool {
+        self.tokens.last().is_some_and(|token| token == "float" || token == "double")
+    }
+
+    fn token(&mut self, token_type: TokenType, rule: &str) -> String {
+        match (token_type, rule) {
+            (TokenType::IntConstant, _) => self.rng.below(1000).to_string(),
+            (TokenType::FloatConstant, _) => format!("{}.{}", self.rng.below(100), self.rng.below(100)),
+            (_, "DeclarationType") => {
+                self.declared = (self.new_name("x"), self.floating_type());
+                self.declared.0.clone()
+            }
+            (_, "Parameter") => {
+                let name = self.new_name("p");
+                self.parameters.push((name.clone(), self.floating_type()));
+                name
+            }
+            _ => self.variable(false),
+        }
+    }
+
+    fn new_name(&mut self, prefix: &str) -> String {
+        if !self.semantic {
+            return NAMES[self.rng.below(NAMES.len())].to_string();
+        }
+        self.names += 1;
+        format!("{}{}", prefix, self.names)
+    }
+
+    // the visible variables, only the integer ones if integer is set; none
+    // in a constant expression
+    fn variables(&self, integer: bool) -> Vec<&String> {
+        if self.constant {
+            return Vec::new();
+        }
+        self.scopes
+            .iter()
+            .flat_map(|scope| scope.variables.iter())
+            .filter(|(_, floating)| !(integer && *floating))
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    fn has_variable(&self, integer: bool) -> bool {
+        !self.semantic || !self.variables(integer).is_empty()
+    }
+
+    fn variable(&mut self, integer: bool) -> String {
+        if !self.semantic {
+            return NAMES[self.rng.below(NAMES.len())].to_string();
+        }
+        let i = self.rng.below(self.variables(integer).len());
+        self.variables(integer)[i].clone()
+    }
+
+    // the functions with their number of parameters that a call with at most
+    // depth levels of arguments can use, only the ones returning an integer
+    // if integer is set
+    fn functions(&mut self, depth: usize, integer: bool) -> Vec<(String, usize)> {
+        if !self.semantic {
+            let name = NAMES[self.rng.below(NAMES.len())].to_string();
+            let arguments = if depth > 0 { self.rng.below(3) } else { 0 };
+            return vec![(name, arguments)];
+        }
+        self.scopes
+            .iter()
+            .flat_map(|scope| scope.functions.iter())
+            .filter(|(_, parameters, floating)| !self.constant && (depth > 0 || *parameters == 0) && !(integer && *floating))
+            .map(|(name, parameters, _)| (name.clone(), *parameters))
+            .collect()
+    }
+
+    // a random expression at most depth operators deep, as tokens with the
+    // precedence of its top operator; in the semantic mode an integer one
+    // if integer is set
+    fn expression(&mut self, depth: usize, integer: bool) -> (Vec<String>, u8) {
+        let integer = integer && self.semantic;
+        if depth == 0 || self.rng.chance(30) {
+            return (self.factor(depth, integer), ATOM);
+        }
+        let has_variable = self.has_variable(integer);
+        let candidates: Vec<&'static Operator> = OPERATORS
+            .iter()
+            .filter(|operator| operator.text != "=" || has_variable)
+            .collect();
+        let operator = candidates[self.rng.below(candidates.len())];
+        let precedence = operator.precedence;
+        let integer = needs_integer(operator) || (integer && keeps_type(operator));
+        let mut tokens = Vec::new();
+        if operator.fixity == Fixity::Prefix {
+            tokens.push(operator.text.to_string());
+            tokens.extend(self.operand(depth - 1, precedence, integer));
+            return (tokens, precedence);
+        }
+
+        let left = if operator.text == "=" {
+            vec![self.variable(integer)]
+        } else if operator.associativity == Associativity::Left {
+            self.operand(depth - 1, precedence, integer)
+        } else {
+            self.operand(depth - 1, precedence + 1, integer)
+        };
+        let right = if self.semantic && (operator.text == "/" || operator.text == "%") {
+            self.divisor(integer)
+        } else if operator.associativity == Associativity::Right {
+            self.operand(depth - 1, precedence, integer)
+        } else {
+            self.operand(depth - 1, precedence + 1, integer)
+        };
+        tokens.extend(left);
+        tokens.push(operator.text.to_string());
+        tokens.extend(right);
+        (tokens, precedence)
+    }
+
+    // an expression that binds at least as tight as precedence, in
+    // parentheses if it does not
+    fn operand(&mut self, depth: usize, precedence: u8, integer: bool) -> Vec<String> {
+        let (tokens, top) = self.expression(depth, integer);
+        if top < precedence || self.rng.chance(5) {
+            let mut res = vec!["(".to_string()];
+            res.extend(tokens);
+            res.push(")".to_string());
+            return res;
+        }
+        tokens
+    }
+
+    // a divisor that consteval cannot find to be zero: a variable, which is
+    // never constant, or a constant other than zero
+    fn divisor(&mut self, integer: bool) -> Vec<String> {
+        if self.has_variable(integer) && self.rng.chance(50) {
+            return vec![self.variable(integer)];
+        }
+        vec![(1 + self.rng.below(999)).to_string()]
+    }
+
+    fn factor(&mut self, depth: usize, integer: bool) -> Vec<String> {
+        let functions = self.functions(depth, integer);
+        match self.rng.below(3) {
+            0 if self.has_variable(integer) => vec![self.variable(integer)],
+            1 if !functions.is_empty() => {
+                let (name, arguments) = functions[self.rng.below(functions.len())].clone();
+                let mut tokens = vec![name, "(".to_string()];
+                for i in 0..arguments {
+                    if i > 0 {
+                        tokens.push(",".to_string());
+                    }
+                    tokens.extend(self.expression(depth - 1, false).0);
+                }
+                tokens.push(")".to_string());
+                tokens
+            }
+            _ => {
+                let token_type = if integer || self.rng.chance(70) {
+                    TokenType::IntConstant
+                } else {
+                    TokenType::FloatConstant
+                };
+                vec![self.token(token_type, "Factor")]
+            }
+        }
+    }
+}
+
+// a random program of the X grammar
+pub fn generate(seed: u64, max_depth: usize, semantic: bool) -> String {
+    Generator::new(&Grammar::x(), seed, max_depth, semantic).program()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_programs_parse() {
+        use crate::grammar::Interpreter;
+        use crate::stage2_scanner::Scanner;
+        use crate::stage3::Parser;
+
+        let grammar = Grammar::x();
+        assert_eq!(heights(&grammar)["DataType"], 2);
+        assert_eq!(generate(7, 8, false), generate(7, 8, false));
+        assert_ne!(generate(7, 8, false), generate(8, 8, false));
+
+        let tokens: Vec<String> = ["f", "(", "a", ",", "g", "(", "(", "1", ")", ")", ")"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(count_arguments(&tokens, 1), 2);
+        assert_eq!(count_arguments(&tokens, 5), 1);
+
+        let type_keywords = ["char", "short", "int", "long", "float", "double"];
+        let mut kinds = Vec::new();
+        for semantic in [false, true] {
+            for seed in 0..100 {
+                let mut generator = Generator::new(&grammar, seed, 10, semantic);
+                let source = generator.program();
+                let all_tokens = Scanner::from_source("generated.x", &source).run().all_tokens.clone();
+                let texts: Vec<String> = all_tokens.iter().map(|t| t.text.clone()).collect();
+                assert_eq!(texts, Generator::new(&grammar, seed, 10, semantic).tokens(), "{}", source);
+                let program = match Parser::from_tokens(all_tokens.clone()).parse() {
+                    Ok(program) => program,
+                    Err(e) => panic!("seed {}: {}\n{}", seed, e, source),
+                };
+                assert!(Interpreter::new(&grammar, all_tokens.clone()).parse().is_ok(), "{}", source);
+                for kind in ["while", "if", "return", "=", "(", "!"] {
+                    if texts.iter().any(|t| t == kind) && !kinds.contains(&kind) {
+                        kinds.push(kind);
+                    }
+                }
+                if !semantic {
+                    continue;
+                }
+                // fresh names resolve without undeclared names, duplicates or shadowing
+                assert_eq!(crate::resolve::resolve("generated.x", &program).1, Vec::new(), "{}", source);
+
+                // the program checks without errors: types, returns, constants
+                let errors: Vec<String> = crate::diagnostics::check_source("generated.x", &source)
+                    .iter()
+                    .filter(|diagnostic| diagnostic.severity == crate::diagnostics::Severity::Error)
+                    .map(|diagnostic| diagnostic.to_string())
+                    .collect();
+                assert_eq!(errors, Vec::<String>::new(), "{}", source);
+
+                // every name is declared before it is used, and every call has
+                // as many arguments as the function has parameters
+                let mut arity: HashMap<&str, usize> = HashMap::new();
+                let mut declared = Vec::new();
+                for (i, token) in texts.iter().enumerate() {
+                    if all_tokens[i].token_type != TokenType::Identifier {
+                        continue;
+                    }
+                    let is_call = texts.get(i + 1).is_some_and(|t| t == "(");
+                    if type_keywords.contains(&texts[i - 1].as_str()) {
+                        declared.push(token);
+                        if is_call {
+                            arity.insert(token, count_arguments(&texts, i + 1));
+                        }
+                    } else {
+                        assert!(declared.contains(&token), "{} is not declared in\n{}", token, source);
+                        if is_call {
+                            assert_eq!(arity[token.as_str()], count_arguments(&texts, i + 1), "{}", source);
+                        }
+                    }
+                }
+            }
+        }
+        // the programs use every kind of statement and expression
+        assert_eq!(kinds.len(), 6);
+    }
+
+    // how many arguments or parameters the parentheses opening at tokens[open] hold
+    fn count_arguments(tokens: &[String], open: usize) -> usize {
+        if tokens[open + 1] == ")" {
+            return 0;
+        }
+        let mut nesting = 0;
+        let mut commas = 0;
+        for token in tokens[open..].iter() {
+            match token.as_str() {
+                "(" => nesting += 1,
+                ")" => {
+                    nesting -= 1;
+                    if nesting == 0 {
+                        break;
+                    }
+                }
+                "," if nesting == 1 => commas += 1,
+                _ => {}
+            }
+        }
+        commas + 1
+    }
+}
@@ -0,0 +1,162 @@
+//Golden-file tests: every tests/**/*.x is scanned, parsed, checked and
+//highlighted, and each output is compared with the file next to it:
+//name.tokens.expected, name.ast.expected, name.diagnostics.expected and
+//name.xhtml.expected, and for a program that checks without errors what it
+//writes when it runs, reading name.in if there is one, and its runtime error
+//in name.output.expected. Blessing (--bless, or BLESS=1 for tests/golden.rs)
+//writes the current outputs as the new expectations instead.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::diagnostics::{check_source, Severity};
+use crate::interpreter::run_source;
+use crate::stage2_scanner::Scanner;
+use crate::stage3::Parser;
+use crate::xhtml_out::XHTML;
+
+pub const TESTS_DIR: &str = "tests";
+
+// a golden file whose contents differ from the output
+#[derive(Debug)]
+pub struct Mismatch {
+    pub expected_file: String,
+    // None if the file does not exist yet
+    pub expected: Option<String>,
+    pub actual: String,
+}
+
+// the first line that differs, zero-based like everywhere else
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let expected = match &self.expected {
+            Some(expected) => expected,
+            None => return write!(f, "{}: missing, run with --bless to create it", self.expected_file),
+        };
+        let mut expected_lines = expected.lines();
+        let mut actual_lines = self.actual.lines();
+        let mut line_num = 0;
+        loop {
+            match (expected_lines.next(), actual_lines.next()) {
+                (Some(e), Some(a)) if e == a => line_num += 1,
+                (e, a) => {
+                    return write!(
+                        f,
+                        "{}: line {} differs\n  expected: {}\n  actual:   {}",
+                        self.expected_file,
+                        line_num,
+                        e.unwrap_or("<end of file>"),
+                        a.unwrap_or("<end of file>")
+                    )
+                }
+            }
+        }
+    }
+}
+
+// the .x files under dir and its subdirectories, sorted
+pub fn discover(dir: &Path) -> Vec<PathBuf> {
+    let mut res = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                res.extend(discover(&path));
+            } else if path.extension().is_some_and(|extension| extension == "x") {
+                res.push(path);
+            }
+        }
+    }
+    res.sort();
+    res
+}
+
+// the outputs of a source by kind: the tokens with their positions, the AST
+// dump or the syntax error, the diagnostics, the XHTML and, if the source
+// has no errors, the output of running it on input
+pub fn outputs(xfilename: &str, source: &str, input: &str) -> Vec<(&'static str, String)> {
+    let tokens = Scanner::from_source(xfilename, source).run().all_tokens.clone();
+    let token_lines: String = tokens
+        .iter()
+        .map(|token| format!("{}:{} {:?} {}\n", token.line_num, token.char_pos, token.token_type, token.text))
+        .collect();
+    let ast = match Parser::from_tokens(tokens).parse() {
+        Ok(program) => program.dump(),
+        Err(e) => format!("{}\n", e),
+    };
+    let checked = check_source(xfilename, source);
+    let diagnostics: String = checked.iter().map(|diagnostic| format!("{}\n", diagnostic)).collect();
+    let xhtml = XHTML::from_source(xfilename, source).render();
+    let mut res = vec![
+        ("tokens", token_lines),
+        ("ast", ast),
+        ("diagnostics", diagnostics),
+        ("xhtml", xhtml),
+    ];
+    if checked.iter().all(|diagnostic| diagnostic.severity != Severity::Error) {
+        let mut output = Vec::new();
+        let ran = run_source(xfilename, source, &mut input.as_bytes(), &mut output);
+        let mut output = String::from_utf8_lossy(&output).into_owned();
+        if let Err(errors) = ran {
+            output.extend(errors.iter().map(|error| format!("{}\n", error)));
+        }
+        res.push(("output", output));
+    }
+    res
+}
+
+// compares the outputs of every .x file under dir with their golden files,
+// or writes them if bless is set
+pub fn run(dir: &str, bless: bool) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    for path in discover(Path::new(dir)) {
+        let xfilename = path.display().to_string().replace('\\', "/");
+        let source = fs::read_to_string(&path).expect("Error occurred when reading the input file!");
+        let input = fs::read_to_string(path.with_extension("in")).unwrap_or_default();
+        for (kind, actual) in outputs(&xfilename, &source, &input) {
+            let expected_file = path.with_extension(format!("{}.expected", kind));
+            if bless {
+                fs::write(&expected_file, &actual).expect("error when writing the file!");
+                continue;
+            }
+            let expected = fs::read_to_string(&expected_file).ok();
+            if expected.as_ref() != Some(&actual) {
+                mismatches.push(Mismatch {
+                    expected_file: expected_file.display().to_string(),
+                    expected,
+                    actual,
+                });
+            }
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn golden_runner() {
+        let dir = Path::new(TESTS_DIR);
+        let files = discover(dir);
+        assert!(files.iter().any(|path| path.ends_with("examples/example1.x")));
+        assert!(files.iter().all(|path| path.extension().unwrap() == "x"));
+
+        let outputs = outputs("test.x", "int x = 1;\nvoid main(){\nx = -x;\nx = print_int(x);\n}", "");
+        assert_eq!(outputs.iter().map(|(kind, _)| *kind).collect::<Vec<_>>(), ["tokens", "ast", "diagnostics", "xhtml", "output"]);
+        assert!(outputs[0].1.starts_with("0:0 Keyword int\n0:4 Identifier x\n"));
+        assert!(outputs[1].1.contains("\n      Expression (x = (-x)) [2:0-2:7]\n"));
+        assert_eq!(outputs[2].1, "");
+        assert_eq!(outputs[4].1, "-1");
+        assert_eq!(super::outputs("test.x", "void main(){\nx = 1;\n}", "").len(), 4);
+
+        let mismatch = Mismatch {
+            expected_file: "a.ast.expected".to_string(),
+            expected: Some("Program\n  Block\n".to_string()),
+            actual: "Program\n".to_string(),
+        };
+        assert_eq!(mismatch.to_string(), "a.ast.expected: line 1 differs\n  expected:   Block\n  actual:   <end of file>");
+    }
+}
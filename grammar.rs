@@ -0,0 +1,521 @@
+//Loads an EBNF grammar from text (see x.ebnf for the notation) and parses
+//token streams from the Scanner with it at runtime, so the X language can be
+//changed without recompiling the hand-written stage3::Parser.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+
+use custom_error::custom_error;
+
+use crate::diagnostics::read_source;
+use crate::stage2_scanner::Scanner;
+use crate::stage2_token::{Token, TokenType};
+use crate::stage3::SyntaxError;
+
+custom_error! {pub GrammarError{line_num: i32, message: String} = "Grammar error at Line {line_num}: {message}."}
+
+// the X grammar as shipped with the crate
+pub const X_GRAMMAR: &str = include_str!("x.ebnf");
+
+// the token classes a grammar can refer to by name
+const TOKEN_CLASSES: &[(&str, TokenType)] = &[
+    ("Identifier", TokenType::Identifier),
+    ("IntConstant", TokenType::IntConstant),
+    ("FloatConstant", TokenType::FloatConstant),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    // a keyword such as while
+    Keyword(String),
+    // an operator such as == or (
+    Operator(String),
+    // any token of a class, e.g. Identifier
+    Token(TokenType),
+    NonTerminal(String),
+    // a b c
+    Sequence(Vec<Expr>),
+    // a | b | c
+    Choice(Vec<Expr>),
+    // [ a ]
+    Optional(Box<Expr>),
+    // { a }
+    Repetition(Box<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub name: String,
+    pub expr: Expr,
+    // the production as written, without quotes, e.g. for error messages
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grammar {
+    // in the order of the grammar file, the first rule is the start rule
+    pub rules: Vec<Rule>,
+}
+
+// tokens of the grammar notation itself
+#[derive(Clone, Debug, PartialEq)]
+enum GrammarToken {
+    // ( ) [ ] { } |
+    Meta(char),
+    Word(String),
+    // an operator, quoted or not
+    Symbol(String),
+}
+
+const META_CHARS: &str = "()[]{}|";
+
+fn tokenize_production(line: &str, line_num: i32) -> Result<Vec<GrammarToken>, GrammarError> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if META_CHARS.contains(c) {
+            tokens.push(GrammarToken::Meta(c));
+            i += 1;
+        } else if c == '\'' {
+            // quoted operator or keyword
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i] != '\'' {
+                i += 1;
+            }
+            if i >= chars.len() || i == start {
+                return Err(GrammarError {
+                    line_num,
+                    message: "unterminated or empty quote".to_string(),
+                });
+            }
+            let text: String = chars[start..i].iter().collect();
+            if text.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                tokens.push(GrammarToken::Word(text));
+            } else {
+                tokens.push(GrammarToken::Symbol(text));
+            }
+            i += 1;
+        } else if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(GrammarToken::Word(chars[start..i].iter().collect()));
+        } else {
+            // a run of operator characters, e.g. == or ;
+            let start = i;
+            while i < chars.len()
+                && !chars[i].is_whitespace()
+                && !chars[i].is_alphanumeric()
+                && chars[i] != '_'
+                && chars[i] != '\''
+                && !META_CHARS.contains(chars[i])
+            {
+                i += 1;
+            }
+            tokens.push(GrammarToken::Symbol(chars[start..i].iter().collect()));
+        }
+    }
+
+    Ok(tokens)
+}
+
+// recursive descent over the tokens of one production
+struct ProductionParser {
+    tokens: Vec<GrammarToken>,
+    index: usize,
+    line_num: i32,
+}
+
+impl ProductionParser {
+    fn error(&self, message: &str) -> GrammarError {
+        GrammarError {
+            line_num: self.line_num,
+            message: message.to_string(),
+        }
+    }
+
+    // Choice := Sequence { | Sequence }
+    fn choice(&mut self) -> Result<Expr, GrammarError> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.tokens.get(self.index) == Some(&GrammarToken::Meta('|')) {
+            self.index += 1;
+            alternatives.push(self.sequence()?);
+        }
+        if alternatives.len() == 1 {
+            return Ok(alternatives.pop().unwrap());
+        }
+        Ok(Expr::Choice(alternatives))
+    }
+
+    // Sequence := Item { Item }
+    fn sequence(&mut self) -> Result<Expr, GrammarError> {
+        let mut items = Vec::new();
+        loop {
+            match self.tokens.get(self.index).cloned() {
+                None | Some(GrammarToken::Meta('|')) | Some(GrammarToken::Meta(')'))
+                | Some(GrammarToken::Meta(']')) | Some(GrammarToken::Meta('}')) => break,
+                Some(GrammarToken::Meta(open)) => {
+                    self.index += 1;
+                    let inner = self.choice()?;
+                    let close = match open {
+                        '(' => ')',
+                        '[' => ']',
+                        _ => '}',
+                    };
+                    if self.tokens.get(self.index) != Some(&GrammarToken::Meta(close)) {
+                        return Err(self.error(&format!("expected '{}'", close)));
+                    }
+                    self.index += 1;
+                    items.push(match open {
+                        '(' => inner,
+                        '[' => Expr::Optional(Box::new(inner)),
+                        _ => Expr::Repetition(Box::new(inner)),
+                    });
+                }
+                Some(GrammarToken::Word(word)) => {
+                    self.index += 1;
+                    let first_char = word.chars().next().unwrap();
+                    items.push(
+                        match TOKEN_CLASSES.iter().find(|(name, _)| *name == word) {
+                            Some((_, token_type)) => Expr::Token(*token_type),
+                            None if first_char.is_uppercase() => Expr::NonTerminal(word),
+                            None => Expr::Keyword(word),
+                        },
+                    );
+                }
+                Some(GrammarToken::Symbol(symbol)) => {
+                    self.index += 1;
+                    items.push(Expr::Operator(symbol));
+                }
+            }
+        }
+        if items.is_empty() {
+            return Err(self.error("empty alternative"));
+        }
+        if items.len() == 1 {
+            return Ok(items.pop().unwrap());
+        }
+        Ok(Expr::Sequence(items))
+    }
+}
+
+impl Grammar {
+    // parses a grammar; blank lines and lines starting with // are ignored
+    pub fn parse(text: &str) -> Result<Grammar, GrammarError> {
+        let mut rules: Vec<Rule> = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line_num = i as i32;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+
+            let (name, body) = match line.split_once(":=") {
+                Some((name, body)) => (name.trim(), body),
+                None => {
+                    return Err(GrammarError {
+                        line_num,
+                        message: "expected Name := ...".to_string(),
+                    })
+                }
+            };
+            if !name.starts_with(|c: char| c.is_uppercase())
+                || !name.chars().all(|c| c.is_alphanumeric() || c == '_')
+            {
+                return Err(GrammarError {
+                    line_num,
+                    message: format!("invalid rule name '{}'", name),
+                });
+            }
+            if rules.iter().any(|rule| rule.name == name) {
+                return Err(GrammarError {
+                    line_num,
+                    message: format!("rule {} is defined twice", name),
+                });
+            }
+
+            let mut parser = ProductionParser {
+                tokens: tokenize_production(body, line_num)?,
+                index: 0,
+                line_num,
+            };
+            let expr = parser.choice()?;
+            if parser.index < parser.tokens.len() {
+                return Err(parser.error("unbalanced brackets"));
+            }
+
+            rules.push(Rule {
+                name: name.to_string(),
+                expr,
+                text: line.replace('\'', ""),
+            });
+        }
+
+        if rules.is_empty() {
+            return Err(GrammarError {
+                line_num: 0,
+                message: "the grammar has no rules".to_string(),
+            });
+        }
+
+        // every nonterminal must have a rule
+        let names: HashSet<&str> = rules.iter().map(|rule| rule.name.as_str()).collect();
+        for rule in rules.iter() {
+            for nonterminal in rule.expr.nonterminals() {
+                if !names.contains(nonterminal.as_str()) {
+                    return Err(GrammarError {
+                        line_num: 0,
+                        message: format!("{} uses undefined rule {}", rule.name, nonterminal),
+                    });
+                }
+            }
+        }
+
+        Ok(Grammar { rules })
+    }
+
+    pub fn from_file(path: &str) -> Result<Grammar, GrammarError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Grammar::parse(&text),
+            Err(e) => Err(GrammarError {
+                line_num: 0,
+                message: format!("cannot read {}: {}", path, e),
+            }),
+        }
+    }
+
+    // the built-in X grammar
+    pub fn x() -> Grammar {
+        Grammar::parse(X_GRAMMAR).expect("x.ebnf is invalid")
+    }
+
+    pub fn rule(&self, name: &str) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.name == name)
+    }
+
+    pub fn start_rule(&self) -> &Rule {
+        &self.rules[0]
+    }
+}
+
+impl Expr {
+    // the nonterminals referenced by this expression, in order of appearance
+    pub fn nonterminals(&self) -> Vec<String> {
+        let mut res = Vec::new();
+        self.collect_nonterminals(&mut res);
+        res
+    }
+
+    fn collect_nonterminals(&self, res: &mut Vec<String>) {
+        match self {
+            Expr::NonTerminal(name) => res.push(name.clone()),
+            Expr::Sequence(items) | Expr::Choice(items) => {
+                for item in items {
+                    item.collect_nonterminals(res);
+                }
+            }
+            Expr::Optional(inner) | Expr::Repetition(inner) => inner.collect_nonterminals(res),
+            _ => {}
+        }
+    }
+}
+
+// the concrete syntax tree built by the Interpreter
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseTree {
+    Token(Token),
+    Node { rule: String, children: Vec<ParseTree> },
+}
+
+/**
+ * A backtracking recursive-descent interpreter for a Grammar. It follows the
+ * same rules as the ebnf_* macros of stage3::Parser: alternatives are tried
+ * in order, and optional or repeated parts are skipped when they fail.
+ */
+pub struct Interpreter<'g> {
+    grammar: &'g Grammar,
+    pub all_tokens: Vec<Token>,
+    token_index: usize,
+}
+
+impl<'g> Interpreter<'g> {
+    pub fn new(grammar: &'g Grammar, all_tokens: Vec<Token>) -> Interpreter<'g> {
+        Interpreter {
+            grammar,
+            all_tokens,
+            token_index: 0,
+        }
+    }
+
+    // parses the whole token stream starting at the grammar's first rule
+    pub fn parse(&mut self) -> Result<ParseTree, SyntaxError> {
+        let start = self.grammar.start_rule();
+        self.token_index = 0;
+        let tree = self.parse_rule(start)?;
+        if self.token_index < self.all_tokens.len() {
+            return Err(self.error_here(&start.text));
+        }
+        Ok(tree)
+    }
+
+    // runs the interpreter, reporting like stage3::Parser::run
+    pub fn run(&mut self) -> String {
+        match self.parse() {
+            Ok(_) => "Input program is syntactically correct.".to_string(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    // an error at the current token, or at the last one past the end
+    fn error_here(&self, ebnf: &str) -> SyntaxError {
+        let (line_num, char_pos) = match self
+            .all_tokens
+            .get(self.token_index)
+            .or(self.all_tokens.last())
+        {
+            Some(token) => (token.line_num, token.char_pos),
+            None => (0, 0),
+        };
+        SyntaxError {
+            line_num,
+            char_pos,
+            ebnf: ebnf.to_string(),
+        }
+    }
+
+    fn parse_rule(&mut self, rule: &'g Rule) -> Result<ParseTree, SyntaxError> {
+        let mut children = Vec::new();
+        self.parse_expr(&rule.expr, rule, &mut children)?;
+        Ok(ParseTree::Node {
+            rule: rule.name.clone(),
+            children,
+        })
+    }
+
+    fn terminal_token(&mut self, matches: bool, rule: &Rule, children: &mut Vec<ParseTree>) -> Result<(), SyntaxError> {
+        if !matches {
+            return Err(self.error_here(&rule.text));
+        }
+        children.push(ParseTree::Token(self.all_tokens[self.token_index].clone()));
+        self.token_index += 1;
+        Ok(())
+    }
+
+    fn parse_expr(
+        &mut self,
+        expr: &'g Expr,
+        rule: &'g Rule,
+        children: &mut Vec<ParseTree>,
+    ) -> Result<(), SyntaxError> {
+        let token = self.all_tokens.get(self.token_index);
+        match expr {
+            Expr::Keyword(text) => {
+                let matches = token.is_some_and(|t| t.token_type == TokenType::Keyword && &t.text == text);
+                self.terminal_token(matches, rule, children)
+            }
+            Expr::Operator(text) => {
+                let matches = token.is_some_and(|t| t.token_type == TokenType::Operator && &t.text == text);
+                self.terminal_token(matches, rule, children)
+            }
+            Expr::Token(token_type) => {
+                let matches = token.is_some_and(|t| t.token_type == *token_type);
+                self.terminal_token(matches, rule, children)
+            }
+            Expr::NonTerminal(name) => {
+                let callee = self.grammar.rule(name).expect("undefined rule");
+                children.push(self.parse_rule(callee)?);
+                Ok(())
+            }
+            Expr::Sequence(items) => {
+                let prev_index = self.token_index;
+                let prev_len = children.len();
+                for item in items {
+                    if let Err(e) = self.parse_expr(item, rule, children) {
+                        // syntax check failed, reset index
+                        self.token_index = prev_index;
+                        children.truncate(prev_len);
+                        return Err(e);
+                    }
+                }
+                Ok(())
+            }
+            Expr::Choice(alternatives) => {
+                for alternative in alternatives {
+                    if self.parse_expr(alternative, rule, children).is_ok() {
+                        return Ok(());
+                    }
+                }
+                Err(self.error_here(&rule.text))
+            }
+            Expr::Optional(inner) => {
+                let _ = self.parse_expr(inner, rule, children);
+                Ok(())
+            }
+            Expr::Repetition(inner) => {
+                loop {
+                    let prev_index = self.token_index;
+                    // stop on failure, or if nothing was consumed
+                    if self.parse_expr(inner, rule, children).is_err() || self.token_index == prev_index {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// parses a .x file with a grammar loaded at runtime
+pub fn run_file(grammar: &Grammar, xfilename: &str) -> io::Result<String> {
+    let source = read_source(xfilename)?;
+    let all_tokens = Scanner::from_source(xfilename, &source).run().all_tokens.clone();
+    Ok(Interpreter::new(grammar, all_tokens).run())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runtime_grammar() {
+        let grammar = Grammar::x();
+        assert_eq!(grammar.start_rule().name, "Program");
+        assert_eq!(
+            grammar.rule("MainDeclaration").unwrap().text,
+            "MainDeclaration := void main ( ) Block"
+        );
+        assert_eq!(
+            grammar.rule("VariableDeclaration").unwrap().expr,
+            Expr::Sequence(vec![
+                Expr::Optional(Box::new(Expr::Sequence(vec![
+                    Expr::Operator("=".to_string()),
+                    Expr::NonTerminal("Expression".to_string()),
+                ]))),
+                Expr::Operator(";".to_string()),
+            ])
+        );
+
+        assert_eq!(
+            run_file(&grammar, "example1.x").unwrap(),
+            "Input program is syntactically correct.".to_string()
+        );
+
+        // a tiny grammar without recompiling
+        let calls = Grammar::parse("Calls := { Identifier '(' ')' ; }").unwrap();
+        let mut scan = Scanner::new("example1.x");
+        let tokens = scan.run().all_tokens.clone();
+        assert!(Interpreter::new(&calls, tokens).parse().is_err());
+
+        assert!(Grammar::parse("Program := Missing").is_err());
+        assert!(Grammar::parse("Program := ( Identifier").is_err());
+    }
+}
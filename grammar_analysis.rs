@@ -0,0 +1,737 @@
+//Static analysis of a Grammar: nullable rules, FIRST and FOLLOW sets,
+//LL(1) conflicts with example token strings and the lookahead needed to
+//resolve them, left recursion, and unreachable or unproductive rules.
+//
+//stage3::Parser backtracks, so a conflict is not a bug by itself, but every
+//conflict is a place where it may try an alternative, fail and start over.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::grammar::{Expr, Grammar};
+use crate::stage2_token::TokenType;
+
+// the longest lookahead tried when suggesting how to resolve a conflict;
+// with the expression operators each extra token makes the sets ~10x larger
+pub const MAX_LOOKAHEAD: usize = 3;
+
+// the end of input in FOLLOW sets
+pub const END: &str = "$";
+
+// sets of token strings of length <= k; a shorter string ends the input.
+// The symbols borrow from the grammar, so the sets are cheap to copy.
+type Lookahead<'g> = BTreeSet<Vec<&'g str>>;
+
+// the terminal symbol of a terminal expression, as shown in reports
+fn symbol(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Keyword(text) | Expr::Operator(text) => Some(text),
+        Expr::Token(TokenType::Identifier) => Some("Identifier"),
+        Expr::Token(TokenType::IntConstant) => Some("IntConstant"),
+        Expr::Token(TokenType::FloatConstant) => Some("FloatConstant"),
+        Expr::Token(TokenType::Keyword) => Some("Keyword"),
+        Expr::Token(TokenType::Operator) => Some("Operator"),
+        Expr::Token(TokenType::Invalid) => Some("Invalid"),
+        _ => None,
+    }
+}
+
+fn concat_k<'g>(a: &Lookahead<'g>, b: &Lookahead<'g>, k: usize) -> Lookahead<'g> {
+    let mut res = Lookahead::new();
+    // b cut to each length still needed; far fewer strings than b itself
+    // once the operators of expressions multiply
+    let mut truncated: Vec<Option<Lookahead<'g>>> = vec![None; k + 1];
+    for x in a {
+        if x.len() >= k {
+            res.insert(x.clone());
+            continue;
+        }
+        let room = k - x.len();
+        let prefixes = truncated[room]
+            .get_or_insert_with(|| b.iter().map(|y| y.iter().take(room).cloned().collect()).collect());
+        for y in prefixes.iter() {
+            let mut joined = x.clone();
+            joined.extend(y.iter().cloned());
+            res.insert(joined);
+        }
+    }
+    res
+}
+
+fn epsilon<'g>() -> Lookahead<'g> {
+    let mut res = Lookahead::new();
+    res.insert(vec![]);
+    res
+}
+
+// FIRST_k and FOLLOW_k sets of every rule for one k
+struct Sets<'g> {
+    k: usize,
+    first: BTreeMap<String, Lookahead<'g>>,
+    follow: BTreeMap<String, Lookahead<'g>>,
+}
+
+impl<'g> Sets<'g> {
+    fn compute(grammar: &'g Grammar, k: usize) -> Sets<'g> {
+        let mut sets = Sets {
+            k,
+            first: grammar
+                .rules
+                .iter()
+                .map(|rule| (rule.name.clone(), Lookahead::new()))
+                .collect(),
+            follow: grammar
+                .rules
+                .iter()
+                .map(|rule| (rule.name.clone(), Lookahead::new()))
+                .collect(),
+        };
+
+        // FIRST_k, by fixed point
+        loop {
+            let mut changed = false;
+            for rule in grammar.rules.iter() {
+                let first = sets.first_of(&rule.expr);
+                if first != sets.first[&rule.name] {
+                    sets.first.insert(rule.name.clone(), first);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        // FOLLOW_k, by fixed point
+        sets.follow
+            .get_mut(&grammar.start_rule().name)
+            .unwrap()
+            .insert(vec![END]);
+        // (nonterminal, rule it is used in, FIRST_k of what follows it there);
+        // the FIRST_k part does not change while FOLLOW_k grows
+        let mut uses = Vec::new();
+        for rule in grammar.rules.iter() {
+            for_each_position(&rule.expr, &[], &mut |expr, cont| {
+                if let Expr::NonTerminal(name) = expr {
+                    uses.push((name.clone(), rule.name.clone(), sets.first_of_list(cont)));
+                }
+            });
+        }
+        loop {
+            let mut changed = false;
+            for (name, rule, first) in uses.iter() {
+                let update = concat_k(first, &sets.follow[rule], k);
+                let follow = sets.follow.get_mut(name).unwrap();
+                let before = follow.len();
+                follow.extend(update);
+                changed |= follow.len() != before;
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        sets
+    }
+
+    fn first_of(&self, expr: &'g Expr) -> Lookahead<'g> {
+        match expr {
+            Expr::NonTerminal(name) => self.first[name].clone(),
+            Expr::Sequence(items) => self.first_of_list(&items.iter().collect::<Vec<&Expr>>()),
+            Expr::Choice(alternatives) => {
+                let mut res = Lookahead::new();
+                for alternative in alternatives {
+                    res.extend(self.first_of(alternative));
+                }
+                res
+            }
+            Expr::Optional(inner) => {
+                let mut res = epsilon();
+                res.extend(self.first_of(inner));
+                res
+            }
+            Expr::Repetition(inner) => {
+                let inner_first = self.first_of(inner);
+                let mut res = epsilon();
+                loop {
+                    let mut next = res.clone();
+                    next.extend(concat_k(&inner_first, &res, self.k));
+                    if next == res {
+                        break;
+                    }
+                    res = next;
+                }
+                res
+            }
+            terminal => {
+                let mut res = Lookahead::new();
+                res.insert(vec![symbol(terminal).unwrap()]);
+                res
+            }
+        }
+    }
+
+    fn first_of_list(&self, list: &[&'g Expr]) -> Lookahead<'g> {
+        let mut res = epsilon();
+        for expr in list {
+            res = concat_k(&res, &self.first_of(expr), self.k);
+        }
+        res
+    }
+
+    // the lookahead after a position: its continuation inside the rule,
+    // then whatever follows the rule
+    fn lookahead_after(&self, cont: &[&'g Expr], rule: &str) -> Lookahead<'g> {
+        concat_k(&self.first_of_list(cont), &self.follow[rule], self.k)
+    }
+}
+
+// calls f with every subexpression and the expressions that follow it
+// inside the rule (a repetition can be followed by itself)
+fn for_each_position<'g, F>(expr: &'g Expr, cont: &[&'g Expr], f: &mut F)
+where
+    F: FnMut(&'g Expr, &[&'g Expr]),
+{
+    f(expr, cont);
+    match expr {
+        Expr::Sequence(items) => {
+            for (i, item) in items.iter().enumerate() {
+                let mut item_cont: Vec<&Expr> = items[i + 1..].iter().collect();
+                item_cont.extend_from_slice(cont);
+                for_each_position(item, &item_cont, f);
+            }
+        }
+        Expr::Choice(alternatives) => {
+            for alternative in alternatives {
+                for_each_position(alternative, cont, f);
+            }
+        }
+        Expr::Optional(inner) => for_each_position(inner, cont, f),
+        Expr::Repetition(inner) => {
+            let mut inner_cont = vec![expr];
+            inner_cont.extend_from_slice(cont);
+            for_each_position(inner, &inner_cont, f);
+        }
+        _ => {}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    pub rule: String,
+    // what the parser has to decide, e.g. "alternatives 1 and 5 of ..."
+    pub description: String,
+    // the tokens both choices can start with
+    pub tokens: BTreeSet<String>,
+    // one example token string for each side of the decision
+    pub examples: Vec<Vec<String>>,
+    // the smallest k that makes the decision LL(k), if <= MAX_LOOKAHEAD
+    pub lookahead: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct Analysis {
+    pub nullable: BTreeSet<String>,
+    pub first: BTreeMap<String, BTreeSet<String>>,
+    pub follow: BTreeMap<String, BTreeSet<String>>,
+    pub conflicts: Vec<Conflict>,
+    // cycles of rules, each starting and ending with the same rule
+    pub left_recursion: Vec<Vec<String>>,
+    pub unreachable: Vec<String>,
+    pub unproductive: Vec<String>,
+}
+
+// one decision of the parser: the options it chooses between at a position
+struct Decision<'g> {
+    rule: String,
+    description: String,
+    // each option as the list of expressions it would parse next
+    options: Vec<Vec<&'g Expr>>,
+}
+
+fn decisions(grammar: &Grammar) -> Vec<Decision<'_>> {
+    let mut res = Vec::new();
+    for rule in grammar.rules.iter() {
+        for_each_position(&rule.expr, &[], &mut |expr, cont| {
+            let rest: Vec<&Expr> = cont.to_vec();
+            match expr {
+                Expr::Choice(alternatives) => {
+                    for i in 0..alternatives.len() {
+                        for j in i + 1..alternatives.len() {
+                            let mut first = vec![&alternatives[i]];
+                            first.extend_from_slice(&rest);
+                            let mut second = vec![&alternatives[j]];
+                            second.extend_from_slice(&rest);
+                            res.push(Decision {
+                                rule: rule.name.clone(),
+                                description: format!(
+                                    "alternatives {} and {} of ( {} )",
+                                    i + 1,
+                                    j + 1,
+                                    display(expr)
+                                ),
+                                options: vec![first, second],
+                            });
+                        }
+                    }
+                }
+                Expr::Optional(inner) | Expr::Repetition(inner) => {
+                    let mut enter = vec![inner.as_ref()];
+                    if let Expr::Repetition(_) = expr {
+                        enter.push(expr);
+                    }
+                    enter.extend_from_slice(&rest);
+                    res.push(Decision {
+                        rule: rule.name.clone(),
+                        description: format!("entering or skipping {}", display(expr)),
+                        options: vec![enter, rest],
+                    });
+                }
+                _ => {}
+            }
+        });
+    }
+    res
+}
+
+// the grammar notation of an expression
+pub fn display(expr: &Expr) -> String {
+    match expr {
+        Expr::NonTerminal(name) => name.clone(),
+        Expr::Sequence(items) => items.iter().map(display_item).collect::<Vec<String>>().join(" "),
+        Expr::Choice(alternatives) => alternatives
+            .iter()
+            .map(display_item)
+            .collect::<Vec<String>>()
+            .join(" | "),
+        Expr::Optional(inner) => format!("[ {} ]", display(inner)),
+        Expr::Repetition(inner) => format!("{{ {} }}", display(inner)),
+        terminal => symbol(terminal).unwrap().to_string(),
+    }
+}
+
+fn display_item(expr: &Expr) -> String {
+    match expr {
+        Expr::Choice(_) | Expr::Sequence(_) => format!("( {} )", display(expr)),
+        _ => display(expr),
+    }
+}
+
+fn nullable_rules(grammar: &Grammar) -> BTreeSet<String> {
+    let mut nullable = BTreeSet::new();
+    loop {
+        let mut changed = false;
+        for rule in grammar.rules.iter() {
+            if !nullable.contains(&rule.name) && is_nullable(&rule.expr, &nullable) {
+                nullable.insert(rule.name.clone());
+                changed = true;
+            }
+        }
+        if !changed {
+            return nullable;
+        }
+    }
+}
+
+fn is_nullable(expr: &Expr, nullable: &BTreeSet<String>) -> bool {
+    match expr {
+        Expr::NonTerminal(name) => nullable.contains(name),
+        Expr::Sequence(items) => items.iter().all(|item| is_nullable(item, nullable)),
+        Expr::Choice(alternatives) => alternatives.iter().any(|a| is_nullable(a, nullable)),
+        Expr::Optional(_) | Expr::Repetition(_) => true,
+        _ => false,
+    }
+}
+
+// the shortest token string each rule derives, None if it derives none
+fn shortest_sentences(grammar: &Grammar) -> BTreeMap<String, Option<Vec<String>>> {
+    let mut shortest: BTreeMap<String, Option<Vec<String>>> =
+        grammar.rules.iter().map(|rule| (rule.name.clone(), None)).collect();
+    loop {
+        let mut changed = false;
+        for rule in grammar.rules.iter() {
+            let candidate = shortest_of(&rule.expr, &shortest);
+            let better = match (&candidate, &shortest[&rule.name]) {
+                (Some(c), Some(s)) => c.len() < s.len(),
+                (Some(_), None) => true,
+                _ => false,
+            };
+            if better {
+                shortest.insert(rule.name.clone(), candidate);
+                changed = true;
+            }
+        }
+        if !changed {
+            return shortest;
+        }
+    }
+}
+
+fn shortest_of(expr: &Expr, shortest: &BTreeMap<String, Option<Vec<String>>>) -> Option<Vec<String>> {
+    match expr {
+        Expr::NonTerminal(name) => shortest[name].clone(),
+        Expr::Sequence(items) => {
+            let mut res = Vec::new();
+            for item in items {
+                res.extend(shortest_of(item, shortest)?);
+            }
+            Some(res)
+        }
+        Expr::Choice(alternatives) => alternatives
+            .iter()
+            .filter_map(|alternative| shortest_of(alternative, shortest))
+            .min_by_key(|sentence| sentence.len()),
+        Expr::Optional(_) | Expr::Repetition(_) => Some(vec![]),
+        terminal => Some(vec![symbol(terminal).unwrap().to_string()]),
+    }
+}
+
+// builds example token strings for conflicts
+struct Examples<'g> {
+    grammar: &'g Grammar,
+    nullable: &'g BTreeSet<String>,
+    shortest: BTreeMap<String, Option<Vec<String>>>,
+}
+
+impl<'g> Examples<'g> {
+    // a short token string derived from the list that starts with token
+    fn starting_with(&self, list: &[&Expr], token: &str, visiting: &mut HashSet<String>) -> Option<Vec<String>> {
+        for (i, expr) in list.iter().enumerate() {
+            if let Some(mut res) = self.expr_starting_with(expr, token, visiting) {
+                for rest in list[i + 1..].iter() {
+                    res.extend(shortest_of(rest, &self.shortest)?);
+                }
+                return Some(res);
+            }
+            if !is_nullable(expr, self.nullable) {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn expr_starting_with(&self, expr: &Expr, token: &str, visiting: &mut HashSet<String>) -> Option<Vec<String>> {
+        match expr {
+            Expr::NonTerminal(name) => {
+                if !visiting.insert(name.clone()) {
+                    return None;
+                }
+                let res = self.expr_starting_with(&self.grammar.rule(name).unwrap().expr, token, visiting);
+                visiting.remove(name);
+                res
+            }
+            Expr::Sequence(items) => self.starting_with(&items.iter().collect::<Vec<&Expr>>(), token, visiting),
+            Expr::Choice(alternatives) => alternatives
+                .iter()
+                .find_map(|alternative| self.expr_starting_with(alternative, token, visiting)),
+            Expr::Optional(inner) | Expr::Repetition(inner) => self.expr_starting_with(inner, token, visiting),
+            terminal => {
+                if symbol(terminal).unwrap() == token {
+                    Some(vec![token.to_string()])
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    // an example for one option of a decision; if the token can only come
+    // from after the rule, the example ends with it
+    fn for_option(&self, option: &[&Expr], token: &str) -> Vec<String> {
+        match self.starting_with(option, token, &mut HashSet::new()) {
+            Some(example) => example,
+            None => {
+                let mut res = Vec::new();
+                for expr in option {
+                    res.extend(shortest_of(expr, &self.shortest).unwrap_or_default());
+                }
+                res.push(token.to_string());
+                res.push("...".to_string());
+                res
+            }
+        }
+    }
+}
+
+// rules reachable from the start rule
+fn reachable_rules(grammar: &Grammar) -> BTreeSet<String> {
+    let mut reachable = BTreeSet::new();
+    let mut pending = vec![grammar.start_rule().name.clone()];
+    while let Some(name) = pending.pop() {
+        if reachable.insert(name.clone()) {
+            pending.extend(grammar.rule(&name).unwrap().expr.nonterminals());
+        }
+    }
+    reachable
+}
+
+fn productive_rules(grammar: &Grammar) -> BTreeSet<String> {
+    let shortest = shortest_sentences(grammar);
+    shortest
+        .into_iter()
+        .filter(|(_, sentence)| sentence.is_some())
+        .map(|(name, _)| name)
+        .collect()
+}
+
+// the nonterminals that can be the first thing an expression parses
+fn left_nonterminals(expr: &Expr, nullable: &BTreeSet<String>, res: &mut BTreeSet<String>) {
+    match expr {
+        Expr::NonTerminal(name) => {
+            res.insert(name.clone());
+        }
+        Expr::Sequence(items) => {
+            for item in items {
+                left_nonterminals(item, nullable, res);
+                if !is_nullable(item, nullable) {
+                    break;
+                }
+            }
+        }
+        Expr::Choice(alternatives) => {
+            for alternative in alternatives {
+                left_nonterminals(alternative, nullable, res);
+            }
+        }
+        Expr::Optional(inner) | Expr::Repetition(inner) => left_nonterminals(inner, nullable, res),
+        _ => {}
+    }
+}
+
+fn left_recursion(grammar: &Grammar, nullable: &BTreeSet<String>) -> Vec<Vec<String>> {
+    let edges: BTreeMap<String, BTreeSet<String>> = grammar
+        .rules
+        .iter()
+        .map(|rule| {
+            let mut left = BTreeSet::new();
+            left_nonterminals(&rule.expr, nullable, &mut left);
+            (rule.name.clone(), left)
+        })
+        .collect();
+
+    // one cycle per rule that reaches itself, reported once per set of rules
+    let mut cycles: Vec<Vec<String>> = Vec::new();
+    let mut seen: BTreeSet<BTreeSet<String>> = BTreeSet::new();
+    for rule in grammar.rules.iter() {
+        let mut path = vec![rule.name.clone()];
+        if find_cycle(&rule.name, &edges, &mut path) {
+            let members: BTreeSet<String> = path.iter().cloned().collect();
+            if seen.insert(members) {
+                cycles.push(path);
+            }
+        }
+    }
+    cycles
+}
+
+fn find_cycle(target: &str, edges: &BTreeMap<String, BTreeSet<String>>, path: &mut Vec<String>) -> bool {
+    let current = path.last().unwrap().clone();
+    for next in edges[&current].iter() {
+        if next == target {
+            path.push(next.clone());
+            return true;
+        }
+        if path.contains(next) {
+            continue;
+        }
+        path.push(next.clone());
+        if find_cycle(target, edges, path) {
+            return true;
+        }
+        path.pop();
+    }
+    false
+}
+
+fn flatten(lookahead: &Lookahead) -> BTreeSet<String> {
+    lookahead
+        .iter()
+        .map(|tokens| match tokens.first() {
+            Some(token) => token.to_string(),
+            None => "ε".to_string(),
+        })
+        .collect()
+}
+
+pub fn analyze(grammar: &Grammar) -> Analysis {
+    let nullable = nullable_rules(grammar);
+    let all_sets: Vec<Sets> = (1..=MAX_LOOKAHEAD).map(|k| Sets::compute(grammar, k)).collect();
+    let sets = &all_sets[0];
+
+    let examples = Examples {
+        grammar,
+        nullable: &nullable,
+        shortest: shortest_sentences(grammar),
+    };
+
+    let mut conflicts = Vec::new();
+    for decision in decisions(grammar) {
+        let sides: Vec<Lookahead> = decision
+            .options
+            .iter()
+            .map(|option| sets.lookahead_after(option, &decision.rule))
+            .collect();
+        let tokens: BTreeSet<String> = sides[0]
+            .intersection(&sides[1])
+            .map(|tokens| tokens[0].to_string())
+            .collect();
+        if tokens.is_empty() {
+            continue;
+        }
+
+        // the smallest k at which the two sides no longer overlap
+        let lookahead = all_sets.iter().skip(1).find_map(|sets_k| {
+            let first = sets_k.lookahead_after(&decision.options[0], &decision.rule);
+            let second = sets_k.lookahead_after(&decision.options[1], &decision.rule);
+            if first.is_disjoint(&second) {
+                Some(sets_k.k)
+            } else {
+                None
+            }
+        });
+
+        let token = tokens.iter().next().unwrap();
+        conflicts.push(Conflict {
+            rule: decision.rule.clone(),
+            description: decision.description.clone(),
+            examples: decision
+                .options
+                .iter()
+                .map(|option| examples.for_option(option, token))
+                .collect(),
+            tokens,
+            lookahead,
+        });
+    }
+
+    let reachable = reachable_rules(grammar);
+    let productive = productive_rules(grammar);
+
+    Analysis {
+        first: sets
+            .first
+            .iter()
+            .map(|(name, first)| (name.clone(), flatten(first)))
+            .collect(),
+        follow: sets
+            .follow
+            .iter()
+            .map(|(name, follow)| (name.clone(), flatten(follow)))
+            .collect(),
+        conflicts,
+        left_recursion: left_recursion(grammar, &nullable),
+        unreachable: grammar
+            .rules
+            .iter()
+            .filter(|rule| !reachable.contains(&rule.name))
+            .map(|rule| rule.name.clone())
+            .collect(),
+        unproductive: grammar
+            .rules
+            .iter()
+            .filter(|rule| !productive.contains(&rule.name))
+            .map(|rule| rule.name.clone())
+            .collect(),
+        nullable,
+    }
+}
+
+fn join(set: &BTreeSet<String>) -> String {
+    set.iter().cloned().collect::<Vec<String>>().join(" ")
+}
+
+impl Analysis {
+    pub fn is_ll1(&self) -> bool {
+        self.conflicts.is_empty() && self.left_recursion.is_empty()
+    }
+
+    pub fn report(&self) -> String {
+        let mut res = "FIRST sets (ε: the rule can be empty)\n".to_string();
+        for (name, first) in self.first.iter() {
+            res += &format!("    {} : {}\n", name, join(first));
+        }
+
+        res += "\nFOLLOW sets ($: end of input)\n";
+        for (name, follow) in self.follow.iter() {
+            res += &format!("    {} : {}\n", name, join(follow));
+        }
+
+        res += &format!("\nLL(1) conflicts: {}\n", self.conflicts.len());
+        for conflict in self.conflicts.iter() {
+            res += &format!(
+                "    {}: {}\n        both can start with: {}\n",
+                conflict.rule,
+                conflict.description,
+                join(&conflict.tokens)
+            );
+            for (i, example) in conflict.examples.iter().enumerate() {
+                res += &format!("        example {}: {}\n", i + 1, example.join(" "));
+            }
+            res += &match conflict.lookahead {
+                Some(k) => format!("        resolved with {} tokens of lookahead\n", k),
+                None => format!(
+                    "        not resolved with up to {} tokens of lookahead\n",
+                    MAX_LOOKAHEAD
+                ),
+            };
+        }
+
+        res += &format!("\nLeft recursion: {}\n", self.left_recursion.len());
+        for cycle in self.left_recursion.iter() {
+            res += &format!("    {}\n", cycle.join(" -> "));
+        }
+
+        res += &format!("\nUnreachable rules: {}\n", self.unreachable.join(" "));
+        res += &format!("Unproductive rules: {}\n", self.unproductive.join(" "));
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn x_grammar_analysis() {
+        let analysis = analyze(&Grammar::x());
+
+        assert_eq!(
+            join(&analysis.first["DataType"]),
+            "char double float int long short unsigned"
+        );
+        assert!(analysis.follow["Program"].contains(END));
+        assert!(analysis.follow["Expression"].contains(";"));
+        assert!(analysis.nullable.is_empty());
+        assert!(analysis.left_recursion.is_empty());
+        assert!(analysis.unreachable.is_empty());
+        assert!(analysis.unproductive.is_empty());
+
+        // Statement tries Assignment before (Expression ;), both start with
+        // Identifier, and = is also a binary operator so no lookahead helps
+        let statement = analysis
+            .conflicts
+            .iter()
+            .find(|c| c.rule == "Statement" && c.description.starts_with("alternatives 1 and 5"))
+            .unwrap();
+        assert!(statement.tokens.contains("Identifier"));
+        assert_eq!(statement.lookahead, None);
+        assert_eq!(statement.examples[0][0], "Identifier");
+
+        // declarations and function definitions share the DeclarationType prefix
+        assert!(analysis
+            .conflicts
+            .iter()
+            .any(|c| c.rule == "Block" && c.description.contains("Declaration") && c.lookahead.is_none()));
+
+        let grammar = Grammar::parse("E := E + T | T\nT := Identifier\nU := U").unwrap();
+        let analysis = analyze(&grammar);
+        assert_eq!(
+            analysis.left_recursion,
+            vec![
+                vec!["E".to_string(), "E".to_string()],
+                vec!["U".to_string(), "U".to_string()]
+            ]
+        );
+        assert_eq!(analysis.unreachable, vec!["U".to_string()]);
+        assert_eq!(analysis.unproductive, vec!["U".to_string()]);
+    }
+}
@@ -0,0 +1,546 @@
+//The interpreter: runs a program that checks without errors by walking its
+//tree, starting with the global initializers and then main. Every value is
+//a consteval::Constant in the type C gives it, so integers have the width
+//and signedness of their type: unsigned arithmetic wraps around, while a
+//signed overflow, an integer division by zero, a floating value that does
+//not fit the integer it is converted to and a read of a variable that was
+//never assigned stop the program with a runtime error at their span.
+//A call gets a frame for its parameters and locals; a nested function's
+//frame links to the latest frame of the function it is defined in, which
+//is how it reads and assigns the variables around it. The intrinsics read
+//the words of the input and write to the output as the program runs. A limit on the
+//number of expressions evaluated stops a program that runs too long, for
+//the fuzzer.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, Write};
+use std::thread;
+
+use crate::ast::{Block, DataType, Declaration, Expression, ExpressionKind, FunctionDefinition, Program, StatementKind};
+use crate::consteval::{binary, unary, Constant, Value};
+use crate::diagnostics::{check_source, Diagnostic, Severity, Span};
+use crate::intrinsics::{self, Intrinsic};
+use crate::resolve::{resolve, SymbolKind, SymbolTable};
+use crate::stage2_scanner::Scanner;
+use crate::stage3::Parser;
+
+// calls nested deeper than this are taken to be a recursion that never ends
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+// the stack of the thread that runs a program, enough for MAX_CALL_DEPTH
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+// the variables of one call, or of main
+struct Frame {
+    // the name of the function, or the span of main
+    body: Span,
+    // the frame of the function the called one is defined in
+    parent: Option<usize>,
+    // None until a variable is assigned
+    variables: HashMap<usize, Option<Constant>>,
+}
+
+// the values of the variables once main has returned, in declaration order
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outcome {
+    pub globals: Vec<(String, Constant)>,
+    // the variables of main, of its blocks too; None if never assigned
+    pub main: Vec<(String, Option<Constant>)>,
+}
+
+pub struct Interpreter<'a> {
+    xfilename: &'a str,
+    table: &'a SymbolTable,
+    // the definition a function symbol calls
+    definitions: HashMap<usize, &'a FunctionDefinition>,
+    // the body a function is defined in, None for a global function
+    enclosing: HashMap<Span, Option<Span>>,
+    // the symbol of every declared name, by its span
+    declared: HashMap<Span, usize>,
+    globals: HashMap<usize, Constant>,
+    frames: Vec<Frame>,
+    input: &'a mut (dyn BufRead + Send),
+    // the words of the last input line that are not read yet
+    words: VecDeque<String>,
+    output: &'a mut (dyn Write + Send),
+    // the most expressions a run may evaluate, None for no limit
+    pub max_steps: Option<u64>,
+    steps: u64,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(
+        xfilename: &'a str,
+        program: &'a Program,
+        table: &'a SymbolTable,
+        input: &'a mut (dyn BufRead + Send),
+        output: &'a mut (dyn Write + Send),
+    ) -> Interpreter<'a> {
+        let declared: HashMap<Span, usize> = table
+            .symbols
+            .iter()
+            .enumerate()
+            .filter(|(_, symbol)| symbol.kind != SymbolKind::Intrinsic)
+            .map(|(i, symbol)| (symbol.span, i))
+            .collect();
+        let mut interpreter = Interpreter {
+            xfilename,
+            table,
+            definitions: HashMap::new(),
+            enclosing: HashMap::new(),
+            declared,
+            globals: HashMap::new(),
+            frames: Vec::new(),
+            input,
+            words: VecDeque::new(),
+            output,
+            max_steps: None,
+            steps: 0,
+        };
+        interpreter.nested(&program.main.block, program.main.span);
+        for function in program.functions.iter() {
+            interpreter.define(function, None);
+        }
+        interpreter
+    }
+
+    // a function definition, and every symbol of the same function
+    fn define(&mut self, function: &'a FunctionDefinition, enclosing: Option<Span>) {
+        let name = Span::of_token(&function.name);
+        self.enclosing.insert(name, enclosing);
+        if let Some(&symbol) = self.declared.get(&name) {
+            let (text, scope) = (&self.table.symbols[symbol].name, self.table.symbols[symbol].scope);
+            for (i, other) in self.table.symbols.iter().enumerate() {
+                if other.kind.is_function() && &other.name == text && other.scope == scope {
+                    self.definitions.insert(i, function);
+                }
+            }
+        }
+        self.nested(&function.block, name);
+    }
+
+    // the functions defined in a body, in its while and if blocks too
+    fn nested(&mut self, block: &'a Block, body: Span) {
+        for statement in block.statements.iter() {
+            if let StatementKind::While(_, inner) | StatementKind::If(_, inner) = &statement.kind {
+                self.nested(inner, body);
+            }
+        }
+        for function in block.functions.iter() {
+            self.define(function, Some(body));
+        }
+    }
+
+    fn error(&self, code: &'static str, message: String, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message,
+            file: self.xfilename.to_string(),
+            span,
+            related: Vec::new(),
+        }
+    }
+
+    // a value converted to the type of a variable, a parameter or a return;
+    // a floating value must fit an integer type
+    fn convert(&self, value: Constant, to: DataType, span: Span) -> Result<Constant, Diagnostic> {
+        let res = value.convert(to);
+        if let Value::Floating(floating) = value.value {
+            if !to.is_floating() && res.as_f64() != floating.trunc() {
+                let message = format!("The value {} does not fit in {}.", value, to);
+                return Err(self.error("X0603", message, span));
+            }
+        }
+        Ok(res)
+    }
+
+    // the frame that holds a local variable or parameter: the current one
+    // or one the static links lead to
+    fn frame_of(&self, symbol: usize) -> Option<usize> {
+        let mut frame = self.frames.len().checked_sub(1);
+        while let Some(index) = frame {
+            if self.frames[index].variables.contains_key(&symbol) {
+                return Some(index);
+            }
+            frame = self.frames[index].parent;
+        }
+        None
+    }
+
+    fn read(&self, symbol: usize, span: Span) -> Result<Constant, Diagnostic> {
+        let value = match self.frame_of(symbol) {
+            Some(frame) => self.frames[frame].variables[&symbol],
+            None => self.globals.get(&symbol).copied(),
+        };
+        value.ok_or_else(|| {
+            let message = format!("'{}' is read before it is assigned.", self.table.symbols[symbol].name);
+            self.error("X0602", message, span)
+        })
+    }
+
+    fn assign(&mut self, symbol: usize, value: Constant, span: Span) -> Result<Constant, Diagnostic> {
+        let value = self.convert(value, self.table.symbols[symbol].data_type, span)?;
+        match self.frame_of(symbol) {
+            Some(frame) => self.frames[frame].variables.insert(symbol, Some(value)),
+            None => self.globals.insert(symbol, value).map(Some),
+        };
+        Ok(value)
+    }
+
+    // the next word of the input, None at its end
+    fn word(&mut self) -> Option<String> {
+        while self.words.is_empty() {
+            let mut line = String::new();
+            if self.input.read_line(&mut line).unwrap_or(0) == 0 {
+                return None;
+            }
+            self.words.extend(line.split_whitespace().map(|word| word.to_string()));
+        }
+        self.words.pop_front()
+    }
+
+    fn intrinsic(&mut self, intrinsic: &Intrinsic, arguments: Vec<Constant>, span: Span) -> Result<Constant, Diagnostic> {
+        let mut arguments = arguments.into_iter().zip(intrinsic.parameters);
+        let mut argument = || arguments.next().map(|(argument, &parameter)| argument.convert(parameter)).unwrap();
+        let text = match intrinsic.name {
+            "print_int" | "print_float" => argument().to_string(),
+            "print_char" => (argument().convert(DataType::UnsignedChar).as_f64() as u8 as char).to_string(),
+            // read_int and read_float
+            _ => {
+                let word = self.word();
+                let value = match (intrinsic.return_type, word.as_deref()) {
+                    (DataType::Int, Some(word)) => word.parse::<i32>().ok().map(|value| Value::Integer(value as i128)),
+                    (_, Some(word)) => word.parse::<f64>().ok().map(Value::Floating),
+                    (_, None) => None,
+                };
+                return match (value, word) {
+                    (Some(value), _) => Ok(Constant::new(intrinsic.return_type, value)),
+                    (None, Some(word)) => {
+                        let message = format!("'{}' expected {} in the input, but found '{}'.", intrinsic.name, intrinsic.return_type, word);
+                        Err(self.error("X0605", message, span))
+                    }
+                    (None, None) => {
+                        let message = format!("'{}' found the end of the input.", intrinsic.name);
+                        Err(self.error("X0605", message, span))
+                    }
+                };
+            }
+        };
+        // flushed, so that what is written shows before the next read; -1
+        // like printf if the output cannot be written
+        let count = match write!(self.output, "{}", text).and_then(|_| self.output.flush()) {
+            Ok(()) => text.chars().count() as i128,
+            Err(_) => -1,
+        };
+        Ok(Constant::new(intrinsic.return_type, Value::Integer(count)))
+    }
+
+    fn call(&mut self, symbol: usize, arguments: Vec<Constant>, span: Span) -> Result<Constant, Diagnostic> {
+        if self.table.symbols[symbol].kind == SymbolKind::Intrinsic {
+            let intrinsic = intrinsics::find(&self.table.symbols[symbol].name).unwrap();
+            return self.intrinsic(intrinsic, arguments, span);
+        }
+        if self.frames.len() > MAX_CALL_DEPTH {
+            let message = format!("Calls are nested more than {} deep; the recursion does not seem to end.", MAX_CALL_DEPTH);
+            return Err(self.error("X0604", message, span));
+        }
+        let function = self.definitions[&symbol];
+        let name = Span::of_token(&function.name);
+        // the latest call of the function this one is defined in
+        let parent = self.enclosing[&name].and_then(|body| self.frames.iter().rposition(|frame| frame.body == body));
+        let mut variables = HashMap::new();
+        for (parameter, argument) in function.parameters.iter().zip(arguments) {
+            let value = self.convert(argument, parameter.data_type, span)?;
+            variables.insert(self.declared[&Span::of_token(&parameter.name)], Some(value));
+        }
+        self.frames.push(Frame {
+            body: name,
+            parent,
+            variables,
+        });
+        let res = self.block(&function.block);
+        self.frames.pop();
+        // check_control_flow makes sure a function returns a value
+        match res? {
+            Some(value) => self.convert(value, function.return_type, span),
+            None => Ok(Constant::new(function.return_type, Value::Integer(0))),
+        }
+    }
+
+    pub fn evaluate(&mut self, expression: &Expression) -> Result<Constant, Diagnostic> {
+        let span = expression.span;
+        self.steps += 1;
+        if let Some(max_steps) = self.max_steps.filter(|&max_steps| self.steps > max_steps) {
+            let message = format!("The program evaluated more than {} expressions; it does not seem to end.", max_steps);
+            return Err(self.error("X0606", message, span));
+        }
+        match &expression.kind {
+            // check rejects a program with an integer constant out of range
+            ExpressionKind::Constant(token) => Constant::of_token(token).ok_or_else(|| {
+                let message = format!("The integer constant {} does not fit in unsigned long, the widest integer type.", token.text);
+                self.error("X0504", message, span)
+            }),
+            ExpressionKind::Identifier(name) => self.read(self.table.uses[&Span::of_token(name)], span),
+            ExpressionKind::Call(name, arguments) => {
+                let mut values = Vec::new();
+                for argument in arguments.iter() {
+                    values.push(self.evaluate(argument)?);
+                }
+                self.call(self.table.uses[&Span::of_token(name)], values, span)
+            }
+            ExpressionKind::Assign(target, value) => {
+                let value = self.evaluate(value)?;
+                self.assign(self.table.uses[&Span::of_token(target)], value, span)
+            }
+            ExpressionKind::Unary(operator, operand) => {
+                let operand = self.evaluate(operand)?;
+                let (res, overflow) = unary(&operator.text, operand).unwrap();
+                if overflow {
+                    let message = format!("The result of '{}' overflows {}.", operator.text, res.data_type);
+                    return Err(self.error("X0601", message, span));
+                }
+                Ok(res)
+            }
+            ExpressionKind::Binary(operator, lhs, rhs) => {
+                let text = operator.text.as_str();
+                let lhs = self.evaluate(lhs)?;
+                // the right operand is not evaluated once the left one decides
+                if text == "&&" || text == "||" {
+                    let res = if lhs.is_true() == (text == "||") { lhs.is_true() } else { self.evaluate(rhs)?.is_true() };
+                    return Ok(Constant::new(DataType::Int, Value::Integer(res as i128)));
+                }
+                let rhs = self.evaluate(rhs)?;
+                match binary(text, lhs, rhs) {
+                    Some((res, false)) => Ok(res),
+                    Some((res, true)) => {
+                        let message = format!("The result of '{}' overflows {}.", text, res.data_type);
+                        Err(self.error("X0601", message, span))
+                    }
+                    // typecheck only lets integer divisions get here
+                    None => {
+                        let message = format!("The divisor of '{}' is zero.", text);
+                        Err(self.error("X0600", message, span))
+                    }
+                }
+            }
+        }
+    }
+
+    fn declarations(&mut self, declarations: &[Declaration]) -> Result<(), Diagnostic> {
+        for declaration in declarations.iter() {
+            let Declaration::Variable(variable) = declaration else {
+                continue;
+            };
+            let symbol = self.declared[&Span::of_token(&variable.name)];
+            let value = match &variable.initializer {
+                Some(initializer) => {
+                    let value = self.evaluate(initializer)?;
+                    Some(self.convert(value, variable.data_type, initializer.span)?)
+                }
+                None => None,
+            };
+            match self.table.symbols[symbol].kind {
+                // globals start at zero like in C
+                SymbolKind::Global => {
+                    let zero = Constant::new(variable.data_type, Value::Integer(0));
+                    self.globals.insert(symbol, value.unwrap_or(zero));
+                }
+                _ => {
+                    self.frames.last_mut().unwrap().variables.insert(symbol, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // runs a block; Some is the value of a return
+    fn block(&mut self, block: &Block) -> Result<Option<Constant>, Diagnostic> {
+        // a block that runs again starts with its variables unassigned
+        self.declarations(&block.declarations)?;
+        for statement in block.statements.iter() {
+            match &statement.kind {
+                StatementKind::Expression(expression) => {
+                    self.evaluate(expression)?;
+                }
+                StatementKind::Return(value) => return Ok(Some(self.evaluate(value)?)),
+                StatementKind::If(condition, body) => {
+                    if self.evaluate(condition)?.is_true() {
+                        if let Some(value) = self.block(body)? {
+                            return Ok(Some(value));
+                        }
+                    }
+                }
+                StatementKind::While(condition, body) => {
+                    while self.evaluate(condition)?.is_true() {
+                        if let Some(value) = self.block(body)? {
+                            return Ok(Some(value));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // runs the global initializers and main
+    pub fn run(&mut self, program: &Program) -> Result<Outcome, Diagnostic> {
+        self.declarations(&program.declarations)?;
+        self.frames.push(Frame {
+            body: program.main.span,
+            parent: None,
+            variables: HashMap::new(),
+        });
+        self.block(&program.main.block)?;
+        let main = self.frames.pop().unwrap();
+
+        let mut globals: Vec<usize> = self.globals.keys().copied().collect();
+        globals.sort();
+        let mut locals: Vec<usize> = main.variables.keys().copied().collect();
+        locals.sort();
+        let name = |symbol: usize| self.table.symbols[symbol].name.clone();
+        Ok(Outcome {
+            globals: globals.into_iter().map(|symbol| (name(symbol), self.globals[&symbol])).collect(),
+            main: locals.into_iter().map(|symbol| (name(symbol), main.variables[&symbol])).collect(),
+        })
+    }
+}
+
+// checks, then runs a source on a thread with a stack deep enough for
+// MAX_CALL_DEPTH calls; the diagnostics of check if it finds errors, or the
+// runtime error. What the program writes goes to output as it runs.
+pub fn run_source(
+    xfilename: &str,
+    source: &str,
+    input: &mut (dyn BufRead + Send),
+    output: &mut (dyn Write + Send),
+) -> Result<Outcome, Vec<Diagnostic>> {
+    run_source_limited(xfilename, source, input, output, None)
+}
+
+// run_source that stops after max_steps expressions with a runtime error
+pub fn run_source_limited(
+    xfilename: &str,
+    source: &str,
+    input: &mut (dyn BufRead + Send),
+    output: &mut (dyn Write + Send),
+    max_steps: Option<u64>,
+) -> Result<Outcome, Vec<Diagnostic>> {
+    let diagnostics = check_source(xfilename, source);
+    if diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error) {
+        return Err(diagnostics);
+    }
+    let tokens = Scanner::from_source(xfilename, source).run().all_tokens.clone();
+    let program = Parser::from_tokens(tokens).parse().unwrap();
+    let (table, _) = resolve(xfilename, &program);
+    thread::scope(|scope| {
+        thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, || {
+                let mut interpreter = Interpreter::new(xfilename, &program, &table, input, output);
+                interpreter.max_steps = max_steps;
+                interpreter.run(&program)
+            })
+            .unwrap()
+            .join()
+            .unwrap()
+    })
+    .map_err(|e| vec![e])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::test_support::{codes, parse};
+
+    fn run(source: &str) -> Result<Outcome, Vec<Diagnostic>> {
+        run_source("test.x", source, &mut "".as_bytes(), &mut Vec::new())
+    }
+
+    #[test]
+    fn examples() {
+        // Foo squares 1.4 seven times in float
+        let outcome = run(&std::fs::read_to_string("example1.x").unwrap()).unwrap();
+        assert!(outcome.globals.is_empty());
+        let expected = (0..7).fold(1.4f32, |value, _| value * value);
+        assert_eq!(outcome.main, [("Value".to_string(), Some(Constant::new(DataType::Float, Value::Floating(expected as f64))))]);
+
+        // Bar(5.7, 2, 1005) takes the second if, and R1 = Foo(1005) + 5.7 is
+        // infinite, which does not fit R1
+        let diagnostics = run(&std::fs::read_to_string("example2.x").unwrap()).unwrap_err();
+        assert_eq!(codes(&diagnostics), [("X0603", 31, 8)]);
+        assert_eq!(diagnostics[0].message, "The value inf does not fit in int.");
+    }
+
+    #[test]
+    fn semantics() {
+        // a nested function reads and assigns the parameters and locals of the
+        // function around it, also from a recursive call of its own
+        let source = "int calls;\nunsigned char byte = 250;\nint outer(int n);\nvoid main(){\nint r;\nlong big;\nunsigned int u;\nr = outer(5);\nbyte = byte + 10;\nbig = 2147483647;\nbig = big + 1;\nu = 0;\nu = u - 1;\nr = 7 / 2 + -7 % 3;\n}\nint outer(int n) {\nint total = 0;\nint add(int k);\nadd(n);\nreturn total;\nint add(int k) {\ncalls = calls + 1;\nif (k > 0) {\ntotal = total + k + n;\nadd(k - 1);\n}\nreturn 0;\n}\n}\n";
+        let outcome = run(source).unwrap();
+        let int = |data_type, value| Constant::new(data_type, Value::Integer(value));
+        assert_eq!(outcome.globals, [("calls".to_string(), int(DataType::Int, 6)), ("byte".to_string(), int(DataType::UnsignedChar, 4))]);
+        assert_eq!(
+            outcome.main,
+            [
+                ("r".to_string(), Some(int(DataType::Int, 2))),
+                ("big".to_string(), Some(int(DataType::Long, 2147483648))),
+                ("u".to_string(), Some(int(DataType::UnsignedInt, 4294967295))),
+            ]
+        );
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let error = |source: &str| {
+            let diagnostics = run(source).unwrap_err();
+            assert_eq!(diagnostics.len(), 1);
+            (diagnostics[0].code, diagnostics[0].span.start_line, diagnostics[0].span.start_char, diagnostics[0].message.clone())
+        };
+        let source = "int zero;\nvoid main(){\nint x;\nx = 1 / zero;\n}\n";
+        assert_eq!(error(source), ("X0600", 3, 4, "The divisor of '/' is zero.".to_string()));
+        let source = "void main(){\nint x = 2147483647;\nx = x + 1;\n}\n";
+        assert_eq!(error(source), ("X0601", 2, 4, "The result of '+' overflows int.".to_string()));
+        let source = "int g;\nvoid main(){\nint x;\nif (g) {\nx = 1;\n}\ng = x;\n}\n";
+        assert_eq!(error(source), ("X0602", 6, 4, "'x' is read before it is assigned.".to_string()));
+        let source = "int f(int n);\nvoid main(){\nint x;\nx = f(1);\n}\nint f(int n) { return f(n + 1); }\n";
+        assert_eq!(error(source).0, "X0604");
+        let source = "void main(){\nint x = 0;\nwhile (1) {\nx = 1 - x;\n}\n}\n";
+        let diagnostics = run_source_limited("test.x", source, &mut "".as_bytes(), &mut Vec::new(), Some(100)).unwrap_err();
+        assert_eq!(codes(&diagnostics), [("X0606", 3, 8)]);
+        assert_eq!(diagnostics[0].message, "The program evaluated more than 100 expressions; it does not seem to end.");
+
+        // a program with errors is not run
+        assert_eq!(codes(&run("void main(){\nx = 1;\n}\n").unwrap_err()), [("X0200", 1, 0)]);
+        let source = "void main(){\nunsigned long x;\nx = 99999999999999999999;\n}\n";
+        assert_eq!(codes(&run(source).unwrap_err()), [("X0504", 2, 4)]);
+        // and if it is run anyway, a constant out of range is a runtime error
+        let program = parse(source);
+        let (table, _) = resolve("test.x", &program);
+        let (mut input, mut output) = ("".as_bytes(), Vec::new());
+        let error = Interpreter::new("test.x", &program, &table, &mut input, &mut output).run(&program).unwrap_err();
+        assert_eq!((error.code, error.span.start_line, error.span.start_char), ("X0504", 2, 4));
+    }
+
+    #[test]
+    fn intrinsics() {
+        // print returns the number of characters written, read takes the
+        // next word of any line
+        let source = "void main(){\nint n;\ndouble d;\nn = print_int(read_int() - 1) + print_char(32);\nd = read_float();\nn = n + print_float(d / 2) + print_char(266);\n}\n";
+        let mut output = Vec::new();
+        let outcome = run_source("test.x", source, &mut "  42\n\n 5 rest".as_bytes(), &mut output).unwrap();
+        assert_eq!(output, b"41 2.5\n");
+        assert_eq!(outcome.main[0], ("n".to_string(), Some(Constant::new(DataType::Int, Value::Integer(7)))));
+
+        // what was written before the runtime error is already out
+        let mut output = Vec::new();
+        let diagnostics = run_source("test.x", source, &mut "1 x".as_bytes(), &mut output).unwrap_err();
+        assert_eq!(output, b"0 ");
+        assert_eq!(diagnostics[0].message, "'read_float' expected double in the input, but found 'x'.");
+
+        // an output that cannot be written makes print return -1
+        let source = "void main(){\nint n;\nn = print_int(5);\n}\n";
+        let outcome = run_source("test.x", source, &mut "".as_bytes(), &mut &mut [0u8; 0][..]).unwrap();
+        assert_eq!(outcome.main[0], ("n".to_string(), Some(Constant::new(DataType::Int, Value::Integer(-1)))));
+    }
+}
@@ -0,0 +1,55 @@
+//Intrinsics: the functions every program can call without declaring them,
+//its only way to read input and write output. Name resolution gives a call
+//of one of these names, where no declaration of that name is visible, a
+//symbol of kind Intrinsic with the signature below, so calls are checked
+//like calls of any function; a program can still declare its own function
+//or variable of the same name, which hides the intrinsic. The interpreter
+//implements them, as would a code generator.
+
+use crate::ast::DataType;
+
+pub struct Intrinsic {
+    pub name: &'static str,
+    pub return_type: DataType,
+    pub parameters: &'static [DataType],
+    pub description: &'static str,
+}
+
+// the print functions return the number of characters they write, or -1 if
+// the output cannot be written, like printf
+pub const INTRINSICS: &[Intrinsic] = &[
+    Intrinsic {
+        name: "print_int",
+        return_type: DataType::Int,
+        parameters: &[DataType::Long],
+        description: "writes an integer in decimal",
+    },
+    Intrinsic {
+        name: "print_float",
+        return_type: DataType::Int,
+        parameters: &[DataType::Double],
+        description: "writes a floating value with the digits that identify it",
+    },
+    Intrinsic {
+        name: "print_char",
+        return_type: DataType::Int,
+        parameters: &[DataType::Int],
+        description: "writes the character of a code converted to unsigned char, like 10 for a new line",
+    },
+    Intrinsic {
+        name: "read_int",
+        return_type: DataType::Int,
+        parameters: &[],
+        description: "reads the next word of the input as an int",
+    },
+    Intrinsic {
+        name: "read_float",
+        return_type: DataType::Double,
+        parameters: &[],
+        description: "reads the next word of the input as a double",
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Intrinsic> {
+    INTRINSICS.iter().find(|intrinsic| intrinsic.name == name)
+}
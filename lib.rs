@@ -0,0 +1,42 @@
+//The X language toolchain as a library: the character stream (stage 1), the
+//scanner and its tokens (stage 2), the parser and its AST (stage 3), the
+//XHTML highlighter and the diagnostics. The most used types are re-exported
+//at the top; main.rs is the command line on top of this crate.
+
+pub mod stage1;
+pub mod stage2_token;
+pub mod stage2_scanner;
+pub mod stage3;
+pub mod ast;
+pub mod operators;
+pub mod xhtml_out;
+pub mod diagnostics;
+pub mod grammar;
+pub mod codegen;
+pub mod x_parser;
+pub mod grammar_analysis;
+pub mod railroad;
+pub mod trace;
+pub mod coverage;
+pub mod generator;
+pub mod printer;
+pub mod minimize;
+pub mod fuzz;
+pub mod golden;
+pub mod cli;
+pub mod resolve;
+pub mod typecheck;
+pub mod definite;
+pub mod cfg;
+pub mod consteval;
+pub mod lint;
+pub mod callgraph;
+pub mod intrinsics;
+pub mod interpreter;
+
+pub use diagnostics::{check_file, check_source, Diagnostic, Severity, Span};
+pub use stage1::CStream;
+pub use stage2_scanner::Scanner;
+pub use stage2_token::{Token, TokenType};
+pub use stage3::{Parser, SyntaxError};
+pub use xhtml_out::XHTML;
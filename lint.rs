@@ -0,0 +1,786 @@
+//The linter: style and maintainability rules on top of the parser and the
+//name resolution. Every rule has an ID, a name and a default level, and a
+//check that returns its findings; a Config changes the level of a rule or
+//turns it off, and a comment turns a rule off for one line or a whole file:
+//
+//    x = y * 60; // lint: allow(magic-number)
+//    // lint: allow(L0001, naming)
+//    // lint: allow-file(nesting-depth)
+//
+//A trailing comment applies to its own line, a comment on a line of its
+//own to the next line with code.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use crate::ast::{Block, Declaration, Expression, ExpressionKind, Program, StatementKind};
+use crate::consteval::folded;
+use crate::diagnostics::{Diagnostic, Related, Severity, Span};
+use crate::printer::expression_source;
+use crate::resolve::{resolve, SymbolKind, SymbolTable};
+use crate::stage2_scanner::Scanner;
+use crate::stage2_token::Token;
+use crate::stage3::Parser;
+
+// while and if blocks nested deeper than this in a function are reported
+pub const MAX_NESTING: usize = 4;
+
+// what a rule checks: the program, its symbols and its comments
+pub struct Context<'a> {
+    pub program: &'a Program,
+    pub table: &'a SymbolTable,
+    pub comments: &'a [Token],
+}
+
+// a problem a rule found; it becomes a diagnostic at the rule's level
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+    pub span: Span,
+    pub message: String,
+    pub related: Vec<Related>,
+}
+
+pub struct Rule {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub default: Severity,
+    pub check: fn(&Context) -> Vec<Finding>,
+}
+
+// every rule of the linter; the SARIF rules table includes them
+pub const RULES: &[Rule] = &[
+    Rule {
+        id: "L0000",
+        name: "unknown-rule",
+        description: "unknown rule in a suppression",
+        default: Severity::Warning,
+        check: unknown_rules,
+    },
+    Rule {
+        id: "L0001",
+        name: "unused-variable",
+        description: "unused variable or parameter",
+        default: Severity::Warning,
+        check: unused_variables,
+    },
+    Rule {
+        id: "L0002",
+        name: "unused-function",
+        description: "unused function",
+        default: Severity::Warning,
+        check: unused_functions,
+    },
+    Rule {
+        id: "L0003",
+        name: "shadowing",
+        description: "shadowed identifier",
+        default: Severity::Warning,
+        check: shadowing,
+    },
+    Rule {
+        id: "L0004",
+        name: "assignment-in-condition",
+        description: "assignment inside a condition",
+        default: Severity::Warning,
+        check: assignments_in_conditions,
+    },
+    Rule {
+        id: "L0005",
+        name: "magic-number",
+        description: "unnamed numeric constant",
+        default: Severity::Note,
+        check: magic_numbers,
+    },
+    Rule {
+        id: "L0006",
+        name: "naming",
+        description: "naming convention",
+        default: Severity::Note,
+        check: naming,
+    },
+    Rule {
+        id: "L0007",
+        name: "nesting-depth",
+        description: "excessive nesting depth",
+        default: Severity::Warning,
+        check: nesting_depth,
+    },
+    Rule {
+        id: "L0008",
+        name: "constant-expression",
+        description: "constant expression that can be folded",
+        default: Severity::Note,
+        check: constant_expressions,
+    },
+];
+
+// a rule by its ID or its name
+pub fn find(rule: &str) -> Option<&'static Rule> {
+    RULES.iter().find(|candidate| candidate.id == rule || candidate.name == rule)
+}
+
+// off, note, warning or error
+fn parse_level(level: &str) -> Result<Option<Severity>, String> {
+    match level {
+        "off" => Ok(None),
+        "note" => Ok(Some(Severity::Note)),
+        "warning" => Ok(Some(Severity::Warning)),
+        "error" => Ok(Some(Severity::Error)),
+        _ => Err(format!("unknown level '{}', expected off, note, warning or error", level)),
+    }
+}
+
+// the level of every rule; None turns a rule off
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    // the rules whose level differs from their default, by ID
+    levels: HashMap<&'static str, Option<Severity>>,
+}
+
+impl Config {
+    pub fn level(&self, rule: &Rule) -> Option<Severity> {
+        self.levels.get(rule.id).copied().unwrap_or(Some(rule.default))
+    }
+
+    pub fn set(&mut self, rule: &str, level: &str) -> Result<(), String> {
+        let rule = find(rule).ok_or(format!("unknown rule '{}'", rule))?;
+        self.levels.insert(rule.id, parse_level(level)?);
+        Ok(())
+    }
+
+    // one "rule = level" per line, where the rule is an ID or a name; empty
+    // lines and lines starting with # are skipped
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        for (line_num, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let res = match line.split_once('=') {
+                Some((rule, level)) => config.set(rule.trim(), level.trim()),
+                None => Err("expected rule = level".to_string()),
+            };
+            res.map_err(|e| format!("line {}: {}", line_num, e))?;
+        }
+        Ok(config)
+    }
+
+    pub fn from_file(filename: &str) -> Result<Config, String> {
+        let text = fs::read_to_string(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        Config::parse(&text).map_err(|e| format!("{}: {}", filename, e))
+    }
+}
+
+// the rules a "lint: allow(...)" comment names, and whether it is
+// allow-file; None for other comments
+fn suppression(comment: &Token) -> Option<(bool, Vec<String>)> {
+    let text = comment.text.trim_start_matches('/').trim().strip_prefix("lint:")?.trim();
+    let (whole_file, rest) = match text.strip_prefix("allow-file") {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix("allow")?),
+    };
+    let rules = rest.trim().strip_prefix('(')?.strip_suffix(')')?;
+    Some((whole_file, rules.split(',').map(|rule| rule.trim().to_string()).collect()))
+}
+
+// the rules that are turned off for the whole file and for single lines
+#[derive(Default)]
+struct Suppressions {
+    file: HashSet<&'static str>,
+    lines: HashMap<i32, HashSet<&'static str>>,
+}
+
+impl Suppressions {
+    fn new(tokens: &[Token], comments: &[Token]) -> Suppressions {
+        let mut suppressions = Suppressions::default();
+        for comment in comments.iter() {
+            let Some((whole_file, rules)) = suppression(comment) else {
+                continue;
+            };
+            let rules = rules.iter().filter_map(|rule| find(rule)).map(|rule| rule.id);
+            if whole_file {
+                suppressions.file.extend(rules);
+                continue;
+            }
+            let trailing = tokens
+                .iter()
+                .any(|token| token.line_num == comment.line_num && token.char_pos < comment.char_pos);
+            let line = if trailing {
+                Some(comment.line_num)
+            } else {
+                tokens.iter().map(|token| token.line_num).find(|&line| line > comment.line_num)
+            };
+            if let Some(line) = line {
+                suppressions.lines.entry(line).or_default().extend(rules);
+            }
+        }
+        suppressions
+    }
+
+    fn suppresses(&self, rule: &Rule, span: &Span) -> bool {
+        self.file.contains(rule.id) || self.lines.get(&span.start_line).is_some_and(|rules| rules.contains(rule.id))
+    }
+}
+
+// where an expression is: the initializer of a declaration, a statement
+// (or a return) or the condition of an if or while
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Place {
+    Initializer,
+    Statement,
+    Condition,
+}
+
+fn block_expressions<'a>(block: &'a Block, res: &mut Vec<(&'a Expression, Place)>) {
+    declaration_expressions(&block.declarations, res);
+    for statement in block.statements.iter() {
+        match &statement.kind {
+            StatementKind::Expression(expression) | StatementKind::Return(expression) => {
+                res.push((expression, Place::Statement))
+            }
+            StatementKind::While(condition, body) | StatementKind::If(condition, body) => {
+                res.push((condition, Place::Condition));
+                block_expressions(body, res);
+            }
+        }
+    }
+    for function in block.functions.iter() {
+        block_expressions(&function.block, res);
+    }
+}
+
+fn declaration_expressions<'a>(declarations: &'a [Declaration], res: &mut Vec<(&'a Expression, Place)>) {
+    for declaration in declarations.iter() {
+        if let Declaration::Variable(variable) = declaration {
+            if let Some(initializer) = &variable.initializer {
+                res.push((initializer, Place::Initializer));
+            }
+        }
+    }
+}
+
+// the expressions of a program that are not part of other expressions
+fn expressions(program: &Program) -> Vec<(&Expression, Place)> {
+    let mut res = Vec::new();
+    declaration_expressions(&program.declarations, &mut res);
+    block_expressions(&program.main.block, &mut res);
+    for function in program.functions.iter() {
+        block_expressions(&function.block, &mut res);
+    }
+    res
+}
+
+// an expression and everything in it, outermost first
+fn subexpressions<'a>(expression: &'a Expression, res: &mut Vec<&'a Expression>) {
+    res.push(expression);
+    match &expression.kind {
+        ExpressionKind::Constant(_) | ExpressionKind::Identifier(_) => {}
+        ExpressionKind::Call(_, arguments) => {
+            for argument in arguments.iter() {
+                subexpressions(argument, res);
+            }
+        }
+        ExpressionKind::Unary(_, operand) => subexpressions(operand, res),
+        ExpressionKind::Binary(_, lhs, rhs) => {
+            subexpressions(lhs, res);
+            subexpressions(rhs, res);
+        }
+        ExpressionKind::Assign(_, value) => subexpressions(value, res),
+    }
+}
+
+// every expression of a program, with where its outermost expression is
+fn all_expressions(program: &Program) -> Vec<(&Expression, Place)> {
+    let mut res = Vec::new();
+    for (expression, place) in expressions(program) {
+        let mut inner = Vec::new();
+        subexpressions(expression, &mut inner);
+        res.extend(inner.into_iter().map(|expression| (expression, place)));
+    }
+    res
+}
+
+// L0000: a suppression that names no rule would silently do nothing
+fn unknown_rules(context: &Context) -> Vec<Finding> {
+    let mut res = Vec::new();
+    for comment in context.comments.iter() {
+        let Some((_, rules)) = suppression(comment) else {
+            continue;
+        };
+        for rule in rules.iter().filter(|rule| find(rule).is_none()) {
+            res.push(Finding {
+                span: Span::of_token(comment),
+                message: format!("'{}' is not a lint rule, so it is not suppressed.", rule),
+                related: Vec::new(),
+            });
+        }
+    }
+    res
+}
+
+// L0001: variables and parameters that are never read; a variable that is
+// only assigned to counts as unused
+fn unused_variables(context: &Context) -> Vec<Finding> {
+    let mut read: HashSet<usize> = HashSet::new();
+    let mut assigned: HashSet<usize> = HashSet::new();
+    for (expression, _) in all_expressions(context.program) {
+        match &expression.kind {
+            ExpressionKind::Identifier(name) => read.extend(context.table.uses.get(&Span::of_token(name))),
+            ExpressionKind::Assign(target, _) => assigned.extend(context.table.uses.get(&Span::of_token(target))),
+            _ => {}
+        }
+    }
+    context
+        .table
+        .symbols
+        .iter()
+        .enumerate()
+        .filter(|(i, symbol)| !symbol.kind.is_function() && !read.contains(i))
+        .map(|(i, symbol)| Finding {
+            span: symbol.span,
+            message: if assigned.contains(&i) {
+                format!("'{}' is a {} that is assigned, but never read.", symbol.name, symbol.kind.describe())
+            } else {
+                format!("'{}' is a {} that is never used.", symbol.name, symbol.kind.describe())
+            },
+            related: Vec::new(),
+        })
+        .collect()
+}
+
+// L0002: functions that are never called, except by themselves; a
+// prototype and its definition are one function
+fn unused_functions(context: &Context) -> Vec<Finding> {
+    let table = context.table;
+    let bodies: HashMap<Span, Span> = context.program.definitions()
+        .iter()
+        .map(|function| (Span::of_token(&function.name), function.span))
+        .collect();
+    let function = |symbol: usize| (table.symbols[symbol].name.as_str(), table.symbols[symbol].scope);
+
+    let mut called = HashSet::new();
+    for (expression, _) in all_expressions(context.program) {
+        let ExpressionKind::Call(name, _) = &expression.kind else {
+            continue;
+        };
+        let span = Span::of_token(name);
+        let Some(&symbol) = table.uses.get(&span) else {
+            continue;
+        };
+        let recursive = table.symbols.iter().any(|other| {
+            (other.name.as_str(), other.scope) == function(symbol)
+                && bodies.get(&other.span).is_some_and(|body| body.contains(&span))
+        });
+        if !recursive {
+            called.insert(function(symbol));
+        }
+    }
+
+    // reported at the definition, or at the first prototype if there is none
+    let mut reported = HashSet::new();
+    let mut res = Vec::new();
+    for (i, symbol) in table.symbols.iter().enumerate() {
+        if !symbol.kind.is_function() || called.contains(&function(i)) || reported.contains(&function(i)) {
+            continue;
+        }
+        let span = table
+            .symbols
+            .iter()
+            .filter(|other| (other.name.as_str(), other.scope) == function(i))
+            .map(|other| other.span)
+            .find(|span| bodies.contains_key(span))
+            .unwrap_or(symbol.span);
+        reported.insert(function(i));
+        res.push(Finding {
+            span,
+            message: format!("'{}' is a {} that is never called.", symbol.name, symbol.kind.describe()),
+            related: Vec::new(),
+        });
+    }
+    res
+}
+
+// L0003: a declaration that hides a visible one of an enclosing scope
+fn shadowing(context: &Context) -> Vec<Finding> {
+    let table = context.table;
+    let mut res = Vec::new();
+    for (i, symbol) in table.symbols.iter().enumerate() {
+        let mut scope = table.scopes[symbol.scope].parent;
+        while let Some(outer) = scope {
+            // the latest declaration of the name before this one
+            let shadowed = table.scopes[outer]
+                .symbols
+                .iter()
+                .rev()
+                .find(|&&other| other < i && table.symbols[other].name == symbol.name);
+            if let Some(&shadowed) = shadowed {
+                let shadowed = &table.symbols[shadowed];
+                res.push(Finding {
+                    span: symbol.span,
+                    message: format!("'{}' shadows the {} of the same name.", symbol.name, shadowed.kind.describe()),
+                    related: vec![Related {
+                        span: shadowed.span,
+                        message: format!("the shadowed {} is declared here", shadowed.kind.describe()),
+                    }],
+                });
+                break;
+            }
+            scope = table.scopes[outer].parent;
+        }
+    }
+    res
+}
+
+// the operands of the && || and ! of a condition
+fn condition_operands<'a>(condition: &'a Expression, res: &mut Vec<&'a Expression>) {
+    match &condition.kind {
+        ExpressionKind::Binary(operator, lhs, rhs) if operator.text == "&&" || operator.text == "||" => {
+            condition_operands(lhs, res);
+            condition_operands(rhs, res);
+        }
+        ExpressionKind::Unary(operator, operand) if operator.text == "!" => condition_operands(operand, res),
+        _ => res.push(condition),
+    }
+}
+
+// L0004: assignments inside a condition, like while ((n = n - 1) > 0), and
+// an operand of the condition that is an assignment, like if (x = 2), which
+// check also reports as X0303
+fn assignments_in_conditions(context: &Context) -> Vec<Finding> {
+    let mut res = Vec::new();
+    for (condition, place) in expressions(context.program) {
+        if place != Place::Condition {
+            continue;
+        }
+        let mut operands = Vec::new();
+        condition_operands(condition, &mut operands);
+        for operand in operands {
+            let mut inner = Vec::new();
+            subexpressions(operand, &mut inner);
+            for expression in inner {
+                if let ExpressionKind::Assign(target, _) = &expression.kind {
+                    res.push(Finding {
+                        span: Span::of_token(target),
+                        message: format!(
+                            "'{}' is assigned inside a condition, where it is easy to miss; assign it in a statement of its own.",
+                            target.text
+                        ),
+                        related: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+    res
+}
+
+// L0005: numbers other than 0 and 1 in statements and conditions; the
+// initializer of a declaration is what gives a number its name
+fn magic_numbers(context: &Context) -> Vec<Finding> {
+    all_expressions(context.program)
+        .into_iter()
+        .filter(|(_, place)| *place != Place::Initializer)
+        .filter_map(|(expression, _)| match &expression.kind {
+            ExpressionKind::Constant(token) if !matches!(token.text.parse::<f64>(), Ok(value) if value == 0.0 || value == 1.0) => {
+                Some(Finding {
+                    span: Span::of_token(token),
+                    message: format!("{} is a magic number; give it a name with an initialized variable.", token.text),
+                    related: Vec::new(),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// L0006: variables and parameters start with a lowercase letter, except
+// globals in capitals like MAX_SIZE, and no name mixes underscores with
+// capitals like Test_value
+fn naming(context: &Context) -> Vec<Finding> {
+    let mut checked = HashSet::new();
+    let mut res = Vec::new();
+    for symbol in context.table.symbols.iter() {
+        // a prototype and its definition are checked once
+        if symbol.kind.is_function() && !checked.insert((symbol.name.as_str(), symbol.scope)) {
+            continue;
+        }
+        let name = &symbol.name;
+        let capitals = !name.chars().any(|c| c.is_lowercase());
+        let message = if !symbol.kind.is_function()
+            && name.starts_with(|c: char| c.is_uppercase())
+            && !(symbol.kind == SymbolKind::Global && capitals)
+        {
+            let suggestion: String = name.chars().take(1).flat_map(|c| c.to_lowercase()).chain(name.chars().skip(1)).collect();
+            format!(
+                "'{}' is a {}, so it should start with a lowercase letter, like '{}'.",
+                name,
+                symbol.kind.describe(),
+                suggestion
+            )
+        } else if name.contains('_')
+            && name.chars().any(|c| c.is_uppercase())
+            && !(symbol.kind == SymbolKind::Global && capitals)
+        {
+            format!("'{}' mixes underscores with capitals; use one style, like '{}'.", name, name.to_lowercase())
+        } else {
+            continue;
+        };
+        res.push(Finding {
+            span: symbol.span,
+            message,
+            related: Vec::new(),
+        });
+    }
+    res
+}
+
+fn nesting(block: &Block, depth: usize, res: &mut Vec<Finding>) {
+    for statement in block.statements.iter() {
+        let (keyword, body) = match &statement.kind {
+            StatementKind::While(_, body) => ("while", body),
+            StatementKind::If(_, body) => ("if", body),
+            _ => continue,
+        };
+        if depth < MAX_NESTING {
+            nesting(body, depth + 1, res);
+            continue;
+        }
+        let span = statement.span;
+        res.push(Finding {
+            span: Span {
+                end_line: span.start_line,
+                end_char: span.start_char + keyword.len() as i32,
+                ..span
+            },
+            message: format!(
+                "This {} block is nested {} deep; more than {} levels of while and if blocks are hard to follow.",
+                keyword,
+                depth + 1,
+                MAX_NESTING
+            ),
+            related: Vec::new(),
+        });
+    }
+    // a nested function starts again at the top
+    for function in block.functions.iter() {
+        nesting(&function.block, 0, res);
+    }
+}
+
+// L0007: while and if blocks nested more than MAX_NESTING deep, at the
+// outermost block that is too deep
+fn nesting_depth(context: &Context) -> Vec<Finding> {
+    let mut res = Vec::new();
+    nesting(&context.program.main.block, 0, &mut res);
+    for function in context.program.functions.iter() {
+        nesting(&function.block, 0, &mut res);
+    }
+    res
+}
+
+// the outermost parts of an expression that consteval::fold replaces by
+// their value; a negative number is one already
+fn foldable<'a>(expression: &'a Expression, res: &mut Vec<(&'a Expression, Expression)>) {
+    if let ExpressionKind::Unary(operator, operand) = &expression.kind {
+        if operator.text == "-" && matches!(operand.kind, ExpressionKind::Constant(_)) {
+            return;
+        }
+    }
+    if let Some(constant) = folded(expression) {
+        res.push((expression, constant));
+        return;
+    }
+    match &expression.kind {
+        ExpressionKind::Constant(_) | ExpressionKind::Identifier(_) => {}
+        ExpressionKind::Call(_, arguments) => {
+            for argument in arguments.iter() {
+                foldable(argument, res);
+            }
+        }
+        ExpressionKind::Unary(_, operand) => foldable(operand, res),
+        ExpressionKind::Binary(_, lhs, rhs) => {
+            foldable(lhs, res);
+            foldable(rhs, res);
+        }
+        ExpressionKind::Assign(_, value) => foldable(value, res),
+    }
+}
+
+// L0008: a constant computed in a statement or a condition, which could be
+// written as its value; initializers are left out, where a constant
+// expression like 60 * 60 * 24 says how the value comes about
+fn constant_expressions(context: &Context) -> Vec<Finding> {
+    let mut res = Vec::new();
+    for (expression, place) in expressions(context.program) {
+        if place == Place::Initializer {
+            continue;
+        }
+        let mut parts = Vec::new();
+        foldable(expression, &mut parts);
+        for (part, constant) in parts {
+            res.push(Finding {
+                span: part.span,
+                message: format!(
+                    "'{}' is always {}; it can be written as its value.",
+                    expression_source(part),
+                    expression_source(&constant)
+                ),
+                related: Vec::new(),
+            });
+        }
+    }
+    res
+}
+
+// the findings of every rule that is not off, as diagnostics at the level
+// of the rule, without the suppressed ones, sorted by position; tokens and
+// comments are those of the scanner
+pub fn lint(
+    xfilename: &str,
+    program: &Program,
+    table: &SymbolTable,
+    tokens: &[Token],
+    comments: &[Token],
+    config: &Config,
+) -> Vec<Diagnostic> {
+    let context = Context {
+        program,
+        table,
+        comments,
+    };
+    let suppressions = Suppressions::new(tokens, comments);
+    let mut diagnostics = Vec::new();
+    for rule in RULES.iter() {
+        let Some(severity) = config.level(rule) else {
+            continue;
+        };
+        for finding in (rule.check)(&context) {
+            if suppressions.suppresses(rule, &finding.span) {
+                continue;
+            }
+            diagnostics.push(Diagnostic {
+                severity,
+                code: rule.id,
+                message: finding.message,
+                file: xfilename.to_string(),
+                span: finding.span,
+                related: finding.related,
+            });
+        }
+    }
+    diagnostics.sort_by_key(|d| (d.span.start_line, d.span.start_char));
+    diagnostics
+}
+
+// scans, parses and lints a source; a program with a syntax error is not
+// linted, and the error is the only diagnostic
+pub fn lint_source(xfilename: &str, source: &str, config: &Config) -> Vec<Diagnostic> {
+    let mut scanner = Scanner::from_source(xfilename, source);
+    scanner.run();
+    let tokens = scanner.all_tokens.clone();
+    match Parser::from_tokens(tokens.clone()).parse() {
+        Ok(program) => {
+            let (table, _) = resolve(xfilename, &program);
+            lint(xfilename, &program, &table, &tokens, &scanner.comments, config)
+        }
+        Err(e) => vec![Diagnostic::from_syntax_error(xfilename, &tokens, &e)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::test_support::codes;
+
+    fn lint_default(source: &str) -> Vec<Diagnostic> {
+        lint_source("test.x", source, &Config::default())
+    }
+
+    #[test]
+    fn examples() {
+        // main's Value is only assigned, BarFoo's parameters shadow Bar's,
+        // and the capitalized locals and the unnamed constants are notes
+        let diagnostics = lint_default(&std::fs::read_to_string("example2.x").unwrap());
+        assert_eq!(
+            codes(&diagnostics),
+            [
+                ("L0001", 6, 10),
+                ("L0006", 6, 10),
+                ("L0005", 8, 16),
+                ("L0005", 8, 25),
+                ("L0005", 8, 30),
+                ("L0005", 8, 33),
+                ("L0006", 12, 10),
+                ("L0006", 22, 8),
+                ("L0006", 23, 8),
+                ("L0006", 24, 10),
+                ("L0005", 27, 20),
+                ("L0005", 27, 25),
+                ("L0005", 30, 21),
+                ("L0005", 30, 26),
+                ("L0003", 35, 21),
+                ("L0003", 35, 33),
+            ]
+        );
+        assert_eq!(diagnostics[1].message, "'Value' is a local variable, so it should start with a lowercase letter, like 'value'.");
+        assert_eq!(lint_default(&std::fs::read_to_string("example1.x").unwrap()).len(), 4);
+    }
+
+    #[test]
+    fn rules() {
+        let source = "int unused;\nint f(int a);\nvoid main(){\nint x;\nint y;\nx = f(2);\nwhile ((y = x) > 0 && x) {\nx = 0;\n}\nif (x) {\nif (x) {\nif (x) {\nif (x) {\nif (x) {\nx = 1;\n}\n}\n}\n}\n}\n}\nint f(int a) { int b; b = a; return 0; }\nint g(int n) { return g(n); }\n";
+        let diagnostics = lint_default(source);
+        assert_eq!(
+            codes(&diagnostics),
+            [
+                ("L0001", 0, 4),
+                ("L0001", 4, 4),
+                ("L0005", 5, 6),
+                ("L0004", 6, 8),
+                ("L0007", 13, 0),
+                ("L0001", 21, 19),
+                ("L0002", 22, 4),
+            ]
+        );
+        assert_eq!(diagnostics[0].message, "'unused' is a global variable that is never used.");
+        assert_eq!(diagnostics[1].message, "'y' is a local variable that is assigned, but never read.");
+        assert_eq!(
+            diagnostics[4].to_string(),
+            "test.x:13:0-13:2: warning[L0007]: This if block is nested 5 deep; more than 4 levels of while and if blocks are hard to follow."
+        );
+
+        // a condition that is an assignment, or an operand of && or ! that is one
+        let source = "void main(){\nint x;\nx = 0;\nif (x = 2) {\n}\nwhile (!(x = x - 1) && x) {\n}\n}\n";
+        let diagnostics: Vec<Diagnostic> = lint_default(source).into_iter().filter(|d| d.code == "L0004").collect();
+        assert_eq!(codes(&diagnostics), [("L0004", 3, 4), ("L0004", 5, 9)]);
+
+        // the outermost constant parts of statements and conditions, but not
+        // of initializers or negative numbers
+        let source = "long day = 60 * 60 * 24;\nvoid main(){\nint x;\nx = x - 2 * 3 + -(4 - 5) - -7;\nif (1 + 1 > 2) {\nx = 0;\n}\n}\n";
+        let diagnostics: Vec<Diagnostic> = lint_default(source).into_iter().filter(|d| d.code == "L0008").collect();
+        assert_eq!(codes(&diagnostics), [("L0008", 3, 8), ("L0008", 3, 16), ("L0008", 4, 4)]);
+        assert_eq!(diagnostics[1].message, "'- (4 - 5)' is always 1; it can be written as its value.");
+    }
+
+    #[test]
+    fn configuration() {
+        let source = "int Count;\nvoid main(){\nCount = 7; // lint: allow(magic-number)\n// lint: allow(L0006, no-such-rule)\nCount = Count * 2;\n}\n";
+        let diagnostics = lint_default(source);
+        assert_eq!(codes(&diagnostics), [("L0006", 0, 4), ("L0000", 3, 0), ("L0005", 4, 16)]);
+        assert_eq!(diagnostics[0].severity, Severity::Note);
+
+        let config = Config::parse("# stricter\nnaming = error\nL0005 = off\n\nunknown-rule=off\n").unwrap();
+        let diagnostics = lint_source("test.x", source, &config);
+        assert_eq!(codes(&diagnostics), [("L0006", 0, 4)]);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+
+        let source = "// lint: allow-file(naming, magic-number)\n".to_string() + source;
+        assert_eq!(lint_default(&source).len(), 1);
+
+        assert_eq!(Config::parse("naming = loud").unwrap_err(), "line 0: unknown level 'loud', expected off, note, warning or error");
+        assert_eq!(Config::parse("\nstyle = off").unwrap_err(), "line 1: unknown rule 'style'");
+        assert!(Config::parse("naming").is_err());
+    }
+}
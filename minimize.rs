@@ -0,0 +1,436 @@
+//Shrinks an X program that makes the Scanner, the Parser or another tool
+//misbehave to a small program that still does. What "misbehaves" means is a
+//predicate on the source text. Delta debugging (ddmin) first removes
+//declarations, statements and function definitions from the AST, unwraps
+//while and if statements and replaces expressions by their sub-expressions,
+//then removes tokens from the Scanner's token stream. Sources that do not
+//parse only get the token pass, and sources the Scanner panics on are
+//shrunk character by character.
+
+use std::collections::HashSet;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::process::Command;
+use std::sync::Arc;
+
+use crate::ast::{Block, Declaration, Expression, ExpressionKind, FunctionDefinition, Program, Statement, StatementKind};
+use crate::diagnostics::check_source;
+use crate::printer::{layout, program_source};
+use crate::stage2_scanner::Scanner;
+use crate::stage3::Parser;
+
+// true if f panics; the panic message is not printed
+pub fn panics<F: FnOnce()>(f: F) -> bool {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let res = panic::catch_unwind(AssertUnwindSafe(f)).is_err();
+    panic::set_hook(hook);
+    res
+}
+
+// a 1-minimal subset of items for which test holds (Zeller's ddmin): removing
+// any single item of it makes test fail. test must hold for all items.
+pub fn ddmin<T: Clone>(items: &[T], test: &mut dyn FnMut(&[T]) -> bool) -> Vec<T> {
+    if test(&[]) {
+        return Vec::new();
+    }
+    let mut items = items.to_vec();
+    let mut n = 2;
+    while items.len() >= 2 {
+        let chunks: Vec<Vec<T>> = items.chunks(items.len().div_ceil(n)).map(|c| c.to_vec()).collect();
+        let mut reduced = false;
+        for chunk in chunks.iter() {
+            if test(chunk) {
+                items = chunk.clone();
+                n = 2;
+                reduced = true;
+                break;
+            }
+        }
+        if !reduced {
+            for i in 0..chunks.len() {
+                let complement: Vec<T> = chunks
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .flat_map(|(_, chunk)| chunk.iter().cloned())
+                    .collect();
+                if test(&complement) {
+                    items = complement;
+                    n = usize::max(n - 1, 2);
+                    reduced = true;
+                    break;
+                }
+            }
+        }
+        if !reduced {
+            if n >= items.len() {
+                break;
+            }
+            n = usize::min(n * 2, items.len());
+        }
+    }
+    items
+}
+
+// rebuilds a program without the declarations, statements and function
+// definitions whose number is in removed, and with the while or if statement
+// numbered unwrap replaced by the statements of its block. They are numbered
+// in source order, the ones inside a function or statement after it.
+struct Pruner<'a> {
+    removed: &'a HashSet<usize>,
+    unwrap: Option<usize>,
+    next: usize,
+}
+
+impl Pruner<'_> {
+    // the number of the next node
+    fn number(&mut self) -> usize {
+        self.next += 1;
+        self.next - 1
+    }
+
+    fn declarations(&mut self, declarations: &[Declaration]) -> Vec<Declaration> {
+        declarations
+            .iter()
+            .filter(|_| {
+                let number = self.number();
+                !self.removed.contains(&number)
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn functions(&mut self, functions: &[FunctionDefinition]) -> Vec<FunctionDefinition> {
+        let mut res = Vec::new();
+        for function in functions.iter() {
+            let number = self.number();
+            let block = Arc::new(self.block(&function.block));
+            if !self.removed.contains(&number) {
+                res.push(FunctionDefinition {
+                    block,
+                    ..function.clone()
+                });
+            }
+        }
+        res
+    }
+
+    fn statements(&mut self, statements: &[Statement]) -> Vec<Statement> {
+        let mut res = Vec::new();
+        for statement in statements.iter() {
+            let number = self.number();
+            let kind = match &statement.kind {
+                StatementKind::While(condition, block) => StatementKind::While(condition.clone(), Arc::new(self.block(block))),
+                StatementKind::If(condition, block) => StatementKind::If(condition.clone(), Arc::new(self.block(block))),
+                kind => kind.clone(),
+            };
+            if self.removed.contains(&number) {
+                continue;
+            }
+            match kind {
+                StatementKind::While(_, block) | StatementKind::If(_, block) if self.unwrap == Some(number) => {
+                    res.extend(block.statements.iter().cloned());
+                }
+                kind => res.push(Statement {
+                    kind,
+                    span: statement.span,
+                }),
+            }
+        }
+        res
+    }
+
+    fn block(&mut self, block: &Block) -> Block {
+        Block {
+            declarations: self.declarations(&block.declarations),
+            statements: self.statements(&block.statements),
+            functions: self.functions(&block.functions),
+            span: block.span,
+        }
+    }
+
+    fn program(&mut self, program: &Program) -> Program {
+        let declarations = self.declarations(&program.declarations);
+        let mut main = program.main.clone();
+        main.block = self.block(&program.main.block);
+        Program {
+            declarations,
+            main,
+            functions: self.functions(&program.functions),
+            span: program.span,
+        }
+    }
+}
+
+fn prune(program: &Program, removed: &HashSet<usize>, unwrap: Option<usize>) -> (Program, usize) {
+    let mut pruner = Pruner {
+        removed,
+        unwrap,
+        next: 0,
+    };
+    let res = pruner.program(program);
+    (res, pruner.next)
+}
+
+// replaces the expression numbered target by its sub-expression numbered
+// choice; expressions are numbered in preorder
+struct Simplifier {
+    target: usize,
+    choice: usize,
+    next: usize,
+    done: bool,
+}
+
+impl Simplifier {
+    fn expression(&mut self, expression: &mut Expression) {
+        let number = self.next;
+        self.next += 1;
+        let mut children: Vec<&mut Expression> = match &mut expression.kind {
+            ExpressionKind::Call(_, arguments) => arguments.iter_mut().map(Arc::make_mut).collect(),
+            ExpressionKind::Unary(_, operand) | ExpressionKind::Assign(_, operand) => vec![Arc::make_mut(operand)],
+            ExpressionKind::Binary(_, lhs, rhs) => vec![Arc::make_mut(lhs), Arc::make_mut(rhs)],
+            ExpressionKind::Constant(_) | ExpressionKind::Identifier(_) => Vec::new(),
+        };
+        if number == self.target && self.choice < children.len() {
+            let child = children.swap_remove(self.choice).clone();
+            *expression = child;
+            self.done = true;
+            return;
+        }
+        for child in children {
+            self.expression(child);
+        }
+    }
+
+    fn block(&mut self, block: &mut Block) {
+        for declaration in block.declarations.iter_mut() {
+            self.declaration(declaration);
+        }
+        for statement in block.statements.iter_mut() {
+            match &mut statement.kind {
+                StatementKind::Expression(expression) | StatementKind::Return(expression) => self.expression(expression),
+                StatementKind::While(condition, block) | StatementKind::If(condition, block) => {
+                    self.expression(condition);
+                    self.block(Arc::make_mut(block));
+                }
+            }
+        }
+        for function in block.functions.iter_mut() {
+            self.block(Arc::make_mut(&mut function.block));
+        }
+    }
+
+    fn declaration(&mut self, declaration: &mut Declaration) {
+        if let Declaration::Variable(variable) = declaration {
+            if let Some(initializer) = variable.initializer.as_mut() {
+                self.expression(initializer);
+            }
+        }
+    }
+
+    fn program(&mut self, program: &mut Program) {
+        for declaration in program.declarations.iter_mut() {
+            self.declaration(declaration);
+        }
+        self.block(&mut program.main.block);
+        for function in program.functions.iter_mut() {
+            self.block(Arc::make_mut(&mut function.block));
+        }
+    }
+}
+
+// the program with one expression replaced, None if the expression has no
+// sub-expression numbered choice; and the number of expressions looked at
+fn simplify(program: &Program, target: usize, choice: usize) -> (Option<Program>, usize) {
+    let mut res = program.clone();
+    let mut simplifier = Simplifier {
+        target,
+        choice,
+        next: 0,
+        done: false,
+    };
+    simplifier.program(&mut res);
+    (if simplifier.done { Some(res) } else { None }, simplifier.next)
+}
+
+fn parse(source: &str) -> Option<Program> {
+    let mut program = None;
+    if panics(|| {
+        let tokens = Scanner::from_source("minimize.x", source).run().all_tokens.clone();
+        program = Parser::from_tokens(tokens).parse().ok();
+    }) {
+        return None;
+    }
+    program
+}
+
+// the AST pass; None if the source does not parse or the predicate does not
+// hold for it once it is printed from its AST
+pub fn minimize_ast(source: &str, interesting: &mut dyn FnMut(&str) -> bool) -> Option<String> {
+    let mut program = parse(source)?;
+    if !interesting(&program_source(&program)) {
+        return None;
+    }
+    loop {
+        let before = program_source(&program);
+
+        // remove declarations, statements and function definitions
+        let none = HashSet::new();
+        let count = prune(&program, &none, None).1;
+        let numbers: Vec<usize> = (0..count).collect();
+        let kept = ddmin(&numbers, &mut |kept| {
+            let removed: HashSet<usize> = numbers.iter().filter(|n| !kept.contains(n)).copied().collect();
+            interesting(&program_source(&prune(&program, &removed, None).0))
+        });
+        let removed: HashSet<usize> = numbers.iter().filter(|n| !kept.contains(n)).copied().collect();
+        program = prune(&program, &removed, None).0;
+
+        // unwrap while and if statements
+        let mut number = 0;
+        while number < prune(&program, &none, None).1 {
+            let candidate = prune(&program, &none, Some(number)).0;
+            let source = program_source(&candidate);
+            if source != program_source(&program) && interesting(&source) {
+                program = candidate;
+            } else {
+                number += 1;
+            }
+        }
+
+        // replace expressions by their sub-expressions
+        let mut target = 0;
+        let mut choice = 0;
+        loop {
+            match simplify(&program, target, choice) {
+                (Some(candidate), _) => {
+                    if interesting(&program_source(&candidate)) {
+                        program = candidate;
+                        choice = 0;
+                    } else {
+                        choice += 1;
+                    }
+                }
+                (None, count) if target + 1 < count => {
+                    target += 1;
+                    choice = 0;
+                }
+                (None, _) => break,
+            }
+        }
+
+        if program_source(&program) == before {
+            return Some(before);
+        }
+    }
+}
+
+// the token pass over the Scanner's tokens, or over the characters if the
+// Scanner panics or laying out the tokens loses what the predicate needs
+pub fn minimize_tokens(source: &str, interesting: &mut dyn FnMut(&str) -> bool) -> String {
+    let mut tokens: Vec<String> = Vec::new();
+    let scanned = !panics(|| {
+        let all_tokens = Scanner::from_source("minimize.x", source).run().all_tokens.clone();
+        tokens = all_tokens.iter().map(|token| token.text.clone()).collect();
+    });
+    if scanned && interesting(&layout(&tokens)) {
+        return layout(&ddmin(&tokens, &mut |kept| interesting(&layout(kept))));
+    }
+    let chars: Vec<char> = source.chars().collect();
+    ddmin(&chars, &mut |kept| interesting(&kept.iter().collect::<String>()))
+        .iter()
+        .collect()
+}
+
+// a small source for which interesting still holds, None if it does not hold
+// for the source to begin with
+pub fn minimize(source: &str, interesting: &mut dyn FnMut(&str) -> bool) -> Option<String> {
+    if !interesting(source) {
+        return None;
+    }
+    let source = minimize_ast(source, interesting).unwrap_or_else(|| source.to_string());
+    Some(minimize_tokens(&source, interesting))
+}
+
+// predicate: scanning or parsing the source panics
+pub fn pipeline_panics(source: &str) -> bool {
+    panics(|| {
+        check_source("minimize.x", source);
+    })
+}
+
+// predicate: a diagnostic with the code, or with the text in its message, is
+// reported (and nothing panics)
+pub fn reports(source: &str, text: &str) -> bool {
+    let mut found = false;
+    let panicked = panics(|| {
+        found = check_source("minimize.x", source)
+            .iter()
+            .any(|diagnostic| diagnostic.code == text || diagnostic.message.contains(text));
+    });
+    !panicked && found
+}
+
+// predicate: the command exits successfully when run with a file holding the
+// source as its last argument
+pub fn command_succeeds(command: &[String], source: &str) -> bool {
+    let path = std::env::temp_dir().join(format!("minimize_{}.x", std::process::id()));
+    fs::write(&path, source).expect("error when writing the file!");
+    let status = Command::new(&command[0]).args(&command[1..]).arg(&path).status();
+    status.is_ok_and(|status| status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimizer() {
+        let numbers: Vec<usize> = (0..20).collect();
+        assert_eq!(ddmin(&numbers, &mut |kept| kept.contains(&3) && kept.contains(&17)), vec![3, 17]);
+
+        let source = "int count = 3;\nint f(int a);\nvoid main(){\nint x = 0;\ncount = count + 1;\nwhile (count > 0) {\nif (x) { x = f(count) * (2 + count); }\ncount = count - 1;\n}\n}\nint f(int a) { return a; }";
+
+        // a statement with * that parses and checks, found through the AST
+        // pass: the other statements and the declarations it does not use
+        // go, the while and the if are unwrapped and only the operands of *
+        // are left; the token pass then drops the initializer
+        let mut parses_with_times =
+            |source: &str| source.contains('*') && !pipeline_panics(source) && check_source("test.x", source).is_empty();
+        assert_eq!(
+            minimize_ast(source, &mut parses_with_times).unwrap(),
+            "int count = 3;\nvoid main() {\n    count * 2;\n}"
+        );
+        assert_eq!(
+            minimize(source, &mut parses_with_times).unwrap(),
+            "int count;\nvoid main() {\n    count * 2;\n}"
+        );
+
+        // a tool that panics on a division by the constant 0
+        let divides_by_zero = |source: &str| {
+            if source.contains("/ 0") {
+                panic!("division by zero");
+            }
+        };
+        let with_division = source.replace("count - 1", "count / 0");
+        assert_eq!(
+            minimize(&with_division, &mut |source| panics(|| divides_by_zero(source))).unwrap(),
+            "/ 0"
+        );
+
+        // a syntax error only gets the token pass, whose result is 1-minimal
+        let broken = "void main(){\nx = 1 + ;\ny = 2;\n}";
+        assert!(minimize_ast(broken, &mut |source| reports(source, "X0100")).is_none());
+        let minimal = minimize(broken, &mut |source| reports(source, "X0100")).unwrap();
+        let tokens: Vec<String> = minimal.split_whitespace().map(|t| t.to_string()).collect();
+        assert!(tokens.len() < 4);
+        for i in 0..tokens.len() {
+            let mut fewer = tokens.clone();
+            fewer.remove(i);
+            assert!(!reports(&layout(&fewer), "X0100"));
+        }
+
+        // the predicate must hold for the input
+        assert!(minimize(broken, &mut |source| source.contains("while")).is_none());
+    }
+}
@@ -0,0 +1,138 @@
+//The operator table of X expressions. stage3::Parser parses expressions by
+//precedence climbing over this table and the scanner recognises every
+//operator in it, so adding an operator means adding one entry here (and to
+//the PrefixOperator or BinaryOperator rule of x.ebnf, which operator_table checks).
+
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+    // a < b < c is a syntax error
+    NonAssociative,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fixity {
+    Prefix,
+    Infix,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Operator {
+    pub text: &'static str,
+    // higher binds tighter
+    pub precedence: u8,
+    pub associativity: Associativity,
+    pub fixity: Fixity,
+}
+
+const fn infix(text: &'static str, precedence: u8, associativity: Associativity) -> Operator {
+    Operator {
+        text,
+        precedence,
+        associativity,
+        fixity: Fixity::Infix,
+    }
+}
+
+const fn prefix(text: &'static str, precedence: u8) -> Operator {
+    Operator {
+        text,
+        precedence,
+        associativity: Associativity::Right,
+        fixity: Fixity::Prefix,
+    }
+}
+
+pub const OPERATORS: &[Operator] = &[
+    // the left side of = must be an Identifier
+    infix("=", 1, Associativity::Right),
+    infix("||", 2, Associativity::Left),
+    infix("&&", 3, Associativity::Left),
+    infix("|", 4, Associativity::Left),
+    infix("^", 5, Associativity::Left),
+    infix("&", 6, Associativity::Left),
+    infix("==", 7, Associativity::NonAssociative),
+    infix("!=", 7, Associativity::NonAssociative),
+    infix("<", 7, Associativity::NonAssociative),
+    infix(">", 7, Associativity::NonAssociative),
+    infix("<=", 7, Associativity::NonAssociative),
+    infix(">=", 7, Associativity::NonAssociative),
+    infix("+", 8, Associativity::Left),
+    infix("-", 8, Associativity::Left),
+    infix("*", 9, Associativity::Left),
+    infix("/", 9, Associativity::Left),
+    infix("%", 9, Associativity::Left),
+    prefix("-", 10),
+    prefix("!", 10),
+    prefix("~", 10),
+];
+
+pub fn find(text: &str, fixity: Fixity) -> Option<&'static Operator> {
+    OPERATORS
+        .iter()
+        .find(|operator| operator.text == text && operator.fixity == fixity)
+}
+
+// the EBNF rule of all operators with a fixity, in table order
+pub fn ebnf(fixity: Fixity) -> &'static str {
+    static PREFIX: OnceLock<String> = OnceLock::new();
+    static INFIX: OnceLock<String> = OnceLock::new();
+
+    let (cell, name) = match fixity {
+        Fixity::Prefix => (&PREFIX, "PrefixOperator"),
+        Fixity::Infix => (&INFIX, "BinaryOperator"),
+    };
+    cell.get_or_init(|| {
+        let alternatives: Vec<String> = OPERATORS
+            .iter()
+            .filter(|operator| operator.fixity == fixity)
+            .map(|operator| {
+                if operator.text.len() > 1 {
+                    format!("( {} )", operator.text)
+                } else {
+                    operator.text.to_string()
+                }
+            })
+            .collect();
+        format!("{} := {}", name, alternatives.join(" | "))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operator_table() {
+        use crate::grammar::{Expr, Grammar};
+
+        assert_eq!(find("-", Fixity::Prefix).unwrap().precedence, 10);
+        assert_eq!(find("-", Fixity::Infix).unwrap().precedence, 8);
+        assert_eq!(find("!", Fixity::Infix), None);
+        assert_eq!(ebnf(Fixity::Prefix), "PrefixOperator := - | ! | ~");
+
+        // x.ebnf lists the same operators as the table
+        let grammar = Grammar::x();
+        for (rule, fixity) in [("PrefixOperator", Fixity::Prefix), ("BinaryOperator", Fixity::Infix)] {
+            let listed: Vec<String> = match &grammar.rule(rule).unwrap().expr {
+                Expr::Choice(alternatives) => alternatives
+                    .iter()
+                    .map(|alternative| match alternative {
+                        Expr::Operator(text) => text.clone(),
+                        other => panic!("{} lists {:?}", rule, other),
+                    })
+                    .collect(),
+                other => panic!("{} is {:?}", rule, other),
+            };
+            let table: Vec<String> = OPERATORS
+                .iter()
+                .filter(|operator| operator.fixity == fixity)
+                .map(|operator| operator.text.to_string())
+                .collect();
+            assert_eq!(listed, table, "x.ebnf and the operator table disagree on {}", rule);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//X source text from tokens or from an AST: one declaration or statement per
+//line, blocks indented by four spaces, and only the parentheses that the
+//precedence and associativity of the operator table need.
+
+use crate::ast::{
+    Block, Declaration, Expression, ExpressionKind, FunctionDefinition, Parameter, Program, Statement, StatementKind,
+};
+use crate::operators::{self, Associativity, Fixity};
+
+// the precedence of an expression without an operator on top
+const ATOM: u8 = u8::MAX;
+
+// one declaration or statement per line and blocks indented by four spaces,
+// so the closing } of a program is alone on the last line
+pub fn layout(tokens: &[String]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = String::new();
+    let mut indent = 0;
+    let mut previous = "";
+    for token in tokens.iter() {
+        if token == "}" {
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            indent = usize::saturating_sub(indent, 1);
+            lines.push(format!("{}}}", "    ".repeat(indent)));
+            previous = token;
+            continue;
+        }
+
+        if line.is_empty() {
+            line = "    ".repeat(indent);
+        } else {
+            // f(a, b); but while (a)
+            let call = token == "("
+                && previous.starts_with(|c: char| c.is_alphanumeric() || c == '_')
+                && !matches!(previous, "while" | "if" | "return");
+            let tight = call || previous == "(" || matches!(token.as_str(), ")" | "," | ";");
+            if !tight {
+                line.push(' ');
+            }
+        }
+        line += token;
+        if token == ";" || token == "{" {
+            lines.push(std::mem::take(&mut line));
+            if token == "{" {
+                indent += 1;
+            }
+        }
+        previous = token;
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+pub fn program_source(program: &Program) -> String {
+    let mut tokens = Vec::new();
+    for declaration in program.declarations.iter() {
+        write_declaration(declaration, &mut tokens);
+    }
+    push(&mut tokens, &["void", "main", "(", ")"]);
+    write_block(&program.main.block, &mut tokens);
+    for function in program.functions.iter() {
+        write_function(function, &mut tokens);
+    }
+    layout(&tokens)
+}
+
+pub fn expression_source(expression: &Expression) -> String {
+    let mut tokens = Vec::new();
+    write_expression(expression, &mut tokens);
+    layout(&tokens)
+}
+
+fn push(tokens: &mut Vec<String>, texts: &[&str]) {
+    tokens.extend(texts.iter().map(|text| text.to_string()));
+}
+
+// the type and the name of a declaration, e.g. unsigned int x
+fn write_typed_name(data_type: &impl ToString, name: &str, tokens: &mut Vec<String>) {
+    tokens.extend(data_type.to_string().split(' ').map(|word| word.to_string()));
+    tokens.push(name.to_string());
+}
+
+fn write_parameters(parameters: &[Parameter], tokens: &mut Vec<String>) {
+    tokens.push("(".to_string());
+    for (i, parameter) in parameters.iter().enumerate() {
+        if i > 0 {
+            tokens.push(",".to_string());
+        }
+        write_typed_name(&parameter.data_type, &parameter.name.text, tokens);
+    }
+    tokens.push(")".to_string());
+}
+
+fn write_declaration(declaration: &Declaration, tokens: &mut Vec<String>) {
+    match declaration {
+        Declaration::Variable(variable) => {
+            write_typed_name(&variable.data_type, &variable.name.text, tokens);
+            if let Some(initializer) = &variable.initializer {
+                tokens.push("=".to_string());
+                write_expression(initializer, tokens);
+            }
+        }
+        Declaration::Function(function) => {
+            write_typed_name(&function.return_type, &function.name.text, tokens);
+            write_parameters(&function.parameters, tokens);
+        }
+    }
+    tokens.push(";".to_string());
+}
+
+fn write_function(function: &FunctionDefinition, tokens: &mut Vec<String>) {
+    write_typed_name(&function.return_type, &function.name.text, tokens);
+    write_parameters(&function.parameters, tokens);
+    write_block(&function.block, tokens);
+}
+
+fn write_block(block: &Block, tokens: &mut Vec<String>) {
+    tokens.push("{".to_string());
+    for declaration in block.declarations.iter() {
+        write_declaration(declaration, tokens);
+    }
+    for statement in block.statements.iter() {
+        write_statement(statement, tokens);
+    }
+    for function in block.functions.iter() {
+        write_function(function, tokens);
+    }
+    tokens.push("}".to_string());
+}
+
+fn write_statement(statement: &Statement, tokens: &mut Vec<String>) {
+    match &statement.kind {
+        StatementKind::Expression(expression) => {
+            write_expression(expression, tokens);
+            tokens.push(";".to_string());
+        }
+        StatementKind::While(condition, block) | StatementKind::If(condition, block) => {
+            let keyword = if matches!(statement.kind, StatementKind::While(..)) { "while" } else { "if" };
+            push(tokens, &[keyword, "("]);
+            write_expression(condition, tokens);
+            tokens.push(")".to_string());
+            write_block(block, tokens);
+        }
+        StatementKind::Return(value) => {
+            tokens.push("return".to_string());
+            write_expression(value, tokens);
+            tokens.push(";".to_string());
+        }
+    }
+}
+
+// the precedence and associativity of the operator on top of an expression
+fn operator_of(expression: &Expression) -> (u8, Associativity) {
+    let (text, fixity) = match &expression.kind {
+        ExpressionKind::Unary(operator, _) => (operator.text.as_str(), Fixity::Prefix),
+        ExpressionKind::Binary(operator, _, _) => (operator.text.as_str(), Fixity::Infix),
+        ExpressionKind::Assign(..) => ("=", Fixity::Infix),
+        _ => return (ATOM, Associativity::Left),
+    };
+    operators::find(text, fixity).map_or((ATOM, Associativity::Left), |operator| {
+        (operator.precedence, operator.associativity)
+    })
+}
+
+// an operand that must bind at least as tight as precedence
+fn write_operand(expression: &Expression, precedence: u8, tokens: &mut Vec<String>) {
+    if operator_of(expression).0 < precedence {
+        tokens.push("(".to_string());
+        write_expression(expression, tokens);
+        tokens.push(")".to_string());
+    } else {
+        write_expression(expression, tokens);
+    }
+}
+
+fn write_expression(expression: &Expression, tokens: &mut Vec<String>) {
+    let (precedence, associativity) = operator_of(expression);
+    match &expression.kind {
+        ExpressionKind::Constant(token) | ExpressionKind::Identifier(token) => tokens.push(token.text.clone()),
+        ExpressionKind::Call(name, arguments) => {
+            push(tokens, &[&name.text, "("]);
+            for (i, argument) in arguments.iter().enumerate() {
+                if i > 0 {
+                    tokens.push(",".to_string());
+                }
+                write_expression(argument, tokens);
+            }
+            tokens.push(")".to_string());
+        }
+        ExpressionKind::Unary(operator, operand) => {
+            tokens.push(operator.text.clone());
+            write_operand(operand, precedence, tokens);
+        }
+        ExpressionKind::Binary(operator, lhs, rhs) => {
+            let tighter = |side: Associativity| if associativity == side { precedence } else { precedence + 1 };
+            write_operand(lhs, tighter(Associativity::Left), tokens);
+            tokens.push(operator.text.clone());
+            write_operand(rhs, tighter(Associativity::Right), tokens);
+        }
+        ExpressionKind::Assign(target, value) => {
+            push(tokens, &[&target.text, "="]);
+            write_operand(value, precedence, tokens);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn printing() {
+        use crate::diagnostics::test_support::parse;
+
+        let tokens: Vec<String> = ["void", "main", "(", ")", "{", "f", "(", "a", ",", "-", "1", ")", ";", "}"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(layout(&tokens), "void main() {\n    f(a, - 1);\n}");
+
+        // only the parentheses the operator table needs are kept
+        let cases = [
+            ("(a + b) * c", "(a + b) * c"),
+            ("a + (b * c)", "a + b * c"),
+            ("(a - b) - c", "a - b - c"),
+            ("a - (b - c)", "a - (b - c)"),
+            ("(a < b) == c", "(a < b) == c"),
+            ("x = (y = 1)", "x = y = 1"),
+            ("a + (x = 1)", "a + (x = 1)"),
+            ("-(a + b)", "- (a + b)"),
+            ("- -a * f((b), 2)", "- - a * f(b, 2)"),
+        ];
+        for (source, expected) in cases {
+            let program = parse(&format!("void main(){{\n{};\n}}", source));
+            let statement = &program.main.block.statements[0];
+            let StatementKind::Expression(expression) = &statement.kind else {
+                panic!("{} is not an expression statement", source);
+            };
+            assert_eq!(expression_source(expression), expected);
+        }
+
+        // printing is a fixed point: the printed program parses to the same text
+        let source = "unsigned int x = 3;\nvoid main(){\nwhile (x >= 1) { x = x - 1; }\nif (x == 0) { return f(x, 2.5); }\nint f(long a, char b) { return a; }\n}";
+        let printed = program_source(&parse(source));
+        assert_eq!(
+            printed,
+            "unsigned int x = 3;\nvoid main() {\n    while (x >= 1) {\n        x = x - 1;\n    }\n    if (x == 0) {\n        return f(x, 2.5);\n    }\n    int f(long a, char b) {\n        return a;\n    }\n}"
+        );
+        assert_eq!(program_source(&parse(&printed)), printed);
+    }
+}
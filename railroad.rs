@@ -0,0 +1,367 @@
+//Railroad (syntax) diagrams for a grammar: one SVG per rule and an index
+//page linking them, like xhtml_out does for .x files but for the grammar.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::grammar::{Expr, Grammar, Rule};
+use crate::stage2_token::TokenType;
+
+// width of one character of box text, with a 13px monospace font
+const CHAR_WIDTH: i32 = 8;
+// half the height of a box
+const BOX_HALF: i32 = 11;
+// horizontal space between items and around branches
+const GAP: i32 = 10;
+// space taken by the curves on each side of a branch
+const BRANCH: i32 = 20;
+// margin around the whole diagram
+const MARGIN: i32 = 20;
+
+// the size of a diagram part; it is entered on the left and left on the
+// right, both at its main line, which is `up` below its top
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Size {
+    width: i32,
+    up: i32,
+    down: i32,
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn label(expr: &Expr) -> String {
+    match expr {
+        Expr::Keyword(text) | Expr::Operator(text) | Expr::NonTerminal(text) => text.clone(),
+        Expr::Token(TokenType::Identifier) => "Identifier".to_string(),
+        Expr::Token(TokenType::IntConstant) => "IntConstant".to_string(),
+        Expr::Token(TokenType::FloatConstant) => "FloatConstant".to_string(),
+        Expr::Token(token_type) => format!("{:?}", token_type),
+        _ => "".to_string(),
+    }
+}
+
+fn measure(expr: &Expr) -> Size {
+    match expr {
+        Expr::Sequence(items) => {
+            let sizes: Vec<Size> = items.iter().map(measure).collect();
+            Size {
+                width: sizes.iter().map(|s| s.width).sum::<i32>() + GAP * (sizes.len() as i32 - 1),
+                up: sizes.iter().map(|s| s.up).max().unwrap(),
+                down: sizes.iter().map(|s| s.down).max().unwrap(),
+            }
+        }
+        Expr::Choice(alternatives) => {
+            let sizes: Vec<Size> = alternatives.iter().map(measure).collect();
+            let mut down = sizes[0].down;
+            for size in sizes.iter().skip(1) {
+                down += GAP + size.up + size.down;
+            }
+            Size {
+                width: sizes.iter().map(|s| s.width).max().unwrap() + 2 * BRANCH,
+                up: sizes[0].up,
+                down,
+            }
+        }
+        Expr::Optional(inner) => {
+            let size = measure(inner);
+            Size {
+                width: size.width + 2 * BRANCH,
+                up: size.up + GAP,
+                down: size.down,
+            }
+        }
+        Expr::Repetition(inner) => {
+            let size = measure(inner);
+            Size {
+                width: size.width + 2 * BRANCH,
+                up: size.up + GAP,
+                down: size.down + GAP,
+            }
+        }
+        terminal => Size {
+            width: CHAR_WIDTH * label(terminal).chars().count() as i32 + 2 * GAP,
+            up: BOX_HALF,
+            down: BOX_HALF,
+        },
+    }
+}
+
+fn line(x1: i32, y1: i32, x2: i32, y2: i32) -> String {
+    format!("<path d=\"M{} {} L{} {}\"/>\n", x1, y1, x2, y2)
+}
+
+// draws expr with its entry point at (x, y); href gives the link target
+// of a nonterminal
+fn draw(expr: &Expr, x: i32, y: i32, href: &dyn Fn(&str) -> String, res: &mut String) {
+    let size = measure(expr);
+    match expr {
+        Expr::Sequence(items) => {
+            let mut item_x = x;
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    *res += &line(item_x, y, item_x + GAP, y);
+                    item_x += GAP;
+                }
+                draw(item, item_x, y, href, res);
+                item_x += measure(item).width;
+            }
+        }
+        Expr::Choice(alternatives) => {
+            let inner_width = size.width - 2 * BRANCH;
+            let right = x + size.width;
+            let mut alternative_y = y;
+            let mut below = 0;
+            for (i, alternative) in alternatives.iter().enumerate() {
+                let alternative_size = measure(alternative);
+                if i == 0 {
+                    *res += &line(x, y, x + BRANCH, y);
+                } else {
+                    alternative_y = y + below + GAP + alternative_size.up;
+                    *res += &format!(
+                        "<path d=\"M{} {} Q{} {} {} {} V{} Q{} {} {} {}\"/>\n",
+                        x, y, x + GAP, y, x + GAP, y + GAP,
+                        alternative_y - GAP,
+                        x + GAP, alternative_y, x + BRANCH, alternative_y
+                    );
+                }
+                draw(alternative, x + BRANCH, alternative_y, href, res);
+                let end = x + BRANCH + alternative_size.width;
+                if i == 0 {
+                    *res += &line(end, y, right, y);
+                    below = alternative_size.down;
+                } else {
+                    *res += &format!(
+                        "<path d=\"M{} {} H{} Q{} {} {} {} V{} Q{} {} {} {}\"/>\n",
+                        end, alternative_y, x + BRANCH + inner_width,
+                        right - GAP, alternative_y, right - GAP, alternative_y - GAP,
+                        y + GAP,
+                        right - GAP, y, right, y
+                    );
+                    below += GAP + alternative_size.up + alternative_size.down;
+                }
+            }
+        }
+        Expr::Optional(inner) | Expr::Repetition(inner) => {
+            let inner_size = measure(inner);
+            let start = x + BRANCH;
+            let end = start + inner_size.width;
+            let right = x + size.width;
+            *res += &line(x, y, start, y);
+            draw(inner, start, y, href, res);
+            *res += &line(end, y, right, y);
+
+            // the way around above
+            let top = y - inner_size.up - GAP;
+            *res += &format!(
+                "<path d=\"M{} {} Q{} {} {} {} V{} Q{} {} {} {} H{} Q{} {} {} {} V{} Q{} {} {} {}\"/>\n",
+                x, y, x + GAP, y, x + GAP, y - GAP,
+                top + GAP,
+                x + GAP, top, start, top,
+                end,
+                right - GAP, top, right - GAP, top + GAP,
+                y - GAP,
+                right - GAP, y, right, y
+            );
+
+            // the way back below
+            if let Expr::Repetition(_) = expr {
+                let bottom = y + inner_size.down + GAP;
+                *res += &format!(
+                    "<path d=\"M{} {} Q{} {} {} {} V{} Q{} {} {} {} H{} Q{} {} {} {} V{} Q{} {} {} {}\"/>\n",
+                    end, y, right - GAP, y, right - GAP, y + GAP,
+                    bottom - GAP,
+                    right - GAP, bottom, end, bottom,
+                    start,
+                    x + GAP, bottom, x + GAP, bottom - GAP,
+                    y + GAP,
+                    x + GAP, y, start, y
+                );
+            }
+        }
+        terminal => {
+            let text = escape(&label(terminal));
+            let (class, radius) = match terminal {
+                Expr::NonTerminal(_) => ("nonterminal", 0),
+                _ => ("terminal", BOX_HALF),
+            };
+            let shape = format!(
+                "<rect class=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\"/>\n<text x=\"{}\" y=\"{}\">{}</text>\n",
+                class,
+                x,
+                y - BOX_HALF,
+                size.width,
+                2 * BOX_HALF,
+                radius,
+                x + size.width / 2,
+                y + 4,
+                text
+            );
+            match terminal {
+                Expr::NonTerminal(name) => {
+                    *res += &format!("<a xlink:href=\"{}\">\n{}</a>\n", escape(&href(name)), shape);
+                }
+                _ => *res += &shape,
+            }
+        }
+    }
+}
+
+// the SVG diagram of one rule
+pub fn diagram(rule: &Rule, href: &dyn Fn(&str) -> String) -> String {
+    let size = measure(&rule.expr);
+    let width = size.width + 2 * MARGIN + 2 * GAP;
+    let height = size.up + size.down + 2 * MARGIN;
+    let y = MARGIN + size.up;
+
+    let mut res = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{}" height="{}" viewBox="0 0 {} {}">
+<title>{}</title>
+<style>
+path {{ fill: none; stroke: navy; stroke-width: 2; }}
+rect {{ stroke: navy; stroke-width: 2; }}
+rect.terminal {{ fill: orange; }}
+rect.nonterminal {{ fill: white; }}
+text {{ font-family: 'Courier New', monospace; font-size: 13px; text-anchor: middle; }}
+</style>
+"#,
+        width,
+        height,
+        width,
+        height,
+        escape(&rule.text)
+    );
+
+    // start and end of the rule
+    res += &format!("<circle cx=\"{}\" cy=\"{}\" r=\"4\" fill=\"navy\"/>\n", MARGIN - 4, y);
+    res += &line(MARGIN, y, MARGIN + GAP, y);
+    draw(&rule.expr, MARGIN + GAP, y, href, &mut res);
+    res += &line(MARGIN + GAP + size.width, y, MARGIN + 2 * GAP + size.width, y);
+    res += &format!(
+        "<circle cx=\"{}\" cy=\"{}\" r=\"4\" fill=\"navy\"/>\n",
+        MARGIN + 2 * GAP + size.width + 4,
+        y
+    );
+
+    res += "</svg>\n";
+    res
+}
+
+// the grammar notation of a rule with its nonterminals as links
+fn linked_production(expr: &Expr, href: &dyn Fn(&str) -> String) -> String {
+    match expr {
+        Expr::NonTerminal(name) => format!("<a href=\"{}\">{}</a>", escape(&href(name)), escape(name)),
+        Expr::Sequence(items) => items
+            .iter()
+            .map(|item| match item {
+                Expr::Choice(_) | Expr::Sequence(_) => format!("( {} )", linked_production(item, href)),
+                _ => linked_production(item, href),
+            })
+            .collect::<Vec<String>>()
+            .join(" "),
+        Expr::Choice(alternatives) => alternatives
+            .iter()
+            .map(|alternative| match alternative {
+                Expr::Sequence(_) => format!("( {} )", linked_production(alternative, href)),
+                _ => linked_production(alternative, href),
+            })
+            .collect::<Vec<String>>()
+            .join(" | "),
+        Expr::Optional(inner) => format!("[ {} ]", linked_production(inner, href)),
+        Expr::Repetition(inner) => format!("{{ {} }}", linked_production(inner, href)),
+        Expr::Operator(text) if text.len() == 1 && "()[]{}|".contains(text.as_str()) => {
+            format!("'{}'", escape(text))
+        }
+        terminal => format!("<b>{}</b>", escape(&label(terminal))),
+    }
+}
+
+// an index page with every rule, its production and its diagram
+pub fn index(grammar: &Grammar) -> String {
+    let svg_file = |name: &str| format!("{}.svg", name);
+
+    let mut res = r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd">
+<html xmlns="http://www.w3.org/1999/xhtml" xml:lang="en">
+<head>
+<title>X Grammar</title>
+</head>
+<body bgcolor="navy" text="orange" link="white" vlink="white">
+<font face="Courier New">
+<h1>X Grammar</h1>
+<ul>
+"#
+    .to_string();
+
+    for rule in grammar.rules.iter() {
+        res += &format!("<li><a href=\"#{}\">{}</a></li>\n", rule.name, rule.name);
+    }
+    res += "</ul>\n";
+
+    for rule in grammar.rules.iter() {
+        res += &format!(
+            "<h2 id=\"{}\"><a href=\"{}\">{}</a></h2>\n<p>{} := {}</p>\n<p><object data=\"{}\" type=\"image/svg+xml\">{}</object></p>\n",
+            rule.name,
+            svg_file(&rule.name),
+            rule.name,
+            rule.name,
+            linked_production(&rule.expr, &|name| format!("#{}", name)),
+            svg_file(&rule.name),
+            escape(&rule.text)
+        );
+    }
+
+    res += "</font>\n</body>\n</html>\n";
+    res
+}
+
+// writes <Rule>.svg for every rule and index.html into out_dir
+pub fn write_diagrams(grammar: &Grammar, out_dir: &str) -> io::Result<()> {
+    fs::create_dir_all(out_dir)?;
+    let href = |name: &str| format!("{}.svg", name);
+    for rule in grammar.rules.iter() {
+        fs::write(
+            Path::new(out_dir).join(format!("{}.svg", rule.name)),
+            diagram(rule, &href),
+        )?;
+    }
+    fs::write(Path::new(out_dir).join("index.html"), index(grammar))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagrams() {
+        let grammar = Grammar::x();
+        let href = |name: &str| format!("{}.svg", name);
+
+        let block = diagram(grammar.rule("Block").unwrap(), &href);
+        assert!(block.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(block.contains("<a xlink:href=\"Declaration.svg\">"));
+        assert!(block.contains("<text x="));
+        assert!(block.ends_with("</svg>\n"));
+
+        let binary = diagram(grammar.rule("BinaryOperator").unwrap(), &href);
+        assert!(binary.contains("&lt;="));
+        assert!(binary.contains("&amp;&amp;"));
+
+        assert_eq!(
+            measure(&Expr::Keyword("void".to_string())),
+            Size {
+                width: 52,
+                up: 11,
+                down: 11
+            }
+        );
+
+        let page = index(&grammar);
+        assert!(page.contains("<h2 id=\"Program\"><a href=\"Program.svg\">Program</a></h2>"));
+        assert!(page.contains("<a href=\"#MainDeclaration\">MainDeclaration</a>"));
+    }
+}
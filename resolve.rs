@@ -0,0 +1,576 @@
+//Name resolution: the symbol tables of a program and the declaration every
+//identifier refers to. The global scope holds the global variables and the
+//functions; a function definition opens a scope for its parameters and the
+//declarations of its block, and every while or if block opens another one.
+//Names are visible from their declaration on, like in C, and a function is
+//visible from its own parameters on so that it can call itself. Functions
+//defined inside a block see the names of the enclosing blocks. Uses are
+//checked against what they refer to: only functions are called, with one
+//argument per parameter, a prototype agrees with its definition, and a
+//function that is called is defined somewhere. A name that is not declared
+//where it is called may be one of the intrinsics, which every program sees.
+
+use std::collections::HashMap;
+
+use crate::ast::{
+    Block, DataType, Declaration, Expression, ExpressionKind, FunctionDefinition, Parameter, Program, StatementKind,
+};
+use crate::diagnostics::{Diagnostic, Related, Severity, Span};
+use crate::intrinsics;
+use crate::stage2_token::Token;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    Global,
+    Function,
+    Parameter,
+    Local,
+    NestedFunction,
+    // declared by no program, see intrinsics.rs
+    Intrinsic,
+}
+
+impl SymbolKind {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            SymbolKind::Global => "global variable",
+            SymbolKind::Function => "function",
+            SymbolKind::Parameter => "parameter",
+            SymbolKind::Local => "local variable",
+            SymbolKind::NestedFunction => "nested function",
+            SymbolKind::Intrinsic => "intrinsic function",
+        }
+    }
+
+    pub fn is_function(&self) -> bool {
+        matches!(self, SymbolKind::Function | SymbolKind::NestedFunction | SymbolKind::Intrinsic)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    // the type of a variable or parameter, the return type of a function
+    pub data_type: DataType,
+    // the parameter types of a function
+    pub parameters: Vec<DataType>,
+    // false for a prototype that has not been defined in the same scope
+    pub defined: bool,
+    // the name in the declaration, an empty span for an intrinsic
+    pub span: Span,
+    pub scope: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct Scope {
+    // None for the global scope
+    pub parent: Option<usize>,
+    pub span: Span,
+    // the symbols declared here, in declaration order
+    pub symbols: Vec<usize>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    pub scopes: Vec<Scope>,
+    pub symbols: Vec<Symbol>,
+    // the symbol each identifier, assignment target and called name refers
+    // to, by the span of its token
+    pub uses: HashMap<Span, usize>,
+}
+
+impl SymbolTable {
+    // the symbol a name token refers to
+    pub fn symbol_of(&self, token: &Token) -> Option<&Symbol> {
+        self.uses.get(&Span::of_token(token)).map(|&symbol| &self.symbols[symbol])
+    }
+}
+
+struct Resolver<'a> {
+    xfilename: &'a str,
+    table: SymbolTable,
+    // the scopes that are open, innermost last, with their visible names
+    open: Vec<(usize, HashMap<String, usize>)>,
+    // the names used where nothing of that name was visible
+    undeclared: Vec<Token>,
+    // the called names with the function they refer to
+    calls: Vec<(Token, usize)>,
+    // the symbol of every intrinsic that is called, by name
+    intrinsics: HashMap<&'static str, usize>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Resolver<'a> {
+    fn diagnostic(&self, severity: Severity, code: &'static str, message: String, span: Span, related: Vec<Related>) -> Diagnostic {
+        Diagnostic {
+            severity,
+            code,
+            message,
+            file: self.xfilename.to_string(),
+            span,
+            related,
+        }
+    }
+
+    fn enter(&mut self, span: Span) {
+        let parent = self.open.last().map(|(scope, _)| *scope);
+        self.table.scopes.push(Scope {
+            parent,
+            span,
+            symbols: Vec::new(),
+        });
+        self.open.push((self.table.scopes.len() - 1, HashMap::new()));
+    }
+
+    fn leave(&mut self) {
+        self.open.pop();
+    }
+
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.open.iter().rev().find_map(|(_, names)| names.get(name).copied())
+    }
+
+    // declares a name in the innermost scope; a definition completes a
+    // prototype of the same name there, everything else of the same name is
+    // a duplicate, and a name of an outer scope is shadowed
+    fn declare(&mut self, name: &Token, kind: SymbolKind, data_type: DataType, parameters: Vec<DataType>, defined: bool) {
+        let span = Span::of_token(name);
+        let (scope, names) = self.open.last().unwrap();
+        let scope = *scope;
+        // a prototype after the definition does not hide it
+        let mut visible = true;
+        if let Some(&previous) = names.get(&name.text) {
+            let old = &self.table.symbols[previous];
+            let completes = kind.is_function() && old.kind.is_function() && (!old.defined || !defined);
+            if !completes {
+                let related = vec![Related {
+                    span: old.span,
+                    message: format!("'{}' is first declared here", name.text),
+                }];
+                let message = format!("'{}' is already declared in this scope as a {}.", name.text, old.kind.describe());
+                let diagnostic = self.diagnostic(Severity::Error, "X0201", message, span, related);
+                self.diagnostics.push(diagnostic);
+                return;
+            }
+            if old.data_type != data_type || old.parameters != parameters {
+                let related = vec![Related {
+                    span: old.span,
+                    message: format!("'{}' is first declared here as {}", name.text, signature(old)),
+                }];
+                let message = format!(
+                    "'{}' is declared as {}, which conflicts with its earlier declaration.",
+                    name.text,
+                    signature_of(&name.text, data_type, &parameters)
+                );
+                let diagnostic = self.diagnostic(Severity::Error, "X0203", message, span, related);
+                self.diagnostics.push(diagnostic);
+            }
+            if defined {
+                self.table.symbols[previous].defined = true;
+            } else {
+                visible = !old.defined;
+            }
+        } else if let Some(outer) = self.lookup(&name.text) {
+            let old = &self.table.symbols[outer];
+            let related = vec![Related {
+                span: old.span,
+                message: format!("the shadowed {} is declared here", old.kind.describe()),
+            }];
+            let message = format!("'{}' shadows the {} of the same name.", name.text, old.kind.describe());
+            let diagnostic = self.diagnostic(Severity::Warning, "X0202", message, span, related);
+            self.diagnostics.push(diagnostic);
+        }
+
+        self.table.symbols.push(Symbol {
+            name: name.text.clone(),
+            kind,
+            data_type,
+            parameters,
+            defined,
+            span,
+            scope,
+        });
+        let symbol = self.table.symbols.len() - 1;
+        self.table.scopes[scope].symbols.push(symbol);
+        if visible {
+            self.open.last_mut().unwrap().1.insert(name.text.clone(), symbol);
+        }
+    }
+
+    fn use_name(&mut self, name: &Token) -> Option<usize> {
+        match self.lookup(&name.text) {
+            Some(symbol) => {
+                self.table.uses.insert(Span::of_token(name), symbol);
+                Some(symbol)
+            }
+            None => {
+                self.undeclared.push(name.clone());
+                None
+            }
+        }
+    }
+
+    // the symbol of an intrinsic, in the global scope but visible nowhere,
+    // added when it is first called
+    fn intrinsic(&mut self, name: &Token) -> Option<usize> {
+        let intrinsic = intrinsics::find(&name.text)?;
+        let symbol = *self.intrinsics.entry(intrinsic.name).or_insert_with(|| {
+            self.table.symbols.push(Symbol {
+                name: intrinsic.name.to_string(),
+                kind: SymbolKind::Intrinsic,
+                data_type: intrinsic.return_type,
+                parameters: intrinsic.parameters.to_vec(),
+                defined: true,
+                span: Span::default(),
+                scope: 0,
+            });
+            self.table.symbols.len() - 1
+        });
+        self.table.uses.insert(Span::of_token(name), symbol);
+        Some(symbol)
+    }
+
+    // a note pointing at the declaration of a symbol; intrinsics have none
+    fn declared_here(&self, symbol: usize) -> Vec<Related> {
+        let symbol = &self.table.symbols[symbol];
+        if symbol.kind == SymbolKind::Intrinsic {
+            return Vec::new();
+        }
+        vec![Related {
+            span: symbol.span,
+            message: format!("'{}' is declared here as a {}", symbol.name, describe(symbol)),
+        }]
+    }
+
+    // a name that is used as a variable, or assigned to
+    fn use_variable(&mut self, name: &Token, assigned: bool) {
+        let symbol = match self.use_name(name) {
+            Some(symbol) if self.table.symbols[symbol].kind.is_function() => symbol,
+            _ => return,
+        };
+        let message = if assigned {
+            format!("'{}' is a function, so it cannot be assigned to.", name.text)
+        } else {
+            format!("'{}' is a function, so it cannot be used as a variable; call it.", name.text)
+        };
+        let diagnostic = self.diagnostic(Severity::Error, "X0206", message, Span::of_token(name), self.declared_here(symbol));
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn use_function(&mut self, name: &Token, arguments: usize) {
+        // a name that is not declared may be an intrinsic
+        let symbol = match self.lookup(&name.text) {
+            Some(_) => self.use_name(name),
+            None => self.intrinsic(name).or_else(|| self.use_name(name)),
+        };
+        let Some(symbol) = symbol else {
+            return;
+        };
+        let function = &self.table.symbols[symbol];
+        let message = if !function.kind.is_function() {
+            format!("'{}' is a {}, not a function, so it cannot be called.", name.text, function.kind.describe())
+        } else if function.parameters.len() != arguments {
+            self.calls.push((name.clone(), symbol));
+            format!(
+                "'{}' takes {}, but {} given.",
+                name.text,
+                count(function.parameters.len(), "argument"),
+                if arguments == 1 { "1 is".to_string() } else { format!("{} are", arguments) }
+            )
+        } else {
+            self.calls.push((name.clone(), symbol));
+            return;
+        };
+        let code = if function.kind.is_function() { "X0207" } else { "X0205" };
+        let diagnostic = self.diagnostic(Severity::Error, code, message, Span::of_token(name), self.declared_here(symbol));
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match &expression.kind {
+            ExpressionKind::Constant(_) => {}
+            ExpressionKind::Identifier(name) => self.use_variable(name, false),
+            ExpressionKind::Call(name, arguments) => {
+                self.use_function(name, arguments.len());
+                for argument in arguments.iter() {
+                    self.expression(argument);
+                }
+            }
+            ExpressionKind::Unary(_, operand) => self.expression(operand),
+            ExpressionKind::Binary(_, lhs, rhs) => {
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+            ExpressionKind::Assign(target, value) => {
+                self.use_variable(target, true);
+                self.expression(value);
+            }
+        }
+    }
+
+    fn declaration(&mut self, declaration: &Declaration, kind: SymbolKind) {
+        match declaration {
+            Declaration::Variable(variable) => {
+                // the initializer cannot see the variable it initializes
+                if let Some(initializer) = &variable.initializer {
+                    self.expression(initializer);
+                }
+                self.declare(&variable.name, kind, variable.data_type, Vec::new(), true);
+            }
+            Declaration::Function(function) => {
+                let kind = if kind == SymbolKind::Global { SymbolKind::Function } else { SymbolKind::NestedFunction };
+                self.parameter_names(&function.parameters);
+                let parameters = function.parameters.iter().map(|parameter| parameter.data_type).collect();
+                self.declare(&function.name, kind, function.return_type, parameters, false);
+            }
+        }
+    }
+
+    // the parameter names of a prototype have no scope, but must differ
+    fn parameter_names(&mut self, parameters: &[Parameter]) {
+        for (i, parameter) in parameters.iter().enumerate() {
+            if let Some(first) = parameters[..i].iter().find(|other| other.name.text == parameter.name.text) {
+                let related = vec![Related {
+                    span: Span::of_token(&first.name),
+                    message: format!("'{}' is first declared here", first.name.text),
+                }];
+                let message = format!("'{}' is already declared in this scope as a parameter.", parameter.name.text);
+                let diagnostic = self.diagnostic(Severity::Error, "X0201", message, Span::of_token(&parameter.name), related);
+                self.diagnostics.push(diagnostic);
+            }
+        }
+    }
+
+    fn function(&mut self, function: &FunctionDefinition, kind: SymbolKind) {
+        let parameters = function.parameters.iter().map(|parameter| parameter.data_type).collect();
+        self.declare(&function.name, kind, function.return_type, parameters, true);
+        self.enter(function.span);
+        for parameter in function.parameters.iter() {
+            self.declare(&parameter.name, SymbolKind::Parameter, parameter.data_type, Vec::new(), true);
+        }
+        self.block_contents(&function.block);
+        self.leave();
+    }
+
+    // the declarations, statements and functions of a block, in the scope
+    // that is open
+    fn block_contents(&mut self, block: &Block) {
+        for declaration in block.declarations.iter() {
+            self.declaration(declaration, SymbolKind::Local);
+        }
+        for statement in block.statements.iter() {
+            match &statement.kind {
+                StatementKind::Expression(expression) | StatementKind::Return(expression) => self.expression(expression),
+                StatementKind::While(condition, body) | StatementKind::If(condition, body) => {
+                    self.expression(condition);
+                    self.block(body);
+                }
+            }
+        }
+        for function in block.functions.iter() {
+            self.function(function, SymbolKind::NestedFunction);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.enter(block.span);
+        self.block_contents(block);
+        self.leave();
+    }
+
+    fn program(&mut self, program: &Program) {
+        self.enter(program.span);
+        for declaration in program.declarations.iter() {
+            self.declaration(declaration, SymbolKind::Global);
+        }
+        self.block(&program.main.block);
+        for function in program.functions.iter() {
+            self.function(function, SymbolKind::Function);
+        }
+        self.leave();
+    }
+
+    // one error per undeclared name, with a declaration of that name that
+    // is not visible there if there is one
+    fn report_undeclared(&mut self) {
+        for name in std::mem::take(&mut self.undeclared) {
+            let related = self
+                .table
+                .symbols
+                .iter()
+                .find(|symbol| symbol.name == name.text)
+                .map(|symbol| Related {
+                    span: symbol.span,
+                    message: format!("a {} '{}' is declared here, but it is not visible there", symbol.kind.describe(), symbol.name),
+                })
+                .into_iter()
+                .collect();
+            let message = format!("'{}' is not declared.", name.text);
+            let diagnostic = self.diagnostic(Severity::Error, "X0200", message, Span::of_token(&name), related);
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    // one error per function that is called but only has a prototype, at
+    // its first call
+    fn report_undefined(&mut self) {
+        let mut reported = Vec::new();
+        for (name, symbol) in std::mem::take(&mut self.calls) {
+            if self.table.symbols[symbol].defined || reported.contains(&symbol) {
+                continue;
+            }
+            reported.push(symbol);
+            let message = format!("'{}' is called, but it is never defined.", name.text);
+            let diagnostic = self.diagnostic(Severity::Error, "X0204", message, Span::of_token(&name), self.declared_here(symbol));
+            self.diagnostics.push(diagnostic);
+        }
+    }
+}
+
+// "1 argument", "2 arguments"
+fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
+
+// a function as its prototype would declare it, without parameter names
+fn signature_of(name: &str, return_type: DataType, parameters: &[DataType]) -> String {
+    let parameters: Vec<String> = parameters.iter().map(|parameter| parameter.to_string()).collect();
+    format!("{} {}({})", return_type, name, parameters.join(", "))
+}
+
+fn signature(symbol: &Symbol) -> String {
+    signature_of(&symbol.name, symbol.data_type, &symbol.parameters)
+}
+
+// what a symbol is, with its type
+fn describe(symbol: &Symbol) -> String {
+    if symbol.kind.is_function() {
+        format!("{} {}", symbol.kind.describe(), signature(symbol))
+    } else {
+        format!("{} of type {}", symbol.kind.describe(), symbol.data_type)
+    }
+}
+
+// the symbol tables of a parsed program and the diagnostics of name
+// resolution, sorted by position
+pub fn resolve(xfilename: &str, program: &Program) -> (SymbolTable, Vec<Diagnostic>) {
+    let mut resolver = Resolver {
+        xfilename,
+        table: SymbolTable::default(),
+        open: Vec::new(),
+        undeclared: Vec::new(),
+        calls: Vec::new(),
+        intrinsics: HashMap::new(),
+        diagnostics: Vec::new(),
+    };
+    resolver.program(program);
+    resolver.report_undeclared();
+    resolver.report_undefined();
+    let mut diagnostics = resolver.diagnostics;
+    diagnostics.sort_by_key(|d| (d.span.start_line, d.span.start_char));
+    (resolver.table, diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::test_support::{codes, parse};
+
+    fn resolve_source(source: &str) -> (SymbolTable, Vec<Diagnostic>) {
+        resolve("test.x", &parse(source))
+    }
+
+    #[test]
+    fn examples() {
+        let (table, diagnostics) = resolve_source(&std::fs::read_to_string("example2.x").unwrap());
+        // BarFoo's parameters shadow Bar's, and BarFoo sees inp2 of Bar
+        assert_eq!(codes(&diagnostics), [("X0202", 35, 21), ("X0202", 35, 33)]);
+        assert_eq!(diagnostics[0].related[0].span.start_line, 21);
+        let names: Vec<(&str, SymbolKind)> = table.symbols.iter().map(|symbol| (symbol.name.as_str(), symbol.kind)).collect();
+        assert!(names.contains(&("BarFoo", SymbolKind::NestedFunction)));
+        assert!(names.contains(&("R3", SymbolKind::Local)));
+        let inp2 = table.symbols.iter().position(|symbol| symbol.name == "inp2").unwrap();
+        assert_eq!(table.uses.values().filter(|&&symbol| symbol == inp2).count(), 3);
+
+        let (table, diagnostics) = resolve_source(&std::fs::read_to_string("example1.x").unwrap());
+        assert_eq!(diagnostics, Vec::new());
+        // the prototype and the definition of Foo, the parameter, two locals
+        assert_eq!(table.symbols.len(), 5);
+        assert_eq!(table.scopes.len(), 4);
+    }
+
+    #[test]
+    fn errors() {
+        let source = "int x;\nfloat x;\nvoid main(){\nint y;\nwhile (y) {\nint w;\n}\nw = z;\nf(y);\n}\nint f(int a) { int a; return g(a); }\nint g(int b) { return f(b); }\n";
+        let (_, diagnostics) = resolve_source(source);
+        assert_eq!(
+            codes(&diagnostics),
+            [("X0201", 1, 6), ("X0200", 7, 0), ("X0200", 7, 4), ("X0200", 8, 0), ("X0201", 10, 19), ("X0200", 10, 29)]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "test.x:1:6-1:7: error[X0201]: 'x' is already declared in this scope as a global variable.\ntest.x:0:4-0:5: note: 'x' is first declared here"
+        );
+        // w is declared in the while block, f only after main, z nowhere
+        assert!(diagnostics[1].related[0].message.contains("local variable 'w'"));
+        assert_eq!(diagnostics[1].related[0].span.start_line, 5);
+        assert!(diagnostics[2].related.is_empty());
+        assert_eq!(diagnostics[3].related[0].span.start_line, 10);
+
+        // a prototype, its definition and a recursive call are fine; a second
+        // definition, prototype parameters with the same name and a call of
+        // a prototype without a definition are not
+        let source = "int f(int a, int a);\nint g(int n);\nvoid main(){\nf(1, 2);\n}\nint g(int n) { int h(int k); return g(n - 1); int h(int k) { return k; } }\nint g(int m) { return m; }\n";
+        let (table, diagnostics) = resolve_source(source);
+        assert_eq!(codes(&diagnostics), [("X0201", 0, 17), ("X0204", 3, 0), ("X0201", 6, 4)]);
+        let prototypes: Vec<(&str, bool)> = table
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.kind.is_function())
+            .map(|symbol| (symbol.name.as_str(), symbol.defined))
+            .collect();
+        assert_eq!(prototypes, [("f", false), ("g", true), ("g", true), ("h", true), ("h", true)]);
+    }
+
+    #[test]
+    fn functions() {
+        // a prototype that disagrees with its definition, a wrong argument
+        // count, a called variable, a function used as a variable and a call
+        // of a prototype that is never defined
+        let source = "int x;\nfloat f(int a);\nint g(int a);\nvoid main(){\nx = f(1, 2);\nx = x(1);\nx = f;\nx = g(1) + g(2);\n}\nint f(int a) { return a; }\n";
+        let (_, diagnostics) = resolve_source(source);
+        assert_eq!(
+            codes(&diagnostics),
+            [("X0207", 4, 4), ("X0205", 5, 4), ("X0206", 6, 4), ("X0204", 7, 4), ("X0203", 9, 4)]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "test.x:4:4-4:5: error[X0207]: 'f' takes 1 argument, but 2 are given.\ntest.x:1:6-1:7: note: 'f' is declared here as a function float f(int)"
+        );
+        assert_eq!(diagnostics[4].related[0].message, "'f' is first declared here as float f(int)");
+    }
+
+    #[test]
+    fn intrinsics() {
+        // intrinsics are called without a declaration, with their own
+        // argument count, but a declaration of the same name hides them
+        let source = "int print_char;\nvoid main(){\nint x;\nx = print_int(1) + print_int(2);\nx = read_int(x);\nx = print_char(10);\nx = print_float;\n}\n";
+        let (table, diagnostics) = resolve_source(source);
+        assert_eq!(codes(&diagnostics), [("X0207", 4, 4), ("X0205", 5, 4), ("X0200", 6, 4)]);
+        assert_eq!(diagnostics[0].message, "'read_int' takes 0 arguments, but 1 is given.");
+        assert!(diagnostics[0].related.is_empty());
+        let intrinsics: Vec<(&str, usize)> = table
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.kind == SymbolKind::Intrinsic)
+            .map(|symbol| (symbol.name.as_str(), symbol.parameters.len()))
+            .collect();
+        assert_eq!(intrinsics, [("print_int", 1), ("read_int", 0)]);
+        assert_eq!(table.uses.values().filter(|&&symbol| table.symbols[symbol].name == "print_int").count(), 2);
+    }
+}
@@ -19,13 +19,13 @@ pub struct CStream {
 	pub char_pos: i32,
 	
 	//read the whole file at once
-	//and store it in a vector of strings
-	//so we need to file the pointer here 
-	input_file: Vec<String>, 
+	//and store it in a vector of lines, each line as its characters 
+	//so that positions count characters and not bytes 
+	input_file: Vec<Vec<char>>, 
 
 	
 	//the contents of the current line 
-	current_line_contents: String, 
+	current_line_contents: Vec<char>, 
 	
 	
 }
@@ -46,10 +46,23 @@ impl CStream {
 		
 		let initial_file_contents = fs::read_to_string(xfilename).expect("Error occurred when reading the input file!"); 
 		
+		CStream::from_source(xfilename, &initial_file_contents)
+		
+	}
+	
+	//the same for contents that are already in memory, xfilename only names them
+	
+	pub fn from_source(xfilename: &str, initial_file_contents: &str) -> CStream {
+		
 		//use the function to_string() to convert any data types into a string here
 		//https://doc.rust-lang.org/std/string/trait.ToString.html?search=to_string
 		//use lines().map()..collect here to read the x file contents by lines 
-		let input_file: Vec<String> = initial_file_contents.lines().map(|mystring| mystring.to_string()).collect(); 
+		let mut input_file: Vec<Vec<char>> = initial_file_contents.lines().map(|mystring| mystring.chars().collect()).collect(); 
+		
+		//an empty file is one empty line 
+		if input_file.is_empty(){
+			input_file.push(Vec::new()); 
+		}
 		
 		CStream {
 			
@@ -78,21 +91,29 @@ impl CStream {
 	
 	pub fn more_available(&self) -> bool {
 		
-		/*if the current line number as pointer sized unsigned integer less than the 
-		maximum size of the buffer file, there are still more characters to be read, 
+		/*if there is a character after the current one, on the current line or on 
+		one of the following lines, there are still more characters to be read, 
 		return true. otherwise, EOF, return false 
 		*/
 		//https://doc.rust-lang.org/std/primitive.usize.html
 		
-		(self.line_num as usize) < (self.input_file.len()-1)
+		((self.char_pos + 1) as usize) < self.current_line_contents.len() 
+		|| self.input_file[(self.line_num as usize) + 1..].iter().any(|line| !line.is_empty())
 		
 	}
 	
 
 	//Moving to the next character and returns it
+	//at the end of the file it stays there and returns a space 
 	
 	pub fn get_next_char(&mut self)-> char{
 		
+		if !self.more_available(){
+			
+			self.char_pos = self.current_line_contents.len() as i32; 
+			return ' '; 
+		}
+		
 		self.char_pos += 1; 
 		
 		while self.char_pos as usize >= self.current_line_contents.len(){
@@ -103,7 +124,7 @@ impl CStream {
 			
 		}
 		
-		return self.current_line_contents.chars().nth(self.char_pos as usize).unwrap();
+		self.current_line_contents[self.char_pos as usize]
 	
 	}
 	
@@ -112,9 +133,13 @@ impl CStream {
 	
 	pub fn get_cur_char(&self)-> char {
 		
-		//get the &str of the current char_pos by using chars().nth(char index).unwrap() method
-		//https://www.codegrepper.com/code-examples/whatever/rust+get+nth+char+in+string
-		return self.current_line_contents.chars().nth(self.char_pos as usize).unwrap(); 
+		//get the char at the current char_pos, a space before the first character 
+		//and after the last one 
+		//https://doc.rust-lang.org/std/primitive.slice.html#method.get
+		if self.char_pos < 0 {
+			return ' '; 
+		}
+		self.current_line_contents.get(self.char_pos as usize).copied().unwrap_or(' ')
 		
 		
 	}
@@ -124,7 +149,7 @@ impl CStream {
 	//the next character is the kth characters at char pos 1  
 	pub fn peek_next_char(&self)->char {
 		
-		return self.peek_ahead_char(1); 
+		self.peek_ahead_char(1)
 	}
 	
 
@@ -156,6 +181,10 @@ impl CStream {
 			//increment line number 
 			line_num += 1; 
 			
+			//past the end of the file 
+			if line_num as usize >= self.input_file.len(){
+				return ' '; 
+			}
 			
 			//save the current line contents as a copy 
 			
@@ -166,9 +195,9 @@ impl CStream {
 		//looping through the rest kth characters 
 		 char_pos += kth_character; 
 		
-		//obtain a &str at the kth character 
+		//obtain the char at the kth character 
 		
-		return current_line_contents.chars().nth(char_pos as usize).unwrap(); 
+		current_line_contents[char_pos as usize]
 		
 	}
 	
@@ -184,12 +213,18 @@ impl CStream {
 		
 		while  ((kth_character as i32)+self.char_pos) >= (self.current_line_contents.len() as i32) {
 			
-			kth_character -= self.current_line_contents.len() - (self.char_pos as usize); 
+			//stop at the end of the file 
+			if self.line_num as usize + 1 >= self.input_file.len(){
+				self.char_pos = self.current_line_contents.len() as i32; 
+				return; 
+			}
+			
+			kth_character -= (self.current_line_contents.len() as i32 - self.char_pos) as usize; 
 			
 			self.char_pos = 0; 
 			
 			//increment line number 
-			self.line_num = self.line_num + 1;
+			self.line_num += 1;
 			
 			
 			
@@ -202,57 +237,73 @@ impl CStream {
 		self.char_pos += kth_character as i32; 
 
 	}
-		
+	
+	
+	//the characters after the current one on the same line, without moving
+
+	pub fn rest_of_line(&self) -> String {
+
+		self.current_line_contents.iter().skip((self.char_pos + 1).max(0) as usize).collect()
+	}
+
+
+	//move to the last character of the current line, so a line comment is skipped
+
+	pub fn skip_rest_of_line(&mut self) {
+
+		self.char_pos = self.char_pos.max(self.current_line_contents.len() as i32 - 1);
+	}
+
 }
 
 
 //Stage 1 testing codes 
 
-pub fn CStreamTest(){
-	
-	let mut t = CStream::new("testing.txt"); 
-	
-	//debugging using assert_eq!
-	//char pos = 0 always at the current character index 
-	
-	assert_eq!(t.peek_next_char(),'b'); 
-	assert_eq!(t.peek_ahead_char(6),'l'); 
-	assert_eq!(t.get_next_char(),'b'); 
-	assert_eq!(t.peek_ahead_char(10),' ');
-	assert_eq!(t.get_next_char(),'i');
-	assert_eq!(t.get_next_char(),'g');
-	assert_eq!(t.get_cur_char(),'g');
-	assert_eq!(t.peek_ahead_char(12),'g'); 
-	//see if return false when reached the EOF 
-	assert_eq!(t.more_available(),true); 
-	//move to starting at the char pos at kth character as char pos 0 
-	t.locate_ahead_char(10); 
-	//see if successfully skip lines of whitespaces 
-	assert_eq!(t.get_cur_char(),'a'); 
-	assert_eq!(t.get_next_char(),'n'); 
-	assert_eq!(t.get_cur_char(),'n');
-	assert_eq!(t.get_next_char(),'g');
-	assert_eq!(t.get_cur_char(),'g'); 
-	//regard the cur char pos as 0, move two next pos
-	assert_eq!(t.get_next_char(),'o'); 
-	assert_eq!(t.get_next_char(),'g');
-	assert_eq!(t.get_next_char(),'r'); 
-	assert_eq!(t.get_next_char(),'a'); 
-	assert_eq!(t.get_next_char(),'p'); 
-	assert_eq!(t.get_next_char(),'e'); 
-	assert_eq!(t.get_next_char(),'f'); 
-	assert_eq!(t.get_next_char(),'r'); 
-	assert_eq!(t.get_next_char(),'u'); 
-	assert_eq!(t.get_next_char(),'i'); 
-	assert_eq!(t.get_next_char(),'t'); 
-	assert_eq!(t.get_next_char(),'c'); 
-	//should reached the EOF 
-	assert_eq!(t.more_available(),false); 
-	
-	//if all CStraem functions tested correctly. output
-	//otherwise, the main thread will panic.
-	
-	println!("Stage 1 successfully approached! All CStream tests passed!");
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn cstream() {
 	
+		let mut t = CStream::new("testing.txt"); 
+	
+		//debugging using assert_eq!
+		//char pos = 0 always at the current character index 
+	
+		assert_eq!(t.peek_next_char(),'b'); 
+		assert_eq!(t.peek_ahead_char(6),'l'); 
+		assert_eq!(t.get_next_char(),'b'); 
+		assert_eq!(t.peek_ahead_char(10),' ');
+		assert_eq!(t.get_next_char(),'i');
+		assert_eq!(t.get_next_char(),'g');
+		assert_eq!(t.get_cur_char(),'g');
+		assert_eq!(t.peek_ahead_char(12),'g'); 
+		//see if return false when reached the EOF 
+		assert!(t.more_available()); 
+		//move to starting at the char pos at kth character as char pos 0 
+		t.locate_ahead_char(10); 
+		//see if successfully skip lines of whitespaces 
+		assert_eq!(t.get_cur_char(),'a'); 
+		assert_eq!(t.get_next_char(),'n'); 
+		assert_eq!(t.get_cur_char(),'n');
+		assert_eq!(t.get_next_char(),'g');
+		assert_eq!(t.get_cur_char(),'g'); 
+		//regard the cur char pos as 0, move two next pos
+		assert_eq!(t.get_next_char(),'o'); 
+		assert_eq!(t.get_next_char(),'g');
+		assert_eq!(t.get_next_char(),'r'); 
+		assert_eq!(t.get_next_char(),'a'); 
+		assert_eq!(t.get_next_char(),'p'); 
+		assert_eq!(t.get_next_char(),'e'); 
+		assert_eq!(t.get_next_char(),'f'); 
+		assert_eq!(t.get_next_char(),'r'); 
+		assert_eq!(t.get_next_char(),'u'); 
+		assert_eq!(t.get_next_char(),'i'); 
+		assert_eq!(t.get_next_char(),'t'); 
+		assert_eq!(t.get_next_char(),'c'); 
+		//should reached the EOF 
+		assert!(!t.more_available());
+	}
 }
 
@@ -3,6 +3,7 @@
 //include the CStream crate
 use crate::stage1::CStream; 
 use crate::stage2_token::{Token, TokenType};
+use crate::operators::OPERATORS;
 
 
 //Write a struct called Scanner that will tokenize
@@ -16,6 +17,9 @@ pub struct Scanner{
 	//name the vector all_tokens and create it in the main function 
 	pub all_tokens: Vec<Token>,
 	
+	//the // line comments, which are not tokens of the grammar 
+	pub comments: Vec<Token>,
+	
 	//match the intended keywords and operators from TokenType elements
 	//store into vectors of strings 
 	op_matched: Vec<String>, 
@@ -31,24 +35,45 @@ impl Scanner {
 	//initializer read the input x file 
 	pub fn new(xfilename: &str) -> Scanner{
 		
+		Scanner::from_stream(CStream::new(xfilename))
+		
+	}
+	
+	//scan source text that is already in memory 
+	pub fn from_source(xfilename: &str, contents: &str) -> Scanner{
+		
+		Scanner::from_stream(CStream::from_source(xfilename, contents))
+		
+	}
+	
+	fn from_stream(t: CStream) -> Scanner{
+		
 		Scanner{
 			
-			t:CStream::new(xfilename), 
+			t, 
 			
 			all_tokens: Vec::new(), 
 			
+			comments: Vec::new(), 
+			
 			//use Vec type here to access tokentype values by index
 			//since it implements the Index trait. 
 			//https://web.mit.edu/rust-lang_v1.26.0/arch/amd64_ubuntu1404/share/doc/rust/html/std/vec/struct.Vec.html
 			//use iterating operations to collect as successful vector of strings values
 			//https://doc.rust-lang.org/rust-by-example/error/iter_result.html
 			
-			op_matched:vec![
-				"(", ",", ")", "{", "}", "=", 
-			"==", "<", ">", "<=", ">=", "!=", 
-			"+", "-", "*", "/",";",]
-			.into_iter().map(|mystring| mystring.to_string())
-			.collect::<Vec<String>>(), 
+			//the punctuation plus every operator of the expression operator table
+			op_matched:{
+				let mut op_matched = vec!["(", ",", ")", "{", "}", ";",]
+				.into_iter().map(|mystring| mystring.to_string())
+				.collect::<Vec<String>>(); 
+				for operator in OPERATORS {
+					if !op_matched.contains(&operator.text.to_string()){
+						op_matched.push(operator.text.to_string()); 
+					}
+				}
+				op_matched
+			}, 
 			//similarly to the valid key elements 
 			key_matched:vec! [
 				"unsigned", "char", "short", "int", "long", 
@@ -75,7 +100,7 @@ impl Scanner {
 		
 		//create a vector of characters 
 		//determine if the character at the first index position matches the operators 
-		let mut seeking_for_matches = "".to_string();
+		let mut seeking_for_matches; 
 		
 		//iterate through the vector of chars 
 		let first_op_char: Vec<char> = self.op_matched.iter().map(|mystring| mystring.chars().nth(0).unwrap()).collect(); 
@@ -84,14 +109,30 @@ impl Scanner {
 		//use is_whitespace() method to detect if the char has the any white spaces
 		//https://doc.rust-lang.org/std/primitive.char.html
 		//if not yet reach the EOF
-		while self.t.more_available(){
-			//convert the next character read into string type
-			seeking_for_matches = self.t.get_next_char().to_string();
-			//if the last char does not contain white spaces, break. 
-			//if contains, skip 
-			if !seeking_for_matches.chars().last().unwrap().is_whitespace(){
-				break; 
+		loop{
+			seeking_for_matches = "".to_string();
+			while self.t.more_available(){
+				//convert the next character read into string type
+				seeking_for_matches = self.t.get_next_char().to_string();
+				//if the last char does not contain white spaces, break.
+				//if contains, skip
+				if !seeking_for_matches.chars().last().unwrap().is_whitespace(){
+					break;
+				}
+			}
+
+			//a line comment is skipped like whitespace and kept in comments
+			let rest = self.t.rest_of_line();
+			if seeking_for_matches != "/" || !rest.starts_with('/'){
+				break;
 			}
+			self.comments.push(Token::new(TokenType::Comment, &format!("/{}", rest), self.t.line_num, self.t.char_pos));
+			self.t.skip_rest_of_line();
+		}
+
+		//only whitespace was left before EOF 
+		if seeking_for_matches.trim().is_empty(){
+			return None; 
 		}
 		
 		//infinitely loop through the vector until encounters EOF, whitespace properties or operators 
@@ -124,6 +165,12 @@ impl Scanner {
 				//with the next character, here to update the matches seeking 
 				//use format! method here for error handling 
 				//https://doc.rust-lang.org/std/macro.format.html
+				seeking_for_matches = format!("{}{}", seeking_for_matches, self.t.get_next_char()); 
+				}
+			//keep joining operator chars while they still spell the start of an operator,
+			//so >= and == are one token 
+			else if self.op_matched.iter().any(|op| op.starts_with(&format!("{}{}", seeking_for_matches, next_char))){
+				
 				seeking_for_matches = format!("{}{}", seeking_for_matches, self.t.get_next_char()); 
 				}else{
 					
@@ -143,8 +190,8 @@ impl Scanner {
 		//initializer of the token type as invalid tokentype
 		let mut token_type = TokenType::Invalid; 
 		
-		//starting from the 1st char at pos 0 
-		let first_char = seeking_for_matches.chars().nth(0).unwrap(); 
+		//starting from the 1st char at pos 0, next_token never passes an empty string 
+		let first_char = seeking_for_matches.chars().next().unwrap_or(' '); 
 		
 		//check the float constant 
 		//first check if numeric 
@@ -152,13 +199,13 @@ impl Scanner {
 		//either puerly number or single dot 
 		
 		//CASE 2: intented Keywords 
-		if self.key_matched.contains(&seeking_for_matches){
+		if self.key_matched.contains(seeking_for_matches){
 			
 			token_type = TokenType::Keyword; 
 		}
 		
 		//CASE 3: intended Operator 
-		else if self.op_matched.contains(&seeking_for_matches){
+		else if self.op_matched.contains(seeking_for_matches){
 			
 			token_type = TokenType::Operator; 
 			
@@ -177,12 +224,12 @@ impl Scanner {
 			//if only a dot without the numeric number (return true contents)
 			//use count() method to return the number of elements in the iterator without counting in the loop 
 			//return the numeric value of object pointed by a pointer integer variable only after filtering 
-			let dot_only = seeking_for_matches.chars().into_iter().filter(|mychar| *mychar == '.').count() == 1; 
+			let dot_only = seeking_for_matches.chars().filter(|mychar| *mychar == '.').count() == 1; 
 		
 			//if only number .(return true contents ) 
 			//use filter() method to filter out only the number at this char position in the iteration
 			//https://docs.rs/filters/latest/filters/
-			let number_only = seeking_for_matches.chars().into_iter().filter(|mychar| *mychar != '.').all(|mychar| mychar.is_numeric()); 
+			let number_only = seeking_for_matches.chars().filter(|mychar| *mychar != '.').all(|mychar| mychar.is_numeric()); 
 	
 			//if both dot and number -> matched float constant type 
 			if number_only && dot_only{
@@ -211,11 +258,11 @@ impl Scanner {
 			token_type, 
 			
 			//return the memory address 
-			&seeking_for_matches, 
+			seeking_for_matches, 
 			
 			self.t.line_num, 
 			//indexing up 
-			self.t.char_pos + 1 - seeking_for_matches.len() as i32, 
+			self.t.char_pos + 1 - seeking_for_matches.chars().count() as i32, 
 			
 			
 		); 
@@ -225,7 +272,7 @@ impl Scanner {
 		
 		//Scanner returned a vector of tokens! 
 		
-		return token; 
+		token
 		
 	}
 	
@@ -233,7 +280,7 @@ impl Scanner {
 	//run to test scanner 
 	pub fn run(&mut self) -> &mut Scanner {
 		
-		while self.next_token() != None{}
+		while self.next_token().is_some(){}
 		//called get_next_token() function to return the next token 
 		//as read from the .x file. the token type is returned. 
 		self
@@ -259,94 +306,95 @@ pub fn run_test() {
 //row_num starting with 1 as the top most line_num, 
 //char_pos starting with left most as 0 
 
-pub fn ScannerTest(){
+#[cfg(test)]
+mod tests {
+	use super::*;
 	
-	//also check if successfully skip all whitespaces 
-	let all_token_tests = vec![(
+	#[test]
+	fn scanner() {
+	
+		//also check if successfully skip all whitespaces 
+		let all_token_tests = [(
 		
-		"example1.x",
-		vec![
-			Token::new(TokenType::Keyword, "float", 1, 0),
-			Token::new(TokenType::Identifier, "Foo", 1, 6),
-			Token::new(TokenType::Operator, "(", 1, 9),
-			Token::new(TokenType::Keyword, "int", 1, 10),
-			Token::new(TokenType::Identifier, "val", 1, 14),
-			Token::new(TokenType::Operator, ")", 1, 17),
-			Token::new(TokenType::Operator, ";", 1, 18),
-			Token::new(TokenType::Keyword, "void", 3, 0),
-			Token::new(TokenType::Keyword, "main", 3, 5),
-			Token::new(TokenType::Operator, "(", 3, 9),
-			Token::new(TokenType::Operator, ")", 3, 10),
-			Token::new(TokenType::Operator, "{", 3, 11),
-			Token::new(TokenType::Keyword, "float", 4, 4),
-			Token::new(TokenType::Identifier, "Value", 4, 10),
-			Token::new(TokenType::Operator, ";", 4, 15),
-			Token::new(TokenType::Identifier, "Value", 6, 4),
-			Token::new(TokenType::Operator, "=", 6, 10),
-			Token::new(TokenType::Identifier, "Foo", 6, 12),
-			Token::new(TokenType::Operator, "(", 6, 15),
-			Token::new(TokenType::IntConstant, "7", 6, 16),
-			Token::new(TokenType::Operator, ")", 6, 17),
-			Token::new(TokenType::Operator, ";", 6, 18),
-			Token::new(TokenType::Operator, "}", 7, 0),
-			Token::new(TokenType::Keyword, "float", 9, 0),
-			Token::new(TokenType::Identifier, "Foo", 9, 6),
-			Token::new(TokenType::Operator, "(", 9, 9),
-			Token::new(TokenType::Keyword, "int", 9, 10),
-			Token::new(TokenType::Identifier, "val", 9, 14),
-			Token::new(TokenType::Operator, ")", 9, 17),
-			Token::new(TokenType::Operator, "{", 9, 18),
-			Token::new(TokenType::Keyword, "float", 10, 4),
-			Token::new(TokenType::Identifier, "TestFloat", 10, 10),
-			Token::new(TokenType::Operator, "=", 10, 20),
-			Token::new(TokenType::FloatConstant, "1.4", 10, 22),
-			Token::new(TokenType::Operator, ";", 10, 25),
-			Token::new(TokenType::Keyword, "while", 12, 4),
-			Token::new(TokenType::Operator, "(", 12, 9),
-			Token::new(TokenType::Identifier, "val", 12, 10),
-			Token::new(TokenType::Operator, ">", 12, 14),
-			Token::new(TokenType::IntConstant, "0", 12, 16),
-			Token::new(TokenType::Operator, ")", 12, 17),
-			Token::new(TokenType::Operator, "{", 12, 18),
-			Token::new(TokenType::Identifier, "TestFloat", 13, 8),
-			Token::new(TokenType::Operator, "=", 13, 18),
-			Token::new(TokenType::Identifier, "TestFloat", 13, 20),
-			Token::new(TokenType::Operator, "*", 13, 30),
-			Token::new(TokenType::Identifier, "TestFloat", 13, 32),
-			Token::new(TokenType::Operator, ";", 13, 41),
-			Token::new(TokenType::Identifier, "val", 14, 8),
-			Token::new(TokenType::Operator, "=", 14, 12),
-			Token::new(TokenType::Identifier, "val", 14, 14),
-			Token::new(TokenType::Operator, "-", 14, 18),
-			Token::new(TokenType::IntConstant, "1", 14, 20),
-			Token::new(TokenType::Operator, ";", 14, 21),
-			Token::new(TokenType::Operator, "}", 15, 4),
-			Token::new(TokenType::Keyword, "return", 16, 4),
-			Token::new(TokenType::Identifier, "TestFloat", 16, 11),
-			Token::new(TokenType::Operator, ";", 16, 20),
-			Token::new(TokenType::Operator, "}", 17, 0),
+			"example1.x",
+			vec![
+				Token::new(TokenType::Keyword, "float", 1, 0),
+				Token::new(TokenType::Identifier, "Foo", 1, 6),
+				Token::new(TokenType::Operator, "(", 1, 9),
+				Token::new(TokenType::Keyword, "int", 1, 10),
+				Token::new(TokenType::Identifier, "val", 1, 14),
+				Token::new(TokenType::Operator, ")", 1, 17),
+				Token::new(TokenType::Operator, ";", 1, 18),
+				Token::new(TokenType::Keyword, "void", 3, 0),
+				Token::new(TokenType::Keyword, "main", 3, 5),
+				Token::new(TokenType::Operator, "(", 3, 9),
+				Token::new(TokenType::Operator, ")", 3, 10),
+				Token::new(TokenType::Operator, "{", 3, 11),
+				Token::new(TokenType::Keyword, "float", 4, 4),
+				Token::new(TokenType::Identifier, "Value", 4, 10),
+				Token::new(TokenType::Operator, ";", 4, 15),
+				Token::new(TokenType::Identifier, "Value", 6, 4),
+				Token::new(TokenType::Operator, "=", 6, 10),
+				Token::new(TokenType::Identifier, "Foo", 6, 12),
+				Token::new(TokenType::Operator, "(", 6, 15),
+				Token::new(TokenType::IntConstant, "7", 6, 16),
+				Token::new(TokenType::Operator, ")", 6, 17),
+				Token::new(TokenType::Operator, ";", 6, 18),
+				Token::new(TokenType::Operator, "}", 7, 0),
+				Token::new(TokenType::Keyword, "float", 9, 0),
+				Token::new(TokenType::Identifier, "Foo", 9, 6),
+				Token::new(TokenType::Operator, "(", 9, 9),
+				Token::new(TokenType::Keyword, "int", 9, 10),
+				Token::new(TokenType::Identifier, "val", 9, 14),
+				Token::new(TokenType::Operator, ")", 9, 17),
+				Token::new(TokenType::Operator, "{", 9, 18),
+				Token::new(TokenType::Keyword, "float", 10, 4),
+				Token::new(TokenType::Identifier, "TestFloat", 10, 10),
+				Token::new(TokenType::Operator, "=", 10, 20),
+				Token::new(TokenType::FloatConstant, "1.4", 10, 22),
+				Token::new(TokenType::Operator, ";", 10, 25),
+				Token::new(TokenType::Keyword, "while", 12, 4),
+				Token::new(TokenType::Operator, "(", 12, 9),
+				Token::new(TokenType::Identifier, "val", 12, 10),
+				Token::new(TokenType::Operator, ">", 12, 14),
+				Token::new(TokenType::IntConstant, "0", 12, 16),
+				Token::new(TokenType::Operator, ")", 12, 17),
+				Token::new(TokenType::Operator, "{", 12, 18),
+				Token::new(TokenType::Identifier, "TestFloat", 13, 8),
+				Token::new(TokenType::Operator, "=", 13, 18),
+				Token::new(TokenType::Identifier, "TestFloat", 13, 20),
+				Token::new(TokenType::Operator, "*", 13, 30),
+				Token::new(TokenType::Identifier, "TestFloat", 13, 32),
+				Token::new(TokenType::Operator, ";", 13, 41),
+				Token::new(TokenType::Identifier, "val", 14, 8),
+				Token::new(TokenType::Operator, "=", 14, 12),
+				Token::new(TokenType::Identifier, "val", 14, 14),
+				Token::new(TokenType::Operator, "-", 14, 18),
+				Token::new(TokenType::IntConstant, "1", 14, 20),
+				Token::new(TokenType::Operator, ";", 14, 21),
+				Token::new(TokenType::Operator, "}", 15, 4),
+				Token::new(TokenType::Keyword, "return", 16, 4),
+				Token::new(TokenType::Identifier, "TestFloat", 16, 11),
+				Token::new(TokenType::Operator, ";", 16, 20),
+				Token::new(TokenType::Operator, "}", 17, 0),
 			
 						
-//all characters are tested properly in example1.x file
+	//all characters are tested properly in example1.x file
 			
-		],
-	)];
+			],
+		)];
 	
 
 	
-	//error handling 
-	let has_error = false;
-	for (i, (input, expected)) in all_token_tests.iter().enumerate() {
-		let actual = Scanner::new(input).run().all_tokens.clone();
-		let res = actual.iter().eq(expected.iter());
-		assert!(
-			res,
-			"\nTest {} failed. On input\n{},\nexpected: '{:?}'\n but got '{:?}'",
-			i, input, expected, actual
-		);
-	}
-	if !has_error {
-		println!("Stage 2 successfully approached! All characters in example1.x file tests passed!");
+		for (i, (input, expected)) in all_token_tests.iter().enumerate() {
+			let actual = Scanner::new(input).run().all_tokens.clone();
+			let res = actual.iter().eq(expected.iter());
+			assert!(
+				res,
+				"\nTest {} failed. On input\n{},\nexpected: '{:?}'\n but got '{:?}'",
+				i, input, expected, actual
+			);
+		}
 	}
 }
 
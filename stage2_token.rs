@@ -16,6 +16,8 @@ pub enum TokenType{
 	Operator, 
 	Identifier, 
 	Invalid, 
+	//a // line comment, only in Scanner::comments 
+	Comment, 
 }
 
 //Define a struct called Token with the following attributes 
@@ -81,6 +83,7 @@ impl std::fmt::Debug for Token{
 				TokenType::Operator => "Operator",
 				TokenType::Identifier => "Identifier",
 				TokenType::Invalid => "Invalid",
+				TokenType::Comment => "Comment",
 				
 			}
 		)
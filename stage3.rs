@@ -3,11 +3,25 @@
 extern crate custom_error;
 use custom_error::custom_error;
 
-
+use std::any::Any;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::ast::{
+    Block, DataType, Declaration, Expression, ExpressionKind, FunctionDeclaration,
+    FunctionDefinition, MainDeclaration, Parameter, Program, Statement, StatementKind,
+    VariableDeclaration,
+};
+use crate::diagnostics::Span;
+use crate::coverage::Coverage;
+use crate::operators::{self, Associativity, Fixity, Operator, OPERATORS};
 use crate::stage2_scanner::Scanner;
 use crate::stage2_token::{Token, TokenType};
+use crate::trace::{self, Trace};
 
-custom_error! {SyntaxError{line_num:i32, char_pos:i32, ebnf: String} ="Error at Line {line_num} Character {char_pos}. The syntax should be: {ebnf}."}
+custom_error! {pub SyntaxError{line_num:i32, char_pos:i32, ebnf: String} ="Error at Line {line_num} Character {char_pos}. The syntax should be: {ebnf}."}
 
 macro_rules!syntax_error{
     ($token:expr, $ebnf:expr) => {
@@ -32,7 +46,7 @@ macro_rules! ebnf_run {
             }
             Err(e) => {
                 // syntax check failed, reset index
-                $self.token_index = prev_index;
+                $self.backtrack(prev_index);
                 $err_block;
                 if $is_return_err {
                     return Err(e);
@@ -44,17 +58,16 @@ macro_rules! ebnf_run {
 
 macro_rules! ebnf_or {
     ($ebnf:expr, $self:ident, $(fn $closure:expr),+) => {
+        let mut alternative = 0;
         loop { // loop hack to exit block scope
             $(
+                alternative += 1;
                 ebnf_run!({
+                    $self.cover_alternative($ebnf, alternative - 1);
                     break;
                 }, {}, false,$self, fn $closure);
             )+
-            if $self.token_index >= $self.all_tokens.len() {
-                return syntax_error!($self.all_tokens.last().unwrap(), $ebnf)
-            } else {
-                return syntax_error!($self.all_tokens[$self.token_index], $ebnf);
-            };
+            return Err($self.error_at($self.token_index, $ebnf));
         }
     };
 }
@@ -67,7 +80,7 @@ macro_rules! ebnf_optional {
                 ebnf_run!({}, { // err block
                     if true { // bypass #[warn(unreachable_code)]
                         // syntax check failed, reset index
-                        $self.token_index = prev_index;
+                        $self.backtrack(prev_index);
                         break;
                     }
                 }, false, $self, fn $closure);
@@ -85,7 +98,7 @@ macro_rules! ebnf_repetition { // zero or more
                 ebnf_run!({}, { // err block
                     if true {  // bypass #[warn(unreachable_code)]
                         // syntax check failed, reset index
-                        $self.token_index = prev_index;
+                        $self.backtrack(prev_index);
                         break;
                     }
                 }, false, $self, fn $closure);
@@ -94,6 +107,29 @@ macro_rules! ebnf_repetition { // zero or more
     };
 }
 
+// packrat memoization: the body of a rule runs at most once per token index,
+// later attempts at the same index reuse the cached result. The subtrees of
+// expressions and blocks are shared behind an Arc, so storing and reusing a
+// result copies one node and not the whole subtree under it
+macro_rules! ebnf_rule {
+    ($self:ident, $ebnf:expr, $body:block) => {{
+        let start = $self.token_index;
+        $self.enter_rule($ebnf, start);
+        if let Some(res) = $self.memo_lookup($ebnf, start) {
+            $self.exit_rule($ebnf, start, &res, true);
+            return res;
+        }
+        $self.rules_run += 1;
+        let res = (|| -> Result<_, SyntaxError> { $body })();
+        $self.memo_store($ebnf, start, &res);
+        $self.exit_rule($ebnf, start, &res, false);
+        res
+    }};
+}
+
+// a cached rule result: the index after the rule and its value, or the error
+type MemoEntry = Result<(usize, Rc<dyn Any>), SyntaxError>;
+
 /**
  * Parses through and validates a .x file, analyzing syntax and
  * ensuring semantics. Terminates on the first error if an error exists.
@@ -103,6 +139,14 @@ pub struct Parser {
     pub all_tokens: Vec<Token>,
     // current token index
     token_index: usize,
+    // results of rules by (rule ebnf, token index where the rule started)
+    memo: HashMap<(&'static str, usize), MemoEntry>,
+    // how many rule bodies the last parse ran, linear in the number of tokens
+    pub rules_run: usize,
+    // set to Some to record the rules and backtracks of the next parse
+    pub trace: Option<Trace>,
+    // set to Some to count the rules and alternatives parses exercise
+    pub coverage: Option<Coverage>,
 }
 
 // ebnf_repeat!
@@ -112,9 +156,94 @@ pub struct Parser {
 impl Parser {
     // init
     pub fn new(xfilename: &str) -> Parser {
+        Parser::from_tokens(Scanner::new(xfilename).run().all_tokens.clone())
+    }
+
+    // parses tokens that were scanned or built elsewhere
+    pub fn from_tokens(all_tokens: Vec<Token>) -> Parser {
         Parser {
-            all_tokens: Scanner::new(xfilename).run().all_tokens.clone(),
+            all_tokens,
             token_index: 0,
+            memo: HashMap::new(),
+            rules_run: 0,
+            trace: None,
+            coverage: None,
+        }
+    }
+
+    fn enter_rule(&mut self, ebnf: &'static str, start: usize) {
+        if let Some(trace) = &mut self.trace {
+            trace.enter(trace::rule_name(ebnf), start);
+            if trace.step {
+                trace.pause(&self.all_tokens[start.min(self.all_tokens.len())..]);
+            }
+        }
+    }
+
+    fn exit_rule<T>(&mut self, ebnf: &'static str, start: usize, res: &Result<T, SyntaxError>, cached: bool) {
+        if let Some(trace) = &mut self.trace {
+            let error = res.as_ref().err().map(|e| e.to_string());
+            trace.exit(trace::rule_name(ebnf), start, self.token_index, error, cached);
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.rule(trace::rule_name(ebnf), res.is_ok());
+        }
+    }
+
+    // an ebnf_or! of a rule took its alternative-th alternative
+    fn cover_alternative(&mut self, ebnf: &'static str, alternative: usize) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.alternative(trace::rule_name(ebnf), alternative);
+        }
+    }
+
+    // moves back to an earlier token after a failed attempt
+    fn backtrack(&mut self, to: usize) {
+        if let Some(trace) = &mut self.trace {
+            if self.token_index != to {
+                trace.backtrack(self.token_index, to);
+            }
+        }
+        self.token_index = to;
+    }
+
+    fn memo_lookup<T: Clone + 'static>(&mut self, ebnf: &'static str, start: usize) -> Option<Result<T, SyntaxError>> {
+        match self.memo.get(&(ebnf, start))? {
+            Ok((end, value)) => {
+                self.token_index = *end;
+                Some(Ok(value.downcast_ref::<T>().unwrap().clone()))
+            }
+            Err(e) => Some(Err(SyntaxError {
+                line_num: e.line_num,
+                char_pos: e.char_pos,
+                ebnf: e.ebnf.clone(),
+            })),
+        }
+    }
+
+    fn memo_store<T: Clone + 'static>(&mut self, ebnf: &'static str, start: usize, res: &Result<T, SyntaxError>) {
+        let entry: MemoEntry = match res {
+            Ok(value) => Ok((self.token_index, Rc::new(value.clone()))),
+            Err(e) => Err(SyntaxError {
+                line_num: e.line_num,
+                char_pos: e.char_pos,
+                ebnf: e.ebnf.clone(),
+            }),
+        };
+        self.memo.insert((ebnf, start), entry);
+    }
+
+    // the error at the token at index; past the end at the last token, and at
+    // the start of the file if there are no tokens
+    fn error_at(&self, index: usize, ebnf: &str) -> SyntaxError {
+        let (line_num, char_pos) = match self.all_tokens.get(index).or(self.all_tokens.last()) {
+            Some(token) => (token.line_num, token.char_pos),
+            None => (0, 0),
+        };
+        SyntaxError {
+            line_num,
+            char_pos,
+            ebnf: ebnf.to_string(),
         }
     }
 
@@ -123,10 +252,10 @@ impl Parser {
         token_type: TokenType,
         text: &str,
         ebnf: &str,
-    ) -> Result<(), SyntaxError> {
+    ) -> Result<Token, SyntaxError> {
         // no more tokens
         if self.token_index >= self.all_tokens.len() {
-            return syntax_error!(self.all_tokens.last().unwrap(), ebnf);
+            return Err(self.error_at(self.token_index, ebnf));
         }
 
         let token = self.all_tokens[self.token_index].clone();
@@ -136,459 +265,912 @@ impl Parser {
                     return syntax_error!(token, ebnf);
                 }
                 TokenType::Operator | TokenType::Keyword => {
-                    if token.text == text.to_string() {
+                    if token.text == text {
                         self.token_index += 1;
                         // println!("OKAY BUT LIKE {}", self.token_index);
-                        return Ok(());
+                        return Ok(token);
                     }
                 }
                 _ => {
                     self.token_index += 1;
-                    return Ok(());
+                    return Ok(token);
                 }
             }
         }
-        return syntax_error!(token, ebnf);
+        syntax_error!(token, ebnf)
     }
 
-    fn syntax_program(&mut self) -> Result<(), SyntaxError> {
-        let ebnf = "Program := { Declaration } MainDeclaration { FunctionDefinition }";
+    // the operator with a fixity at the current token, looked up in the
+    // operator table
+    fn table_operator(&mut self, fixity: Fixity, ebnf: &'static str) -> Result<(Token, &'static Operator), SyntaxError> {
+        if self.token_index >= self.all_tokens.len() {
+            return Err(self.error_at(self.token_index, ebnf));
+        }
 
-        ebnf_repetition!(self, fn || self.syntax_declaration());
-        ebnf_run!(self, fn || self.syntax_main_declaration());
-        ebnf_repetition!(self, fn || self.syntax_function_definition());
+        let token = self.all_tokens[self.token_index].clone();
+        if token.token_type == TokenType::Operator {
+            if let Some(operator) = operators::find(&token.text, fixity) {
+                // the alternatives of the rule are the operators in table order
+                let alternative = OPERATORS
+                    .iter()
+                    .filter(|o| o.fixity == fixity)
+                    .position(|o| o.text == operator.text)
+                    .unwrap();
+                self.cover_alternative(ebnf, alternative);
+                self.token_index += 1;
+                return Ok((token, operator));
+            }
+        }
+        syntax_error!(token, ebnf)
+    }
 
-        return Ok(());
+    // the span from the token at start to the last token consumed; every
+    // rule that succeeds consumes at least one token
+    fn span_from(&self, start: usize) -> Span {
+        let first = Span::of_token(&self.all_tokens[start]);
+        let last = Span::of_token(&self.all_tokens[self.token_index - 1]);
+        Span {
+            start_line: first.start_line,
+            start_char: first.start_char,
+            end_line: last.end_line,
+            end_char: last.end_char,
+        }
     }
 
-    fn syntax_declaration(&mut self) -> Result<(), SyntaxError> {
-        let ebnf = "Declaration := DeclarationType (VariableDeclaration | FunctionDeclaration)";
+    fn syntax_program(&mut self) -> Result<Program, SyntaxError> {
+        let ebnf = "Program := { Declaration } MainDeclaration { FunctionDefinition }";
 
-        ebnf_run!(self, fn || self.syntax_declaration_type());
-        ebnf_or!(
-            ebnf,
-            self,
-            fn || self.syntax_variable_declaration(),
-            fn || self.syntax_function_declaration()
-        );
+        ebnf_rule!(self, ebnf, {
+            let start = self.token_index;
+            let mut declarations = Vec::new();
+            let mut main = None;
+            let mut functions = Vec::new();
+
+            ebnf_repetition!(self, fn || self.syntax_declaration().map(|d| declarations.push(d)));
+            ebnf_run!(self, fn || self.syntax_main_declaration().map(|m| main = Some(m)));
+            ebnf_repetition!(self, fn || self.syntax_function_definition().map(|f| functions.push(f)));
+
+            return Ok(Program {
+                declarations,
+                main: main.unwrap(),
+                functions,
+                span: self.span_from(start),
+            });
+        })
+    }
+
+    fn syntax_declaration(&mut self) -> Result<Declaration, SyntaxError> {
+        let ebnf = "Declaration := DeclarationType (VariableDeclaration | FunctionDeclaration)";
 
-        return Ok(());
+        ebnf_rule!(self, ebnf, {
+            let start = self.token_index;
+            let mut declaration_type = None;
+            let mut initializer = None;
+            let mut parameters = None;
+
+            ebnf_run!(self, fn || self.syntax_declaration_type().map(|d| declaration_type = Some(d)));
+            ebnf_or!(
+                ebnf,
+                self,
+                fn || self.syntax_variable_declaration().map(|i| initializer = Some(i)),
+                fn || self.syntax_function_declaration().map(|p| parameters = Some(p))
+            );
+
+            let (data_type, name) = declaration_type.unwrap();
+            let span = self.span_from(start);
+            return Ok(match parameters {
+                Some(parameters) => Declaration::Function(FunctionDeclaration {
+                    return_type: data_type,
+                    name,
+                    parameters,
+                    span,
+                }),
+                None => Declaration::Variable(VariableDeclaration {
+                    data_type,
+                    name,
+                    initializer: initializer.unwrap(),
+                    span,
+                }),
+            });
+        })
     }
 
-    fn syntax_main_declaration(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_main_declaration(&mut self) -> Result<MainDeclaration, SyntaxError> {
         let ebnf = "MainDeclaration := void main ( ) Block";
 
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Keyword, "void", ebnf));
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Keyword, "main", ebnf));
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "(", ebnf));
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ")", ebnf));
-        ebnf_run!(self, fn || self.syntax_block());
+        ebnf_rule!(self, ebnf, {
+            let start = self.token_index;
+            let mut block = None;
+
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Keyword, "void", ebnf));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Keyword, "main", ebnf));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "(", ebnf));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ")", ebnf));
+            ebnf_run!(self, fn || self.syntax_block().map(|b| block = Some(b)));
 
-        return Ok(());
+            return Ok(MainDeclaration {
+                block: block.unwrap(),
+                span: self.span_from(start),
+            });
+        })
     }
 
-    fn syntax_function_definition(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_function_definition(&mut self) -> Result<FunctionDefinition, SyntaxError> {
         let ebnf = "FunctionDefinition := DeclarationType ParameterBlock Block";
 
-        ebnf_run!(self, fn || self.syntax_declaration_type());
-        ebnf_run!(self, fn || self.syntax_parameter_block());
-        ebnf_run!(self, fn || self.syntax_block());
-
-        return Ok(());
+        ebnf_rule!(self, ebnf, {
+            let start = self.token_index;
+            let mut declaration_type = None;
+            let mut parameters = None;
+            let mut block = None;
+
+            ebnf_run!(self, fn || self.syntax_declaration_type().map(|d| declaration_type = Some(d)));
+            ebnf_run!(self, fn || self.syntax_parameter_block().map(|p| parameters = Some(p)));
+            ebnf_run!(self, fn || self.syntax_block().map(|b| block = Some(b)));
+
+            let (return_type, name) = declaration_type.unwrap();
+            return Ok(FunctionDefinition {
+                return_type,
+                name,
+                parameters: parameters.unwrap(),
+                block: Arc::new(block.unwrap()),
+                span: self.span_from(start),
+            });
+        })
     }
 
-    fn syntax_declaration_type(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_declaration_type(&mut self) -> Result<(DataType, Token), SyntaxError> {
         let ebnf = "DeclarationType := DataType Identifier";
 
-        ebnf_run!(self, fn || self.syntax_data_type());
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Identifier, "", ebnf));
+        ebnf_rule!(self, ebnf, {
+            let mut data_type = None;
+            let mut name = None;
 
-        return Ok(());
+            ebnf_run!(self, fn || self.syntax_data_type().map(|d| data_type = Some(d)));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Identifier, "", ebnf).map(|t| name = Some(t)));
+
+            return Ok((data_type.unwrap(), name.unwrap()));
+        })
     }
 
-    fn syntax_variable_declaration(&mut self) -> Result<(), SyntaxError> {
-        let ebnf = "VariableDeclaration := [= Constant] ;";
+    // the initializer, if any
+    fn syntax_variable_declaration(&mut self) -> Result<Option<Expression>, SyntaxError> {
+        let ebnf = "VariableDeclaration := [= Expression] ;";
 
-        ebnf_optional!(
-            self,
-            fn || self.terminal_token(TokenType::Operator, "=", "ebnf"),
-            fn || self.syntax_constant()
-        );
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ";", ebnf));
+        ebnf_rule!(self, ebnf, {
+            let mut initializer = None;
 
-        return Ok(());
+            ebnf_optional!(
+                self,
+                fn || self.terminal_token(TokenType::Operator, "=", ebnf),
+                fn || self.syntax_expression().map(|e| initializer = Some(e))
+            );
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ";", ebnf));
+
+            return Ok(initializer);
+        })
     }
 
-    fn syntax_function_declaration(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_function_declaration(&mut self) -> Result<Vec<Parameter>, SyntaxError> {
         let ebnf = "FunctionDeclaration := ParameterBlock ;";
 
-        ebnf_run!(self, fn || self.syntax_parameter_block());
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ";", ebnf));
+        ebnf_rule!(self, ebnf, {
+            let mut parameters = None;
 
-        return Ok(());
+            ebnf_run!(self, fn || self.syntax_parameter_block().map(|p| parameters = Some(p)));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ";", ebnf));
+
+            return Ok(parameters.unwrap());
+        })
     }
 
-    fn syntax_block(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_block(&mut self) -> Result<Block, SyntaxError> {
         let ebnf = "Block := { {Declaration} {Statement} {FunctionDefinition} }";
 
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "{", ebnf));
-        ebnf_repetition!(self, fn || self.syntax_declaration());
-        ebnf_repetition!(self, fn || self.syntax_statement());
-        ebnf_repetition!(self, fn || self.syntax_function_definition());
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "}", ebnf));
-
-        return Ok(());
+        ebnf_rule!(self, ebnf, {
+            let start = self.token_index;
+            let mut declarations = Vec::new();
+            let mut statements = Vec::new();
+            let mut functions = Vec::new();
+
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "{", ebnf));
+            ebnf_repetition!(self, fn || self.syntax_declaration().map(|d| declarations.push(d)));
+            ebnf_repetition!(self, fn || self.syntax_statement().map(|s| statements.push(s)));
+            ebnf_repetition!(self, fn || self.syntax_function_definition().map(|f| functions.push(f)));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "}", ebnf));
+
+            return Ok(Block {
+                declarations,
+                statements,
+                functions,
+                span: self.span_from(start),
+            });
+        })
     }
 
-    fn syntax_parameter_block(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_parameter_block(&mut self) -> Result<Vec<Parameter>, SyntaxError> {
         let ebnf = "ParameterBlock := ( [Parameter {, Parameter}] )";
 
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "(", ebnf));
-
-        // [ Parameter {, Parameter } ]
-        ebnf_optional!(
-            self,
-            // Parameter
-            fn || self.syntax_parameter(),
-            // {, Parameter }
-            fn || {
-                ebnf_repetition!(
-                    self,
-                    // ,
-                    fn || self.terminal_token(TokenType::Operator, ",", "ebnf"),
-                    // Parameter
-                    fn || self.syntax_parameter()
-                );
-                return Ok(());
-            }
-        );
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ")", ebnf));
+        ebnf_rule!(self, ebnf, {
+            let mut parameters = Vec::new();
+
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "(", ebnf));
+
+            // [ Parameter {, Parameter } ]
+            ebnf_optional!(
+                self,
+                // Parameter
+                fn || self.syntax_parameter().map(|p| parameters.push(p)),
+                // {, Parameter }
+                fn || {
+                    ebnf_repetition!(
+                        self,
+                        // ,
+                        fn || self.terminal_token(TokenType::Operator, ",", "ebnf"),
+                        // Parameter
+                        fn || self.syntax_parameter().map(|p| parameters.push(p))
+                    );
+                    Ok(())
+                }
+            );
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ")", ebnf));
 
-        return Ok(());
+            return Ok(parameters);
+        })
     }
 
-    fn syntax_data_type(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_data_type(&mut self) -> Result<DataType, SyntaxError> {
         let ebnf = "DataType := IntegerType | FloatType";
 
-        ebnf_or!(
-            ebnf,
-            self,
-            fn || self.syntax_integer_type(),
-            fn || self.syntax_float_type()
-        );
+        ebnf_rule!(self, ebnf, {
+            let mut data_type = None;
+
+            ebnf_or!(
+                ebnf,
+                self,
+                fn || self.syntax_integer_type().map(|d| data_type = Some(d)),
+                fn || self.syntax_float_type().map(|d| data_type = Some(d))
+            );
 
-        return Ok(());
+            return Ok(data_type.unwrap());
+        })
     }
 
-    fn syntax_constant(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_constant(&mut self) -> Result<Expression, SyntaxError> {
         let ebnf = "Constant := IntConstant | FloatConstant";
 
-        ebnf_or!(ebnf, self,
-            fn || self.terminal_token(TokenType::IntConstant, "", ebnf),
-            fn || self.terminal_token(TokenType::FloatConstant, "", ebnf)
-        );
+        ebnf_rule!(self, ebnf, {
+            let start = self.token_index;
+            let mut constant = None;
+
+            ebnf_or!(ebnf, self,
+                fn || self.terminal_token(TokenType::IntConstant, "", ebnf).map(|t| constant = Some(t)),
+                fn || self.terminal_token(TokenType::FloatConstant, "", ebnf).map(|t| constant = Some(t))
+            );
 
-        return Ok(());
+            return Ok(Expression {
+                kind: ExpressionKind::Constant(constant.unwrap()),
+                span: self.span_from(start),
+                ty: None,
+            });
+        })
     }
 
-    fn syntax_statement(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_statement(&mut self) -> Result<Statement, SyntaxError> {
         let ebnf =
             "Statement := Assignment | WhileLoop | IfStatement | ReturnStatement | (Expression ;)";
 
-        ebnf_or!(
-            ebnf,
-            self,
-            fn || self.syntax_assignment(),
-            fn || self.syntax_while_loop(),
-            fn || self.syntax_if_statement(),
-            fn || self.syntax_return_statement(),
-            fn || {
-                ebnf_run!(self, fn || self.syntax_expression());
-                ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ";", ebnf));
-                return Ok(());
-            }
-        );
+        ebnf_rule!(self, ebnf, {
+            let start = self.token_index;
+            let mut statement = None;
+
+            ebnf_or!(
+                ebnf,
+                self,
+                fn || self.syntax_assignment().map(|s| statement = Some(s)),
+                fn || self.syntax_while_loop().map(|s| statement = Some(s)),
+                fn || self.syntax_if_statement().map(|s| statement = Some(s)),
+                fn || self.syntax_return_statement().map(|s| statement = Some(s)),
+                fn || {
+                    let mut expression = None;
+                    ebnf_run!(self, fn || self.syntax_expression().map(|e| expression = Some(e)));
+                    ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ";", ebnf));
+                    statement = Some(Statement {
+                        kind: StatementKind::Expression(expression.unwrap()),
+                        span: self.span_from(start),
+                    });
+                    Ok(())
+                }
+            );
 
-        return Ok(());
+            return Ok(statement.unwrap());
+        })
     }
 
-    fn syntax_parameter(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_parameter(&mut self) -> Result<Parameter, SyntaxError> {
         let ebnf = "Parameter := DataType Identifier";
 
-        ebnf_run!(self, fn || self.syntax_data_type());
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Identifier, "", ebnf));
+        ebnf_rule!(self, ebnf, {
+            let start = self.token_index;
+            let mut data_type = None;
+            let mut name = None;
 
-        return Ok(());
+            ebnf_run!(self, fn || self.syntax_data_type().map(|d| data_type = Some(d)));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Identifier, "", ebnf).map(|t| name = Some(t)));
+
+            return Ok(Parameter {
+                data_type: data_type.unwrap(),
+                name: name.unwrap(),
+                span: self.span_from(start),
+            });
+        })
     }
 
-    fn syntax_integer_type(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_integer_type(&mut self) -> Result<DataType, SyntaxError> {
         let ebnf = "IntegerType := [unsigned] ( char | short | int | long )";
 
-        ebnf_optional!(
-            self,
-            fn || self.terminal_token(TokenType::Keyword, "unsigned", ebnf)
-        );
-        ebnf_or!(
-            ebnf,
-            self,
-            fn || self.terminal_token(TokenType::Keyword, "char", ebnf),
-            fn || self.terminal_token(TokenType::Keyword, "short", ebnf),
-            fn || self.terminal_token(TokenType::Keyword, "int", ebnf),
-            fn || self.terminal_token(TokenType::Keyword, "long", ebnf)
-        );
-
-        return Ok(());
+        ebnf_rule!(self, ebnf, {
+            let mut unsigned = false;
+            let mut keyword = None;
+
+            ebnf_optional!(
+                self,
+                fn || self.terminal_token(TokenType::Keyword, "unsigned", ebnf).map(|_| unsigned = true)
+            );
+            ebnf_or!(
+                ebnf,
+                self,
+                fn || self.terminal_token(TokenType::Keyword, "char", ebnf).map(|t| keyword = Some(t)),
+                fn || self.terminal_token(TokenType::Keyword, "short", ebnf).map(|t| keyword = Some(t)),
+                fn || self.terminal_token(TokenType::Keyword, "int", ebnf).map(|t| keyword = Some(t)),
+                fn || self.terminal_token(TokenType::Keyword, "long", ebnf).map(|t| keyword = Some(t))
+            );
+
+            return Ok(DataType::from_keywords(unsigned, &keyword.unwrap().text).unwrap());
+        })
     }
 
-    fn syntax_float_type(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_float_type(&mut self) -> Result<DataType, SyntaxError> {
         let ebnf = "FloatType := float | double";
 
-        ebnf_or!(
-            ebnf,
-            self,
-            fn || self.terminal_token(TokenType::Keyword, "float", ebnf),
-            fn || self.terminal_token(TokenType::Keyword, "double", ebnf)
-        );
+        ebnf_rule!(self, ebnf, {
+            let mut data_type = None;
 
-        return Ok(());
+            ebnf_or!(
+                ebnf,
+                self,
+                fn || self.terminal_token(TokenType::Keyword, "float", ebnf).map(|_| data_type = Some(DataType::Float)),
+                fn || self.terminal_token(TokenType::Keyword, "double", ebnf).map(|_| data_type = Some(DataType::Double))
+            );
+
+            return Ok(data_type.unwrap());
+        })
     }
 
-    fn syntax_assignment(&mut self) -> Result<(), SyntaxError> {
+    // a = b = e; is the expression statement (a = (b = e));
+    fn syntax_assignment(&mut self) -> Result<Statement, SyntaxError> {
         let ebnf = "Assignment := Identifier = {Identifier =} Expression ;";
 
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Identifier, "", ebnf));
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "=", ebnf));
-        ebnf_repetition!(
-            self,
-            fn || self.terminal_token(TokenType::Identifier, "", ebnf),
-            fn || self.terminal_token(TokenType::Operator, "=", ebnf)
-        );
-        ebnf_run!(self, fn || self.syntax_expression());
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ";", ebnf));
-
-        return Ok(());
+        ebnf_rule!(self, ebnf, {
+            let start = self.token_index;
+            let mut targets = Vec::new();
+            let mut value = None;
+
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Identifier, "", ebnf).map(|t| targets.push(t)));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "=", ebnf));
+            ebnf_repetition!(
+                self,
+                fn || {
+                    let target = self.terminal_token(TokenType::Identifier, "", ebnf)?;
+                    self.terminal_token(TokenType::Operator, "=", ebnf)?;
+                    targets.push(target);
+                    Ok(())
+                }
+            );
+            ebnf_run!(self, fn || self.syntax_expression().map(|e| value = Some(e)));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ";", ebnf));
+
+            let mut expression = value.unwrap();
+            let end = expression.span;
+            for target in targets.into_iter().rev() {
+                let target_span = Span::of_token(&target);
+                expression = Expression {
+                    kind: ExpressionKind::Assign(target, Arc::new(expression)),
+                    span: Span {
+                        end_line: end.end_line,
+                        end_char: end.end_char,
+                        ..target_span
+                    },
+                    ty: None,
+                };
+            }
+            return Ok(Statement {
+                kind: StatementKind::Expression(expression),
+                span: self.span_from(start),
+            });
+        })
     }
 
-    fn syntax_while_loop(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_while_loop(&mut self) -> Result<Statement, SyntaxError> {
         let ebnf = "WhileLoop := while ( Expression ) Block";
 
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Keyword, "while", ebnf));
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "(", ebnf));
-        ebnf_run!(self, fn || self.syntax_expression());
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ")", ebnf));
-        ebnf_run!(self, fn || self.syntax_block());
-
-        return Ok(());
+        ebnf_rule!(self, ebnf, {
+            let start = self.token_index;
+            let mut condition = None;
+            let mut block = None;
+
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Keyword, "while", ebnf));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "(", ebnf));
+            ebnf_run!(self, fn || self.syntax_expression().map(|e| condition = Some(e)));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ")", ebnf));
+            ebnf_run!(self, fn || self.syntax_block().map(|b| block = Some(b)));
+
+            return Ok(Statement {
+                kind: StatementKind::While(condition.unwrap(), Arc::new(block.unwrap())),
+                span: self.span_from(start),
+            });
+        })
     }
 
-    fn syntax_if_statement(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_if_statement(&mut self) -> Result<Statement, SyntaxError> {
         let ebnf = "IfStatement := if ( Expression ) Block";
 
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Keyword, "if", ebnf));
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "(", ebnf));
-        ebnf_run!(self, fn || self.syntax_expression());
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ")", ebnf));
-        ebnf_run!(self, fn || self.syntax_block());
-
-        return Ok(());
+        ebnf_rule!(self, ebnf, {
+            let start = self.token_index;
+            let mut condition = None;
+            let mut block = None;
+
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Keyword, "if", ebnf));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "(", ebnf));
+            ebnf_run!(self, fn || self.syntax_expression().map(|e| condition = Some(e)));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ")", ebnf));
+            ebnf_run!(self, fn || self.syntax_block().map(|b| block = Some(b)));
+
+            return Ok(Statement {
+                kind: StatementKind::If(condition.unwrap(), Arc::new(block.unwrap())),
+                span: self.span_from(start),
+            });
+        })
     }
 
-    fn syntax_return_statement(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_return_statement(&mut self) -> Result<Statement, SyntaxError> {
         let ebnf = "ReturnStatement := return Expression ;";
 
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Keyword, "return", ebnf));
-        ebnf_run!(self, fn || self.syntax_expression());
-        ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ";", ebnf));
-
-        return Ok(());
-    }
+        ebnf_rule!(self, ebnf, {
+            let start = self.token_index;
+            let mut value = None;
 
-    fn syntax_expression(&mut self) -> Result<(), SyntaxError> {
-        let ebnf = "Expression := SimpleExpression [ RelationOperator SimpleExpression ]";
-
-        ebnf_run!(self, fn || self.syntax_simple_expression());
-        ebnf_optional!(
-            self,
-            fn || self.syntax_relation_operator(),
-            fn || self.syntax_simple_expression()
-        );
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Keyword, "return", ebnf));
+            ebnf_run!(self, fn || self.syntax_expression().map(|e| value = Some(e)));
+            ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ";", ebnf));
 
-        return Ok(());
+            return Ok(Statement {
+                kind: StatementKind::Return(value.unwrap()),
+                span: self.span_from(start),
+            });
+        })
     }
 
-    fn syntax_simple_expression(&mut self) -> Result<(), SyntaxError> {
-        let ebnf = "SimpleExpression := Term { AddOperator Term }";
-
-        ebnf_run!(self, fn || self.syntax_term());
-        ebnf_repetition!(
-            self,
-            fn || self.syntax_add_operator(),
-            fn || self.syntax_term()
-        );
+    fn syntax_expression(&mut self) -> Result<Expression, SyntaxError> {
+        let ebnf = "Expression := { PrefixOperator } Factor { BinaryOperator { PrefixOperator } Factor }";
 
-        return Ok(());
+        ebnf_rule!(self, ebnf, {
+            return self.climb_expression(0);
+        })
     }
 
-    fn syntax_term(&mut self) -> Result<(), SyntaxError> {
-        let ebnf = "Term := Factor { MultOperator Factor }";
+    // precedence climbing over the operator table: an operand followed by
+    // every binary operator that binds at least as tight as min_precedence
+    fn climb_expression(&mut self, min_precedence: u8) -> Result<Expression, SyntaxError> {
+        let start = self.token_index;
+        let mut lhs = match self.syntax_prefix_operator() {
+            Ok((token, operator)) => {
+                let operand = match self.climb_expression(operator.precedence) {
+                    Ok(operand) => operand,
+                    Err(e) => {
+                        self.backtrack(start);
+                        return Err(e);
+                    }
+                };
+                Expression {
+                    kind: ExpressionKind::Unary(token, Arc::new(operand)),
+                    span: self.span_from(start),
+                    ty: None,
+                }
+            }
+            Err(_) => self.syntax_factor()?,
+        };
+
+        // the precedence of the last non-associative operator applied here,
+        // a second one at the same level ends the expression
+        let mut non_associative = None;
+        // the precedence of the last operator applied here; a tighter one
+        // after it was refused by its right operand, e.g. the second < in
+        // x = a < b < c, and ends the expression
+        let mut max_precedence = u8::MAX;
+        loop {
+            let prev_index = self.token_index;
+            let (token, operator) = match self.syntax_binary_operator() {
+                Ok(found) => found,
+                Err(_) => break,
+            };
+            let is_assign = operator.text == "=";
+            if operator.precedence < min_precedence
+                || operator.precedence > max_precedence
+                || non_associative == Some(operator.precedence)
+                || (is_assign && !matches!(lhs.kind, ExpressionKind::Identifier(_)))
+            {
+                self.backtrack(prev_index);
+                break;
+            }
 
-        ebnf_run!(self, fn || self.syntax_factor());
-        ebnf_repetition!(
-            self,
-            fn || self.syntax_mult_operator(),
-            fn || self.syntax_factor()
-        );
+            let next_precedence = match operator.associativity {
+                Associativity::Right => operator.precedence,
+                Associativity::Left | Associativity::NonAssociative => operator.precedence + 1,
+            };
+            let rhs = match self.climb_expression(next_precedence) {
+                Ok(rhs) => rhs,
+                Err(_) => {
+                    // like a failed repetition, keep what was parsed so far
+                    self.backtrack(prev_index);
+                    break;
+                }
+            };
 
-        return Ok(());
+            let span = self.span_from(start);
+            lhs = match lhs.kind {
+                ExpressionKind::Identifier(target) if is_assign => Expression {
+                    kind: ExpressionKind::Assign(target, Arc::new(rhs)),
+                    span,
+                    ty: None,
+                },
+                _ => Expression {
+                    kind: ExpressionKind::Binary(token, Arc::new(lhs), Arc::new(rhs)),
+                    span,
+                    ty: None,
+                },
+            };
+            if operator.associativity == Associativity::NonAssociative {
+                non_associative = Some(operator.precedence);
+            }
+            max_precedence = operator.precedence;
+        }
+        Ok(lhs)
     }
 
-    fn syntax_factor(&mut self) -> Result<(), SyntaxError> {
+    fn syntax_factor(&mut self) -> Result<Expression, SyntaxError> {
         let ebnf = "Factor := ( ( Expression ) ) | Constant | ( Identifier [ ( [ Expression {, Expression} ] ) ] )";
 
-        ebnf_or!(
-            ebnf,
-            self,
-            // ( Expression )
-            fn || {
-                ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "(", ebnf));
-                ebnf_run!(self, fn || self.syntax_expression());
-                ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ")", ebnf));
-                return Ok(());
-            },
-            // Constant
-            fn || self.syntax_constant(),
-            // Identifier [ ( [ Expression {, Expression} ] ) ]
-            fn || {
-                // Identifier
-                ebnf_run!(self, fn || self.terminal_token(TokenType::Identifier, "", ebnf));
-                // [ ( [ Expression {, Expression} ] ) ]
-                ebnf_optional!(
-                    self,
-                    // (
-                    fn || self.terminal_token(TokenType::Operator, "(", ebnf),
-                    // [ Expression {, Expression} ]
-                    fn || {
-                        ebnf_optional!(
-                            self,
-                            // Expression
-                            fn || self.syntax_expression(),
-                            // {, Expression}
-                            fn || {
-                                ebnf_repetition!(
-                                    self,
-                                    // ,
-                                    fn || self.terminal_token(TokenType::Operator, ",", ebnf),
-                                    // Expression
-                                    fn || self.syntax_expression()
-                                );
-                                return Ok(());
-                            }
-                        );
-                        return Ok(());
-                    },
-                    // )
-                    fn || self.terminal_token(TokenType::Operator, ")", ebnf)
-                );
-                return Ok(());
-            }
-        );
+        ebnf_rule!(self, ebnf, {
+            let start = self.token_index;
+            let mut factor = None;
+
+            ebnf_or!(
+                ebnf,
+                self,
+                // ( Expression )
+                fn || {
+                    ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, "(", ebnf));
+                    ebnf_run!(self, fn || self.syntax_expression().map(|e| factor = Some(e)));
+                    ebnf_run!(self, fn || self.terminal_token(TokenType::Operator, ")", ebnf));
+                    Ok(())
+                },
+                // Constant
+                fn || self.syntax_constant().map(|c| factor = Some(c)),
+                // Identifier [ ( [ Expression {, Expression} ] ) ]
+                fn || {
+                    let mut name = None;
+                    let mut arguments = Vec::new();
+                    let mut called = false;
+                    // Identifier
+                    ebnf_run!(self, fn || self.terminal_token(TokenType::Identifier, "", ebnf).map(|t| name = Some(t)));
+                    // [ ( [ Expression {, Expression} ] ) ]
+                    ebnf_optional!(
+                        self,
+                        // (
+                        fn || self.terminal_token(TokenType::Operator, "(", ebnf),
+                        // [ Expression {, Expression} ]
+                        fn || {
+                            ebnf_optional!(
+                                self,
+                                // Expression
+                                fn || self.syntax_expression().map(|e| arguments.push(Arc::new(e))),
+                                // {, Expression}
+                                fn || {
+                                    ebnf_repetition!(
+                                        self,
+                                        // ,
+                                        fn || self.terminal_token(TokenType::Operator, ",", ebnf),
+                                        // Expression
+                                        fn || self.syntax_expression().map(|e| arguments.push(Arc::new(e)))
+                                    );
+                                    Ok(())
+                                }
+                            );
+                            Ok(())
+                        },
+                        // )
+                        fn || self.terminal_token(TokenType::Operator, ")", ebnf).map(|_| called = true)
+                    );
+                    let name = name.unwrap();
+                    factor = Some(Expression {
+                        kind: if called {
+                            ExpressionKind::Call(name, arguments)
+                        } else {
+                            ExpressionKind::Identifier(name)
+                        },
+                        span: self.span_from(start),
+                        ty: None,
+                    });
+                    Ok(())
+                }
+            );
 
-        return Ok(());
+            // a parenthesized expression spans its parentheses
+            let mut factor = factor.unwrap();
+            factor.span = self.span_from(start);
+            return Ok(factor);
+        })
     }
 
-    fn syntax_relation_operator(&mut self) -> Result<(), SyntaxError> {
-        let ebnf = "RelationOperator := ( == ) | < | > | ( <= ) | ( >= ) | ( != )";
-
-        ebnf_or!(
-            ebnf,
-            self,
-            fn || self.terminal_token(TokenType::Operator, "==", ebnf),
-            fn || self.terminal_token(TokenType::Operator, "<", ebnf),
-            fn || self.terminal_token(TokenType::Operator, ">", ebnf),
-            fn || self.terminal_token(TokenType::Operator, "<=", ebnf),
-            fn || self.terminal_token(TokenType::Operator, ">=", ebnf),
-            fn || self.terminal_token(TokenType::Operator, "!=", ebnf)
-        );
+    fn syntax_prefix_operator(&mut self) -> Result<(Token, &'static Operator), SyntaxError> {
+        let ebnf = operators::ebnf(Fixity::Prefix);
 
-        return Ok(());
+        ebnf_rule!(self, ebnf, {
+            return self.table_operator(Fixity::Prefix, ebnf);
+        })
     }
 
-    fn syntax_add_operator(&mut self) -> Result<(), SyntaxError> {
-        let ebnf = "AddOperator := + | -";
-
-        ebnf_or!(
-            ebnf,
-            self,
-            fn || self.terminal_token(TokenType::Operator, "+", ebnf),
-            fn || self.terminal_token(TokenType::Operator, "-", ebnf)
-        );
+    fn syntax_binary_operator(&mut self) -> Result<(Token, &'static Operator), SyntaxError> {
+        let ebnf = operators::ebnf(Fixity::Infix);
 
-        return Ok(());
+        ebnf_rule!(self, ebnf, {
+            return self.table_operator(Fixity::Infix, ebnf);
+        })
     }
 
-    fn syntax_mult_operator(&mut self) -> Result<(), SyntaxError> {
-        let ebnf = "MultOperator := * | /";
-
-        ebnf_or!(
-            ebnf,
-            self,
-            fn || self.terminal_token(TokenType::Operator, "*", ebnf),
-            fn || self.terminal_token(TokenType::Operator, "/", ebnf)
-        );
-
-        return Ok(());
+    // parses the whole token stream, returning the first syntax error
+    pub fn parse(&mut self) -> Result<Program, SyntaxError> {
+        self.token_index = 0;
+        self.memo.clear();
+        self.rules_run = 0;
+        if let Some(trace) = &mut self.trace {
+            trace.clear();
+        }
+        let program = self.syntax_program()?;
+
+        // tokens after the last function definition
+        if self.token_index < self.all_tokens.len() {
+            return syntax_error!(
+                self.all_tokens[self.token_index],
+                "Program := { Declaration } MainDeclaration { FunctionDefinition }"
+            );
+        }
+        Ok(program)
     }
 
     // runs the parser
     pub fn run(&mut self) -> String {
-        match self.syntax_program() {
+        match self.parse() {
             Ok(_) => "Input program is syntactically correct.".to_string(),
             Err(e) => e.to_string(),
         }
     }
 }
 
-pub fn test_run() {
-    let mut parser = Parser::new("example7.x");
-    println!("{}", parser.run());
+
+// a program shape bench parses at growing depths
+struct Shape {
+    name: &'static str,
+    // the main block nested depth deep
+    main_block: fn(usize) -> String,
+    // whether the program is syntactically correct
+    correct: bool,
 }
 
-pub fn test() {
-    assert_eq!(
-        Parser::new("example1.x").run(),
-        "Input program is syntactically correct.".to_string()
-    );
-    assert_eq!(
-        Parser::new("example2.x").run(),
-        "Input program is syntactically correct.".to_string()
-    );
-    assert_eq!( // check error msg
-        Parser::new("example3.x").run(), 
-        "Error at Line 1 Character 0. The syntax should be: MainDeclaration := void main ( ) Block.".to_string()
-    );
-    assert_eq!( // check error msg
-        Parser::new("example4.x").run(), 
-        "Error at Line 1 Character 0. The syntax should be: MainDeclaration := void main ( ) Block.".to_string()
-    );
-    assert_eq!(
-        Parser::new("example5.x").run(), 
-        "Error at Line 2 Character 0. The syntax should be: MainDeclaration := void main ( ) Block.".to_string()
-    );
-    assert_eq!(
-        Parser::new("example6.x").run(), 
-        "Error at Line 1 Character 4. The syntax should be: Block := { {Declaration} {Statement} {FunctionDefinition} }.".to_string()
-    );
-    assert_eq!( // check error msg
-        Parser::new("example7.x").run(), 
-        "Error at Line 1 Character 0. The syntax should be: MainDeclaration := void main ( ) Block.".to_string()
-    );
-    assert_eq!( // check error msg
-        Parser::new("example8.x").run(), 
-        "Error at Line 1 Character 0. The syntax should be: MainDeclaration := void main ( ) Block.".to_string()
-    );
-
-    println!("All tests passed");
+const SHAPES: [Shape; 4] = [
+    Shape {
+        name: "parentheses",
+        main_block: |depth| format!("x = {}1{};", "(".repeat(depth), ")".repeat(depth)),
+        correct: true,
+    },
+    Shape {
+        name: "calls",
+        main_block: |depth| format!("f({}x{});", "f(".repeat(depth), ")".repeat(depth)),
+        correct: true,
+    },
+    Shape {
+        name: "blocks",
+        main_block: |depth| format!("{}{}", "while (x) {".repeat(depth), "}".repeat(depth)),
+        correct: true,
+    },
+    // a missing ) and no ;
+    Shape {
+        name: "unbalanced",
+        main_block: |depth| format!("{}1{}", "(".repeat(depth), ")".repeat(depth - 1)),
+        correct: false,
+    },
+];
+
+const DEPTHS: [usize; 4] = [100, 200, 400, 800];
+
+// the fastest of five parses, to keep the timing steady, with the parser
+// of the last one and whether it succeeded
+fn time_parse(tokens: &[Token]) -> (Parser, bool, f64) {
+    let mut best = f64::MAX;
+    let mut run = || {
+        let mut parser = Parser::from_tokens(tokens.to_vec());
+        let start = Instant::now();
+        let ok = parser.parse().is_ok();
+        best = best.min(start.elapsed().as_secs_f64());
+        (parser, ok)
+    };
+    for _ in 0..4 {
+        run();
+    }
+    let (parser, ok) = run();
+    (parser, ok, best)
 }
 
+// parses programs nested deeper and deeper and checks that the rules run and
+// the time per token stay about the same; returns what did not
+pub fn bench(out: &mut dyn std::io::Write) -> std::io::Result<Vec<String>> {
+    let mut failures = Vec::new();
+    for Shape { name, main_block, correct } in SHAPES {
+        let mut rules_per_token = Vec::new();
+        let mut time_per_token = Vec::new();
+        for depth in DEPTHS {
+            let source = format!("void main() {{\n{}\n}}\n", main_block(depth));
+            let tokens = Scanner::from_source("bench.x", &source).run().all_tokens.clone();
+            let (parser, ok, seconds) = time_parse(&tokens);
+            if ok != correct {
+                failures.push(format!("{} at depth {}: parsed {}", name, depth, if ok { "without an error" } else { "with an error" }));
+            }
+
+            let rules = parser.rules_run as f64 / tokens.len() as f64;
+            let micros = seconds * 1e6 / tokens.len() as f64;
+            writeln!(
+                out,
+                "{:11} depth {:4}: {:5} tokens, {:6} rules run ({:.2} per token), {:8.3}ms ({:.3}us per token)",
+                name,
+                depth,
+                tokens.len(),
+                parser.rules_run,
+                rules,
+                seconds * 1e3,
+                micros
+            )?;
+            rules_per_token.push(rules);
+            time_per_token.push(micros);
+        }
+
+        // linear: the deepest nesting does not cost more per token than the
+        // shallowest, with room for the noise of timing
+        let deepest = DEPTHS[DEPTHS.len() - 1];
+        if rules_per_token[rules_per_token.len() - 1] > rules_per_token[0] * 1.5 {
+            failures.push(format!(
+                "{} at depth {}: {:.2} rules run per token, {:.2} at depth {}",
+                name,
+                deepest,
+                rules_per_token[rules_per_token.len() - 1],
+                rules_per_token[0],
+                DEPTHS[0]
+            ));
+        }
+        if time_per_token[time_per_token.len() - 1] > time_per_token[0] * 3.0 {
+            failures.push(format!(
+                "{} at depth {}: {:.3}us per token, {:.3}us at depth {}",
+                name,
+                deepest,
+                time_per_token[time_per_token.len() - 1],
+                time_per_token[0],
+                DEPTHS[0]
+            ));
+        }
+    }
+    Ok(failures)
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::test_support::parser_of;
 
+    #[test]
+    fn parser() {
+        assert_eq!(
+            Parser::new("example1.x").run(),
+            "Input program is syntactically correct.".to_string()
+        );
+        assert_eq!(
+            Parser::new("example2.x").run(),
+            "Input program is syntactically correct.".to_string()
+        );
+        // expressions follow the precedence and associativity of the operator table
+        let cases = [
+            ("1 + 2 * 3", "(1 + (2 * 3))"),
+            ("a - b - c", "((a - b) - c)"),
+            ("x = y = 1 + 2", "(x = (y = (1 + 2)))"),
+            ("f(x = 1)", "f((x = 1))"),
+            ("-x * y", "((-x) * y)"),
+            ("a || b && c == d", "(a || (b && (c == d)))"),
+            ("a | b ^ c & d % 2", "(a | (b ^ (c & (d % 2))))"),
+            ("f(a >= 1, !b, ~-c)", "f((a >= 1), (!b), (~(-c)))"),
+            ("(1 + 2) * 3", "((1 + 2) * 3)"),
+            (
+                "a < b < c",
+                "Error at Line 1 Character 0. The syntax should be: Block := { {Declaration} {Statement} {FunctionDefinition} }.",
+            ),
+            (
+                "x = a < b < c",
+                "Error at Line 1 Character 0. The syntax should be: Block := { {Declaration} {Statement} {FunctionDefinition} }.",
+            ),
+            (
+                "1 = 2",
+                "Error at Line 1 Character 0. The syntax should be: Block := { {Declaration} {Statement} {FunctionDefinition} }.",
+            ),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse_expression(source), expected, "on {}", source);
+        }
+
+        assert_eq!( // check error msg
+            Parser::new("example3.x").run(), 
+            "Error at Line 1 Character 0. The syntax should be: MainDeclaration := void main ( ) Block.".to_string()
+        );
+        assert_eq!( // check error msg
+            Parser::new("example4.x").run(), 
+            "Error at Line 1 Character 0. The syntax should be: MainDeclaration := void main ( ) Block.".to_string()
+        );
+        assert_eq!(
+            Parser::new("example5.x").run(), 
+            "Error at Line 2 Character 0. The syntax should be: MainDeclaration := void main ( ) Block.".to_string()
+        );
+        assert_eq!(
+            Parser::new("example6.x").run(), 
+            "Error at Line 1 Character 4. The syntax should be: Block := { {Declaration} {Statement} {FunctionDefinition} }.".to_string()
+        );
+        assert_eq!( // check error msg
+            Parser::new("example7.x").run(), 
+            "Error at Line 1 Character 0. The syntax should be: MainDeclaration := void main ( ) Block.".to_string()
+        );
+        assert_eq!( // check error msg
+            Parser::new("example8.x").run(), 
+            "Error at Line 1 Character 0. The syntax should be: MainDeclaration := void main ( ) Block.".to_string()
+        );
+    }
+
+    #[test]
+    fn memo_shares_subtrees() {
+        // f(f(...f(x)...)); the memo holds every call, and a memo hit must not
+        // copy the calls nested in it
+        let depth = 200;
+        let text = format!("void main(){{\n{}x{};\n}}", "f(".repeat(depth), ")".repeat(depth));
+        let mut parser = parser_of(&text);
+        let program = parser.parse().unwrap();
+        let mut expression = match &program.main.block.statements[0].kind {
+            StatementKind::Expression(expression) => expression,
+            other => panic!("not an expression statement: {:?}", other),
+        };
+        let mut calls = 0;
+        while let ExpressionKind::Call(_, arguments) = &expression.kind {
+            assert!(Arc::strong_count(&arguments[0]) > 1, "argument of call {} was copied", calls);
+            expression = &arguments[0];
+            calls += 1;
+        }
+        assert_eq!(calls, depth);
+    }
+
+    // the expression statement of a main with one statement, fully parenthesized
+    fn parse_expression(source: &str) -> String {
+        let text = format!("void main(){{\n{};\n}}", source);
+        let mut parser = parser_of(&text);
+        match parser.parse() {
+            Ok(program) => match &program.main.block.statements[0].kind {
+                StatementKind::Expression(expression) => expression.to_string(),
+                other => panic!("not an expression statement: {:?}", other),
+            },
+            Err(e) => e.to_string(),
+        }
+    }
+}
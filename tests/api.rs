@@ -0,0 +1,30 @@
+// the public API as another crate sees it: every stage through the
+// re-exports at the top of the library
+use proj2zhang::{check_source, CStream, Parser, Scanner, Severity, TokenType, XHTML};
+
+const SOURCE: &str = "int x = 1;\nvoid main(){\nx = x + 1;\n}\n";
+
+#[test]
+fn stages() {
+    let mut stream = CStream::from_source("api.x", SOURCE);
+    assert_eq!(stream.get_next_char(), 'i');
+
+    let tokens = Scanner::from_source("api.x", SOURCE).run().all_tokens.clone();
+    assert_eq!(tokens.len(), 17);
+    assert_eq!((tokens[1].token_type, tokens[1].text.as_str()), (TokenType::Identifier, "x"));
+
+    let program = Parser::from_tokens(tokens).parse().unwrap();
+    assert_eq!(program.declarations.len(), 1);
+    assert_eq!(program.main.block.statements.len(), 1);
+
+    assert!(XHTML::from_source("api.x", SOURCE).render().contains("<font color=\"yellow\">x</font>"));
+}
+
+#[test]
+fn diagnostics() {
+    assert_eq!(check_source("api.x", SOURCE), Vec::new());
+
+    let diagnostics = check_source("api.x", "int x;\n");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!((diagnostics[0].severity, diagnostics[0].code), (Severity::Error, "X0100"));
+}
@@ -0,0 +1,24 @@
+// the exit codes of the binary as CI sees them
+use std::process::Command;
+
+fn exit_code(args: &[&str]) -> i32 {
+    let output = Command::new(env!("CARGO_BIN_EXE_proj2zhang")).args(args).output().unwrap();
+    output.status.code().unwrap()
+}
+
+#[test]
+fn exit_codes() {
+    assert_eq!(exit_code(&["parse", "example1.x", "example2.x"]), 0);
+    assert_eq!(exit_code(&["check", "tests/parser/*.x"]), 0);
+    assert_eq!(exit_code(&["check", "tests/**/*.x"]), 1);
+    assert_eq!(exit_code(&["fmt", "--check", "example1.x"]), 1);
+    assert_eq!(exit_code(&["lex", "no_such_file.x"]), 2);
+    assert_eq!(exit_code(&["check", "--format", "json", "no_such_file.x"]), 2);
+    assert_eq!(exit_code(&["interpret", "--grammar", "x.ebnf", "no_such_file.x"]), 2);
+    assert_eq!(exit_code(&["trace", "no_such_file.x"]), 2);
+    assert_eq!(exit_code(&["coverage", "no_such_file.x"]), 2);
+    assert_eq!(exit_code(&["minimize", "no_such_file.x", "panic"]), 2);
+    assert_eq!(exit_code(&["--format", "json", "example1.x"]), 2);
+    assert_eq!(exit_code(&["check", "--format", "xml", "example1.x"]), 2);
+    assert_eq!(exit_code(&[]), 2);
+}
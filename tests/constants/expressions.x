@@ -0,0 +1,22 @@
+long day = 60 * 60 * 24;
+short seconds = 60 * 60 * 24;
+unsigned int mask = ~0 - 1;
+int big = 2147483647 + 1;
+double half = 1 / 2.0;
+int copy = day;
+int broken = 1 % (3 - 3);
+void main(){
+    long x = day / 7;
+    if (day > 0 || x) {
+        x = x / 0;
+    }
+    while (big < 0 && half > 1) {
+        x = 1;
+    }
+    if (1 < 2) {
+        x = 2;
+    }
+    while (0 && x) {
+        x = 3;
+    }
+}
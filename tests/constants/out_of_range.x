@@ -0,0 +1,7 @@
+// no integer type holds these constants, so they are errors like in C
+unsigned long largest = 18446744073709551615;
+unsigned long beyond = 18446744073709551616;
+void main(){
+long x;
+x = 1 || 99999999999999999999;
+}
@@ -0,0 +1,21 @@
+int sign(int value);
+int clamp(int value);
+void main(){
+    int x = 5;
+    x = sign(x) + clamp(x);
+}
+int sign(int value){
+    if (value < 0) {
+        return -1;
+    }
+    if (value > 0) {
+        return 1;
+    }
+}
+int clamp(int value){
+    while (value > 100) {
+        return 100;
+    }
+    return value;
+    value = 0;
+}
@@ -0,0 +1,16 @@
+int limit = 10;
+void main(){
+    int sum;
+    int i;
+    int last;
+    int unused;
+    i = 0;
+    while (i < limit) {
+        sum = sum + i;
+        last = i;
+        i = i + 1;
+    }
+    if (limit > 0 && (unused = limit) > 1) {
+        limit = last + unused;
+    }
+}
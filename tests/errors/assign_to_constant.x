@@ -0,0 +1,3 @@
+void main(){
+1 = 2;
+}
\ No newline at end of file
@@ -0,0 +1,3 @@
+void main(){
+x = a < b < c;
+}
\ No newline at end of file
@@ -0,0 +1,9 @@
+// the golden-file tests of every tests/**/*.x; BLESS=1 cargo test --test golden
+// writes the current outputs as the new expectations
+#[test]
+fn golden_files() {
+    let bless = std::env::var("BLESS").is_ok_and(|value| value != "0");
+    let mismatches = proj2zhang::golden::run(proj2zhang::golden::TESTS_DIR, bless);
+    let report: Vec<String> = mismatches.iter().map(|mismatch| mismatch.to_string()).collect();
+    assert!(mismatches.is_empty(), "{} golden files differ:\n{}", mismatches.len(), report.join("\n"));
+}
@@ -0,0 +1,8 @@
+// a comment on a line of its own
+int total; // after a declaration
+
+void main(){
+    total = 4/2;// right after a token
+    total = total / 2; // lint: allow(magic-number)
+}
+// at the end of the file
@@ -0,0 +1,3 @@
+void main(){
+x = 1.2.3 + $y;
+}
\ No newline at end of file
@@ -0,0 +1,5 @@
+int naïve;
+
+void main(){
+naïve = 1;
+}
\ No newline at end of file
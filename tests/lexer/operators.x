@@ -0,0 +1,5 @@
+void main(){
+x = a >= b == c <= d != e;
+y = !a && ~b || -c % 2 ^ 3 | 4 & 5;
+z = 1.5 + .5 * 3. / 007;
+}
\ No newline at end of file
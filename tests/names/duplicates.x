@@ -0,0 +1,17 @@
+int x;
+float x;
+int Twice(int a, int a);
+
+void main(){
+    long y;
+    short y;
+}
+
+int Twice(int a, int b){
+    int b;
+    return a + b;
+}
+
+int Twice(int c, int d){
+    return c;
+}
@@ -0,0 +1,17 @@
+int total;
+float scale(int value);
+int twice(int value);
+long missing(long value);
+void main(){
+    total = scale(2, 3) + twice();
+    total = total(1);
+    total = twice;
+    scale = 1.5;
+    total = missing(total) + missing(1);
+}
+int scale(int value){
+    return value * 2;
+}
+int twice(int value){
+    return value + value;
+}
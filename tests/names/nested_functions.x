@@ -0,0 +1,20 @@
+int depth = 3;
+
+int Outer(int n);
+
+void main(){
+    int r;
+    r = Outer(depth);
+}
+
+int Outer(int n){
+    int depth;
+    int Inner(int k);
+
+    depth = Inner(n) + Outer(n - 1);
+    return depth;
+
+    int Inner(int n){
+        return n * depth;
+    }
+}
@@ -0,0 +1,17 @@
+int total;
+
+void main(){
+    int i;
+
+    while (i < 10) {
+        int square;
+        square = i * i;
+        total = total + square;
+    }
+    square = 0;
+    count = Sum(total);
+}
+
+int Sum(int n){
+    return n + total;
+}
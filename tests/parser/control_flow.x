@@ -0,0 +1,12 @@
+void main(){
+int i = 10;
+while (i > 0) {
+if (i % 2 == 0) {
+i = i - 2;
+}
+i = i - 1;
+}
+}
+int half(int n){
+return n / 2;
+}
@@ -0,0 +1,12 @@
+unsigned long total = 0;
+double average(int count, float sum);
+void main(){
+char c;
+short s = 7;
+int square(int n) {
+return n * n;
+}
+}
+double average(int count, float sum) {
+return sum / count;
+}
\ No newline at end of file
@@ -0,0 +1,27 @@
+int x;
+int y;
+int z;
+int a;
+int b;
+int c;
+int d;
+int e;
+int f(int p, int q, int r);
+int g(int p, int q);
+int h();
+
+void main(){
+x = 1 + 2 * 3 - 4;
+y = (1 + 2) * -(3 - 4);
+z = a = b = c || d && !e;
+f(g(1, 2), h(), x >= 1);
+}
+int f(int p, int q, int r){
+return p + q + r;
+}
+int g(int p, int q){
+return p * q;
+}
+int h(){
+return 0;
+}
\ No newline at end of file
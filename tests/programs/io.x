@@ -0,0 +1,22 @@
+int count;
+int sum(int n);
+void main(){
+    int x;
+    double mean;
+    x = read_int();
+    while (x != 0) {
+        count = count + 1;
+        x = print_int(sum(x)) + print_char(10);
+        x = read_int();
+    }
+    mean = read_float() / 4;
+    x = print_float(mean) + print_char(10);
+    x = print_int(count);
+    x = print_char(10);
+}
+int sum(int n) {
+    if (n < 2) {
+        return n;
+    }
+    return n + sum(n - 1);
+}
@@ -0,0 +1,6 @@
+void main(){
+    int x;
+    x = print_int(read_int() * 2);
+    x = print_char(10);
+    x = read_int();
+}
@@ -0,0 +1,16 @@
+float level = 1.5;
+void main(){
+    int n = 3;
+    if (n = 4) {
+        n = n - 1;
+    }
+    while (level) {
+        level = level - 0.5;
+    }
+    if (level == 0.0 || !(n < 2)) {
+        n = 0;
+    }
+    if (n != 3 && level < 1.0) {
+        return n;
+    }
+}
@@ -0,0 +1,17 @@
+char small = 300;
+unsigned int count = 7;
+double ratio = 0.5;
+int half(double value);
+void main(){
+    short s = 2;
+    long big;
+    big = s * 1000;
+    count = -1;
+    s = big;
+    big = half(ratio) + s;
+    ratio = half(big % 3);
+    big = ratio % 2;
+}
+int half(double value){
+    return value / 2;
+}
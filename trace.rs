@@ -0,0 +1,275 @@
+//Parse tracing for stage3::Parser: every rule entry and exit with its token
+//range and outcome, and every backtrack, as an indented text tree or as JSON.
+//In step mode the parser stops at each rule entry and shows the rule stack
+//and the upcoming tokens.
+
+use std::io::{self, BufRead, Write};
+
+use crate::diagnostics::json_string;
+use crate::stage2_token::Token;
+
+// how many upcoming tokens step mode shows
+const STEP_TOKENS: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceEvent {
+    Enter {
+        rule: &'static str,
+        index: usize,
+    },
+    // end is the token index after the rule, start again if it failed;
+    // cached means the result came from the memo table
+    Exit {
+        rule: &'static str,
+        start: usize,
+        end: usize,
+        error: Option<String>,
+        cached: bool,
+    },
+    // the parser gave up on tokens from..to and continues at to
+    Backtrack {
+        from: usize,
+        to: usize,
+    },
+}
+
+// one rule call with what happened inside it
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceNode {
+    pub rule: &'static str,
+    pub start: usize,
+    pub end: usize,
+    pub error: Option<String>,
+    pub cached: bool,
+    pub children: Vec<TraceItem>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceItem {
+    Rule(TraceNode),
+    Backtrack { from: usize, to: usize },
+}
+
+#[derive(Debug, Default)]
+pub struct Trace {
+    pub events: Vec<TraceEvent>,
+    // the rules being parsed, innermost last, with their start index
+    pub stack: Vec<(&'static str, usize)>,
+    // stop at every rule entry and wait for the user
+    pub step: bool,
+}
+
+// the rule name of an ebnf string: "Block" for "Block := ..."
+pub fn rule_name(ebnf: &'static str) -> &'static str {
+    ebnf.split(" :=").next().unwrap()
+}
+
+impl Trace {
+    pub fn new(step: bool) -> Trace {
+        Trace {
+            step,
+            ..Trace::default()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.stack.clear();
+    }
+
+    pub fn enter(&mut self, rule: &'static str, index: usize) {
+        self.events.push(TraceEvent::Enter { rule, index });
+        self.stack.push((rule, index));
+    }
+
+    pub fn exit(&mut self, rule: &'static str, start: usize, end: usize, error: Option<String>, cached: bool) {
+        self.stack.pop();
+        self.events.push(TraceEvent::Exit {
+            rule,
+            start,
+            end,
+            error,
+            cached,
+        });
+    }
+
+    pub fn backtrack(&mut self, from: usize, to: usize) {
+        self.events.push(TraceEvent::Backtrack { from, to });
+    }
+
+    // step mode: shows where the parser is and waits for a command;
+    // an empty line steps, c runs to the end
+    pub fn pause(&mut self, upcoming: &[Token]) {
+        let stack: Vec<&str> = self.stack.iter().map(|(rule, _)| *rule).collect();
+        let tokens: Vec<&str> = upcoming.iter().take(STEP_TOKENS).map(|t| t.text.as_str()).collect();
+        println!("rule stack:  {}", stack.join(" > "));
+        println!("next tokens: {}", tokens.join(" "));
+        print!("[enter] step, [c] continue: ");
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line).unwrap_or(0) == 0 || line.trim() == "c" {
+            self.step = false;
+        }
+    }
+
+    // the events as a tree of rule calls
+    pub fn tree(&self) -> Vec<TraceItem> {
+        let mut roots = Vec::new();
+        let mut open: Vec<TraceNode> = Vec::new();
+        for event in self.events.iter() {
+            match event {
+                TraceEvent::Enter { rule, index } => open.push(TraceNode {
+                    rule,
+                    start: *index,
+                    end: *index,
+                    error: None,
+                    cached: false,
+                    children: Vec::new(),
+                }),
+                TraceEvent::Exit {
+                    end, error, cached, ..
+                } => {
+                    let mut node = open.pop().unwrap();
+                    node.end = *end;
+                    node.error = error.clone();
+                    node.cached = *cached;
+                    match open.last_mut() {
+                        Some(parent) => parent.children.push(TraceItem::Rule(node)),
+                        None => roots.push(TraceItem::Rule(node)),
+                    }
+                }
+                TraceEvent::Backtrack { from, to } => {
+                    let item = TraceItem::Backtrack { from: *from, to: *to };
+                    match open.last_mut() {
+                        Some(parent) => parent.children.push(item),
+                        None => roots.push(item),
+                    }
+                }
+            }
+        }
+        roots
+    }
+
+    // one line per rule call, children indented by two spaces, e.g.
+    // Factor [7..8] ok "1"
+    pub fn to_text(&self, tokens: &[Token]) -> String {
+        let mut res = "".to_string();
+        for item in self.tree().iter() {
+            write_text(item, tokens, 0, &mut res);
+        }
+        res
+    }
+
+    pub fn to_json(&self, tokens: &[Token]) -> String {
+        let items: Vec<String> = self.tree().iter().map(|item| item_json(item, tokens)).collect();
+        format!("[{}]\n", items.join(","))
+    }
+}
+
+// the text of the tokens from..to, shortened
+fn token_text(tokens: &[Token], from: usize, to: usize) -> String {
+    let texts: Vec<&str> = tokens[from.min(tokens.len())..to.min(tokens.len())]
+        .iter()
+        .map(|t| t.text.as_str())
+        .collect();
+    if texts.len() > STEP_TOKENS {
+        format!("{} ...", texts[..STEP_TOKENS].join(" "))
+    } else {
+        texts.join(" ")
+    }
+}
+
+fn write_text(item: &TraceItem, tokens: &[Token], depth: usize, res: &mut String) {
+    let indent = "  ".repeat(depth);
+    match item {
+        TraceItem::Rule(node) => {
+            let outcome = match &node.error {
+                None => format!("ok \"{}\"", token_text(tokens, node.start, node.end)),
+                Some(error) => format!("failed: {}", error),
+            };
+            let cached = if node.cached { " (cached)" } else { "" };
+            *res += &format!(
+                "{}{} [{}..{}] {}{}\n",
+                indent, node.rule, node.start, node.end, outcome, cached
+            );
+            for child in node.children.iter() {
+                write_text(child, tokens, depth + 1, res);
+            }
+        }
+        TraceItem::Backtrack { from, to } => {
+            *res += &format!(
+                "{}backtrack {} -> {}, dropping \"{}\"\n",
+                indent,
+                from,
+                to,
+                token_text(tokens, *to, *from)
+            );
+        }
+    }
+}
+
+fn item_json(item: &TraceItem, tokens: &[Token]) -> String {
+    match item {
+        TraceItem::Rule(node) => {
+            let children: Vec<String> = node.children.iter().map(|child| item_json(child, tokens)).collect();
+            format!(
+                "{{\"rule\":{},\"start\":{},\"end\":{},\"text\":{},\"ok\":{},\"error\":{},\"cached\":{},\"children\":[{}]}}",
+                json_string(node.rule),
+                node.start,
+                node.end,
+                json_string(&token_text(tokens, node.start, node.end)),
+                node.error.is_none(),
+                match &node.error {
+                    Some(error) => json_string(error),
+                    None => "null".to_string(),
+                },
+                node.cached,
+                children.join(",")
+            )
+        }
+        TraceItem::Backtrack { from, to } => format!("{{\"backtrack\":{{\"from\":{},\"to\":{}}}}}", from, to),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_trace() {
+        use crate::diagnostics::test_support::parser_of;
+
+        assert_eq!(rule_name("Block := { {Declaration} }"), "Block");
+
+        let source = "void main(){\nx = 1;\n}";
+        let mut parser = parser_of(source);
+        parser.trace = Some(Trace::new(false));
+        assert!(parser.parse().is_ok());
+        let trace = parser.trace.as_ref().unwrap();
+
+        // every entry has its exit and the stack is empty again
+        let enters = trace.events.iter().filter(|e| matches!(e, TraceEvent::Enter { .. })).count();
+        let exits = trace.events.iter().filter(|e| matches!(e, TraceEvent::Exit { .. })).count();
+        assert_eq!(enters, exits);
+        assert!(trace.stack.is_empty());
+
+        let text = trace.to_text(&parser.all_tokens);
+        assert!(text.starts_with("Program [0..10] ok \"void main ( ) { x = 1 ...\"\n"));
+        // x = 1 ; is an Assignment, so Statement never needs its last alternative
+        assert!(text.contains("\n        Assignment [5..9] ok \"x = 1 ;\"\n"));
+        // Block first tries a Declaration at x
+        assert!(text.contains("      Declaration [5..5] failed: Error at Line 1 Character 0."));
+
+        let json = trace.to_json(&parser.all_tokens);
+        assert!(json.starts_with("[{\"rule\":\"Program\",\"start\":0,\"end\":10,"));
+        assert!(json.contains("\"ok\":false,\"error\":\"Error at Line 1 Character 0."));
+
+        // a failed Factor after an operator is a backtrack
+        let mut parser = parser_of("void main(){\nx = 1 + ;\n}");
+        parser.trace = Some(Trace::new(false));
+        assert!(parser.parse().is_err());
+        let text = parser.trace.as_ref().unwrap().to_text(&parser.all_tokens);
+        assert!(text.contains("backtrack 9 -> 8, dropping \"+\""));
+    }
+}
@@ -0,0 +1,445 @@
+//Type checking: the type of every expression, stored in its ty field. The
+//operands of a binary operator go through C's usual arithmetic conversions
+//(char and short are promoted to int, then the operand of lower rank is
+//converted to the other one's type), and a value that is converted
+//implicitly, by an assignment, an initializer, an argument or a return,
+//is checked for losing information on the way. Conditions of if and while
+//are checked for the mistakes C compilers warn about.
+
+use std::sync::Arc;
+
+use crate::ast::{Block, DataType, Declaration, Expression, ExpressionKind, FunctionDefinition, Program, StatementKind};
+use crate::consteval;
+use crate::diagnostics::{Diagnostic, Related, Severity, Span};
+use crate::resolve::{SymbolKind, SymbolTable};
+use crate::stage2_token::{Token, TokenType};
+
+// char and short (signed or not) become int in arithmetic
+pub fn promote(data_type: DataType) -> DataType {
+    match data_type {
+        DataType::Char | DataType::UnsignedChar | DataType::Short | DataType::UnsignedShort => DataType::Int,
+        data_type => data_type,
+    }
+}
+
+// the common type of the operands of an arithmetic operator
+pub fn usual_arithmetic_conversions(lhs: DataType, rhs: DataType) -> DataType {
+    if lhs == DataType::Double || rhs == DataType::Double {
+        return DataType::Double;
+    }
+    if lhs == DataType::Float || rhs == DataType::Float {
+        return DataType::Float;
+    }
+    let (lhs, rhs) = (promote(lhs), promote(rhs));
+    if lhs == rhs {
+        return lhs;
+    }
+    if lhs.is_unsigned() == rhs.is_unsigned() {
+        return if lhs.bits() >= rhs.bits() { lhs } else { rhs };
+    }
+    let (unsigned, signed) = if lhs.is_unsigned() { (lhs, rhs) } else { (rhs, lhs) };
+    // a wider signed type holds every value of the unsigned one; int and
+    // long differ in size, so no signed type of the same size is left over
+    if signed.bits() > unsigned.bits() {
+        signed
+    } else {
+        unsigned
+    }
+}
+
+// the smallest and largest value of an integer type
+fn range(data_type: DataType) -> (f64, f64) {
+    let bits = data_type.bits() as i32;
+    if data_type.is_unsigned() {
+        (0.0, 2f64.powi(bits) - 1.0)
+    } else {
+        (-(2f64.powi(bits - 1)), 2f64.powi(bits - 1) - 1.0)
+    }
+}
+
+// the number of bits of precision of a value of the type
+fn precision(data_type: DataType) -> u32 {
+    match data_type {
+        DataType::Float => 24,
+        DataType::Double => 53,
+        data_type if data_type.is_unsigned() => data_type.bits(),
+        data_type => data_type.bits() - 1,
+    }
+}
+
+// whether converting any value of one type to another may change it
+pub fn is_lossy(from: DataType, to: DataType) -> bool {
+    match (from.is_floating(), to.is_floating()) {
+        (true, false) => true,
+        (true, true) => from.bits() > to.bits(),
+        (false, true) => precision(from) > precision(to),
+        (false, false) => {
+            let ((from_min, from_max), (to_min, to_max)) = (range(from), range(to));
+            from_min < to_min || from_max > to_max
+        }
+    }
+}
+
+// whether converting a constant value to a type changes it
+fn changes_constant(value: f64, to: DataType) -> bool {
+    match to {
+        DataType::Float => value.abs() > f32::MAX as f64,
+        DataType::Double => false,
+        to => {
+            let (min, max) = range(to);
+            value.fract() != 0.0 || value < min || value > max
+        }
+    }
+}
+
+// the type of a constant: int if it fits, then long, then unsigned long;
+// floating constants are double, like in C
+pub fn constant_type(token: &Token) -> DataType {
+    if token.token_type == TokenType::FloatConstant {
+        return DataType::Double;
+    }
+    match token.text.parse::<u64>() {
+        Ok(value) if value <= i32::MAX as u64 => DataType::Int,
+        Ok(value) if value <= i64::MAX as u64 => DataType::Long,
+        _ => DataType::UnsignedLong,
+    }
+}
+
+struct Checker<'a> {
+    xfilename: &'a str,
+    table: &'a SymbolTable,
+    // the return type and the name of the functions being checked,
+    // innermost last; None for main
+    functions: Vec<Option<(DataType, Token)>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    fn report(&mut self, severity: Severity, code: &'static str, message: String, span: Span, related: Vec<Related>) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            code,
+            message,
+            file: self.xfilename.to_string(),
+            span,
+            related,
+        });
+    }
+
+    // the implicit conversion of a value to a type; what names the
+    // conversion, like "the assignment to 'x'"
+    fn convert(&mut self, value: &Expression, to: DataType, what: String, related: Vec<Related>) {
+        let from = match value.ty {
+            Some(from) => from,
+            None => return,
+        };
+        let message = match consteval::value(value) {
+            Some(constant) if changes_constant(constant.as_f64(), to) => {
+                format!("{} converts the constant {} to {}, which changes its value.", what, constant, to)
+            }
+            Some(_) => return,
+            None if is_lossy(from, to) => format!("{} converts {} to {}, which may lose information.", what, from, to),
+            None => return,
+        };
+        let message = capitalize(&message);
+        self.report(Severity::Warning, "X0300", message, value.span, related);
+    }
+
+    // an operator that needs integer operands
+    fn integer_operand(&mut self, operator: &Token, operand: &Expression, side: &str) {
+        if let Some(data_type) = operand.ty.filter(|data_type| data_type.is_floating()) {
+            let message = format!("The operator '{}' needs an integer operand, but {} is {}.", operator.text, side, data_type);
+            self.report(Severity::Error, "X0301", message, operand.span, Vec::new());
+        }
+    }
+
+    fn expression(&mut self, expression: &mut Expression) -> Option<DataType> {
+        let ty = match &mut expression.kind {
+            ExpressionKind::Constant(token) => Some(constant_type(token)),
+            ExpressionKind::Identifier(name) => {
+                // a function used as a value has no type
+                self.table.symbol_of(name).filter(|symbol| !symbol.kind.is_function()).map(|symbol| symbol.data_type)
+            }
+            ExpressionKind::Call(name, arguments) => {
+                for argument in arguments.iter_mut() {
+                    self.expression(Arc::make_mut(argument));
+                }
+                match self.table.symbol_of(name).filter(|symbol| symbol.kind.is_function()) {
+                    Some(symbol) => {
+                        for (i, (argument, &parameter)) in arguments.iter().zip(symbol.parameters.iter()).enumerate() {
+                            // an intrinsic is not declared anywhere in the source
+                            let related = match symbol.kind {
+                                SymbolKind::Intrinsic => Vec::new(),
+                                _ => vec![Related {
+                                    span: symbol.span,
+                                    message: format!("parameter {} of '{}' is declared as {} here", i + 1, name.text, parameter),
+                                }],
+                            };
+                            self.convert(argument, parameter, format!("argument {} of '{}'", i + 1, name.text), related);
+                        }
+                        Some(symbol.data_type)
+                    }
+                    None => None,
+                }
+            }
+            ExpressionKind::Unary(operator, operand) => {
+                let operand_type = self.expression(Arc::make_mut(operand));
+                match operator.text.as_str() {
+                    "!" => Some(DataType::Int),
+                    "~" => {
+                        self.integer_operand(operator, operand, "the operand");
+                        operand_type.map(promote)
+                    }
+                    _ => operand_type.map(promote),
+                }
+            }
+            ExpressionKind::Binary(operator, lhs, rhs) => {
+                let (lhs_type, rhs_type) = (self.expression(Arc::make_mut(lhs)), self.expression(Arc::make_mut(rhs)));
+                match operator.text.as_str() {
+                    "==" | "!=" | "<" | ">" | "<=" | ">=" | "&&" | "||" => Some(DataType::Int),
+                    text => {
+                        if matches!(text, "%" | "&" | "|" | "^") {
+                            self.integer_operand(operator, lhs, "the left operand");
+                            self.integer_operand(operator, rhs, "the right operand");
+                        }
+                        match (lhs_type, rhs_type) {
+                            (Some(lhs), Some(rhs)) => Some(usual_arithmetic_conversions(lhs, rhs)),
+                            _ => None,
+                        }
+                    }
+                }
+            }
+            ExpressionKind::Assign(target, value) => {
+                self.expression(Arc::make_mut(value));
+                match self.table.symbol_of(target).filter(|symbol| !symbol.kind.is_function()) {
+                    Some(symbol) => {
+                        let related = vec![Related {
+                            span: symbol.span,
+                            message: format!("'{}' is declared as {} here", target.text, symbol.data_type),
+                        }];
+                        self.convert(value, symbol.data_type, format!("the assignment to '{}'", target.text), related);
+                        Some(symbol.data_type)
+                    }
+                    None => None,
+                }
+            }
+        };
+        expression.ty = ty;
+        ty
+    }
+
+    // an if or while condition: any arithmetic value is one, but these
+    // usually are not what was meant
+    fn condition(&mut self, condition: &Expression) {
+        let message = match &condition.kind {
+            ExpressionKind::Assign(target, _) => {
+                format!("The condition assigns to '{}'; use == to compare.", target.text)
+            }
+            ExpressionKind::Binary(operator, lhs, rhs) if operator.text == "&&" || operator.text == "||" => {
+                self.condition(lhs);
+                self.condition(rhs);
+                return;
+            }
+            ExpressionKind::Unary(operator, operand) if operator.text == "!" => {
+                self.condition(operand);
+                return;
+            }
+            ExpressionKind::Binary(operator, lhs, rhs)
+                if (operator.text == "==" || operator.text == "!=")
+                    && [lhs, rhs].iter().any(|operand| operand.ty.is_some_and(|ty| ty.is_floating())) =>
+            {
+                format!("The condition compares floating-point values with '{}', which is exact.", operator.text)
+            }
+            _ => match condition.ty {
+                Some(ty) if ty.is_floating() => {
+                    format!("The condition is of type {}, which is true unless it is exactly zero.", ty)
+                }
+                _ => return,
+            },
+        };
+        self.report(Severity::Warning, "X0303", message, condition.span, Vec::new());
+    }
+
+    fn declaration(&mut self, declaration: &mut Declaration) {
+        if let Declaration::Variable(variable) = declaration {
+            if let Some(initializer) = &mut variable.initializer {
+                self.expression(initializer);
+                let what = format!("the initializer of '{}'", variable.name.text);
+                self.convert(initializer, variable.data_type, what, Vec::new());
+            }
+        }
+    }
+
+    fn function(&mut self, function: &mut FunctionDefinition) {
+        self.functions.push(Some((function.return_type, function.name.clone())));
+        self.block(Arc::make_mut(&mut function.block));
+        self.functions.pop();
+    }
+
+    fn block(&mut self, block: &mut Block) {
+        for declaration in block.declarations.iter_mut() {
+            self.declaration(declaration);
+        }
+        for statement in block.statements.iter_mut() {
+            match &mut statement.kind {
+                StatementKind::Expression(expression) => {
+                    self.expression(expression);
+                }
+                StatementKind::While(condition, body) | StatementKind::If(condition, body) => {
+                    self.expression(condition);
+                    self.condition(condition);
+                    self.block(Arc::make_mut(body));
+                }
+                StatementKind::Return(value) => {
+                    self.expression(value);
+                    match self.functions.last().cloned().flatten() {
+                        Some((return_type, name)) => {
+                            let related = vec![Related {
+                                span: Span::of_token(&name),
+                                message: format!("'{}' is declared to return {} here", name.text, return_type),
+                            }];
+                            self.convert(value, return_type, format!("the return value of '{}'", name.text), related);
+                        }
+                        None => {
+                            let message = "'main' returns void, so it cannot return a value.".to_string();
+                            self.report(Severity::Error, "X0302", message, value.span, Vec::new());
+                        }
+                    }
+                }
+            }
+        }
+        for function in block.functions.iter_mut() {
+            self.function(function);
+        }
+    }
+
+    fn program(&mut self, program: &mut Program) {
+        for declaration in program.declarations.iter_mut() {
+            self.declaration(declaration);
+        }
+        self.functions.push(None);
+        self.block(&mut program.main.block);
+        self.functions.pop();
+        for function in program.functions.iter_mut() {
+            self.function(function);
+        }
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// gives every expression of a resolved program its type and returns the
+// diagnostics of type checking, sorted by position
+pub fn check_types(xfilename: &str, program: &mut Program, table: &SymbolTable) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        xfilename,
+        table,
+        functions: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    checker.program(program);
+    let mut diagnostics = checker.diagnostics;
+    diagnostics.sort_by_key(|d| (d.span.start_line, d.span.start_char));
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::test_support::{codes, parse};
+    use crate::resolve::resolve;
+
+    fn check_source(source: &str) -> (Program, Vec<Diagnostic>) {
+        let mut program = parse(source);
+        let (table, _) = resolve("test.x", &program);
+        let diagnostics = check_types("test.x", &mut program, &table);
+        (program, diagnostics)
+    }
+
+    #[test]
+    fn conversions() {
+        use DataType::*;
+
+        assert_eq!(usual_arithmetic_conversions(Char, Short), Int);
+        assert_eq!(usual_arithmetic_conversions(Int, UnsignedInt), UnsignedInt);
+        assert_eq!(usual_arithmetic_conversions(Long, UnsignedInt), Long);
+        assert_eq!(usual_arithmetic_conversions(Long, UnsignedLong), UnsignedLong);
+        assert_eq!(usual_arithmetic_conversions(UnsignedLong, Float), Float);
+        assert_eq!(usual_arithmetic_conversions(Float, Double), Double);
+
+        assert!(is_lossy(Float, Int));
+        assert!(is_lossy(Double, Float));
+        assert!(is_lossy(Int, Float));
+        assert!(is_lossy(Int, UnsignedInt));
+        assert!(is_lossy(Long, Short));
+        assert!(!is_lossy(Short, Float));
+        assert!(!is_lossy(UnsignedInt, Long));
+        assert!(!is_lossy(Int, Double));
+    }
+
+    #[test]
+    fn examples() {
+        let (program, diagnostics) = check_source(&std::fs::read_to_string("example1.x").unwrap());
+        assert_eq!(diagnostics, Vec::new());
+        match &program.main.block.statements[0].kind {
+            StatementKind::Expression(expression) => assert_eq!(expression.ty, Some(DataType::Float)),
+            _ => panic!("expected an expression statement"),
+        }
+
+        // Bar passes a long to an int parameter and stores float sums in
+        // an int; BarFoo returns an int as a short
+        let (_, diagnostics) = check_source(&std::fs::read_to_string("example2.x").unwrap());
+        assert_eq!(
+            codes(&diagnostics),
+            [("X0300", 28, 20), ("X0300", 28, 24), ("X0300", 31, 13), ("X0300", 31, 17), ("X0300", 36, 15)]
+        );
+        assert_eq!(
+            diagnostics[2].to_string(),
+            "test.x:31:13-31:29: warning[X0300]: The assignment to 'R1' converts float to int, which may lose information.\ntest.x:22:8-22:10: note: 'R1' is declared as int here"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let source = "int x = 1.5;\nchar c = 300;\nunsigned int u = 7;\nfloat f = 1.4;\nvoid main(){\nu = -1;\nx = f % 2;\nx = ~f;\nif (x = 1) {\n}\nwhile (f) {\n}\nif (f == 1.0 && x < 2) {\n}\nreturn x;\n}\n";
+        let (program, diagnostics) = check_source(source);
+        assert_eq!(
+            codes(&diagnostics),
+            [
+                ("X0300", 0, 8),
+                ("X0300", 1, 9),
+                ("X0300", 5, 4),
+                ("X0301", 6, 4),
+                ("X0300", 6, 4),
+                ("X0300", 7, 4),
+                ("X0301", 7, 5),
+                ("X0303", 8, 4),
+                ("X0303", 10, 7),
+                ("X0303", 12, 4),
+                ("X0302", 14, 7),
+            ]
+        );
+        assert_eq!(diagnostics[0].message, "The initializer of 'x' converts the constant 1.5 to int, which changes its value.");
+        assert_eq!(diagnostics[2].message, "The assignment to 'u' converts the constant -1 to unsigned int, which changes its value.");
+
+        // an intrinsic has no declaration to point to
+        let (_, diagnostics) = check_source("void main(){\ndouble d;\nd = 2.5;\nprint_int(d);\n}\n");
+        assert_eq!(codes(&diagnostics), [("X0300", 3, 10)]);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "test.x:3:10-3:11: warning[X0300]: Argument 1 of 'print_int' converts double to long, which may lose information."
+        );
+
+        // the types stay known past the errors
+        match &program.main.block.statements[1].kind {
+            StatementKind::Expression(expression) => assert_eq!(expression.ty, Some(DataType::Int)),
+            _ => panic!("expected an expression statement"),
+        }
+    }
+}
@@ -0,0 +1,1351 @@
+// @generated by codegen.rs from the X grammar, do not edit by hand.
+
+#![allow(dead_code, unused_labels, unused_mut, unused_parens, unused_variables)]
+
+use crate::diagnostics::Span;
+use crate::stage2_token::{Token, TokenType};
+use crate::stage3::SyntaxError;
+
+// Program := { Declaration } MainDeclaration { FunctionDefinition }
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub declarations: Vec<Declaration>,
+    pub main_declaration: Box<MainDeclaration>,
+    pub function_definitions: Vec<FunctionDefinition>,
+}
+
+// Declaration := DeclarationType (VariableDeclaration | FunctionDeclaration)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Declaration {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub declaration_type: Box<DeclarationType>,
+    pub variable_declaration: Option<Box<VariableDeclaration>>,
+    pub function_declaration: Option<Box<FunctionDeclaration>>,
+}
+
+// MainDeclaration := void main ( ) Block
+#[derive(Clone, Debug, PartialEq)]
+pub struct MainDeclaration {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub block: Box<Block>,
+}
+
+// FunctionDefinition := DeclarationType ParameterBlock Block
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionDefinition {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub declaration_type: Box<DeclarationType>,
+    pub parameter_block: Box<ParameterBlock>,
+    pub block: Box<Block>,
+}
+
+// DeclarationType := DataType Identifier
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeclarationType {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub data_type: Box<DataType>,
+    pub identifier: Token,
+}
+
+// VariableDeclaration := [= Expression] ;
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariableDeclaration {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub expression: Option<Box<Expression>>,
+}
+
+// FunctionDeclaration := ParameterBlock ;
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionDeclaration {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub parameter_block: Box<ParameterBlock>,
+}
+
+// Block := { {Declaration} {Statement} {FunctionDefinition} }
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub declarations: Vec<Declaration>,
+    pub statements: Vec<Statement>,
+    pub function_definitions: Vec<FunctionDefinition>,
+}
+
+// ParameterBlock := ( [Parameter {, Parameter}] )
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParameterBlock {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub parameters: Vec<Parameter>,
+}
+
+// DataType := IntegerType | FloatType
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataType {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub integer_type: Option<Box<IntegerType>>,
+    pub float_type: Option<Box<FloatType>>,
+}
+
+// Constant := IntConstant | FloatConstant
+#[derive(Clone, Debug, PartialEq)]
+pub struct Constant {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub int_constant: Option<Token>,
+    pub float_constant: Option<Token>,
+}
+
+// Statement := Assignment | WhileLoop | IfStatement | ReturnStatement | (Expression ;)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statement {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub assignment: Option<Box<Assignment>>,
+    pub while_loop: Option<Box<WhileLoop>>,
+    pub if_statement: Option<Box<IfStatement>>,
+    pub return_statement: Option<Box<ReturnStatement>>,
+    pub expression: Option<Box<Expression>>,
+}
+
+// Parameter := DataType Identifier
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameter {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub data_type: Box<DataType>,
+    pub identifier: Token,
+}
+
+// IntegerType := [unsigned] ( char | short | int | long )
+#[derive(Clone, Debug, PartialEq)]
+pub struct IntegerType {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+}
+
+// FloatType := float | double
+#[derive(Clone, Debug, PartialEq)]
+pub struct FloatType {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+}
+
+// Assignment := Identifier = {Identifier =} Expression ;
+#[derive(Clone, Debug, PartialEq)]
+pub struct Assignment {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub identifiers: Vec<Token>,
+    pub expression: Box<Expression>,
+}
+
+// WhileLoop := while ( Expression ) Block
+#[derive(Clone, Debug, PartialEq)]
+pub struct WhileLoop {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub expression: Box<Expression>,
+    pub block: Box<Block>,
+}
+
+// IfStatement := if ( Expression ) Block
+#[derive(Clone, Debug, PartialEq)]
+pub struct IfStatement {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub expression: Box<Expression>,
+    pub block: Box<Block>,
+}
+
+// ReturnStatement := return Expression ;
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReturnStatement {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub expression: Box<Expression>,
+}
+
+// Expression := { PrefixOperator } Factor { BinaryOperator { PrefixOperator } Factor }
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub prefix_operators: Vec<PrefixOperator>,
+    pub factors: Vec<Factor>,
+    pub binary_operators: Vec<BinaryOperator>,
+}
+
+// Factor := ( ( Expression ) ) | Constant | ( Identifier [ ( [ Expression {, Expression} ] ) ] )
+#[derive(Clone, Debug, PartialEq)]
+pub struct Factor {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+    pub expressions: Vec<Expression>,
+    pub constant: Option<Box<Constant>>,
+    pub identifier: Option<Token>,
+}
+
+// PrefixOperator := - | ! | ~
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrefixOperator {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+}
+
+// BinaryOperator := = | ( || ) | ( && ) | | | ^ | & | ( == ) | ( != ) | < | > | ( <= ) | ( >= ) | + | - | * | / | %
+#[derive(Clone, Debug, PartialEq)]
+pub struct BinaryOperator {
+    pub span: Span,
+    // the terminals of this rule, in order
+    pub tokens: Vec<Token>,
+}
+
+
+/**
+ * A backtracking recursive-descent parser generated from the grammar.
+ */
+pub struct Parser {
+    pub all_tokens: Vec<Token>,
+    token_index: usize,
+}
+
+impl Parser {
+    pub fn new(all_tokens: Vec<Token>) -> Parser {
+        Parser {
+            all_tokens,
+            token_index: 0,
+        }
+    }
+
+    // an error at the current token, or at the last one past the end
+    fn error_here(&self, ebnf: &str) -> SyntaxError {
+        let (line_num, char_pos) = match self.all_tokens.get(self.token_index).or(self.all_tokens.last()) {
+            Some(token) => (token.line_num, token.char_pos),
+            None => (0, 0),
+        };
+        SyntaxError {
+            line_num,
+            char_pos,
+            ebnf: ebnf.to_string(),
+        }
+    }
+
+    fn terminal(&mut self, token_type: TokenType, text: &str, ebnf: &str) -> Result<Token, SyntaxError> {
+        match self.all_tokens.get(self.token_index) {
+            Some(token)
+                if token.token_type == token_type
+                    && (text.is_empty() || token.text == text) =>
+            {
+                self.token_index += 1;
+                Ok(token.clone())
+            }
+            _ => Err(self.error_here(ebnf)),
+        }
+    }
+
+    // the span of the tokens consumed since start
+    fn span_from(&self, start: usize) -> Span {
+        if self.token_index <= start {
+            return match self.all_tokens.get(start) {
+                Some(token) => Span {
+                    start_line: token.line_num,
+                    start_char: token.char_pos,
+                    end_line: token.line_num,
+                    end_char: token.char_pos,
+                },
+                None => Span::default(),
+            };
+        }
+        let first = Span::of_token(&self.all_tokens[start]);
+        let last = Span::of_token(&self.all_tokens[self.token_index - 1]);
+        Span {
+            start_line: first.start_line,
+            start_char: first.start_char,
+            end_line: last.end_line,
+            end_char: last.end_char,
+        }
+    }
+
+    // parses the whole token stream
+    pub fn parse(&mut self) -> Result<Program, SyntaxError> {
+        self.token_index = 0;
+        let tree = self.parse_program()?;
+        if self.token_index < self.all_tokens.len() {
+            return Err(self.error_here("Program := { Declaration } MainDeclaration { FunctionDefinition }"));
+        }
+        Ok(tree)
+    }
+
+    pub fn parse_program(&mut self) -> Result<Program, SyntaxError> {
+        const EBNF: &str = "Program := { Declaration } MainDeclaration { FunctionDefinition }";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut declarations: Vec<Declaration> = Vec::new();
+        let mut main_declaration: Option<MainDeclaration> = None;
+        let mut function_definitions: Vec<FunctionDefinition> = Vec::new();
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = ({
+                loop {
+                    let mark = (self.token_index, tokens.len(), declarations.len(), main_declaration.is_some(), function_definitions.len());
+                    // stop on failure, or if nothing was consumed
+                    if (self.parse_declaration().map(|node| declarations.push(node))).is_err() || self.token_index == mark.0 {
+                        self.token_index = mark.0; tokens.truncate(mark.1); declarations.truncate(mark.2); main_declaration = main_declaration.filter(|_| mark.3); function_definitions.truncate(mark.4);
+                        break;
+                    }
+                }
+                Ok::<(), SyntaxError>(())
+            }) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.parse_main_declaration().map(|node| main_declaration = Some(node))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = ({
+                loop {
+                    let mark = (self.token_index, tokens.len(), declarations.len(), main_declaration.is_some(), function_definitions.len());
+                    // stop on failure, or if nothing was consumed
+                    if (self.parse_function_definition().map(|node| function_definitions.push(node))).is_err() || self.token_index == mark.0 {
+                        self.token_index = mark.0; tokens.truncate(mark.1); declarations.truncate(mark.2); main_declaration = main_declaration.filter(|_| mark.3); function_definitions.truncate(mark.4);
+                        break;
+                    }
+                }
+                Ok::<(), SyntaxError>(())
+            }) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(Program {
+            span: self.span_from(start),
+            tokens,
+            declarations,
+            main_declaration: Box::new(main_declaration.unwrap()),
+            function_definitions,
+        })
+    }
+
+    pub fn parse_declaration(&mut self) -> Result<Declaration, SyntaxError> {
+        const EBNF: &str = "Declaration := DeclarationType (VariableDeclaration | FunctionDeclaration)";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut declaration_type: Option<DeclarationType> = None;
+        let mut variable_declaration: Option<VariableDeclaration> = None;
+        let mut function_declaration: Option<FunctionDeclaration> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = (self.parse_declaration_type().map(|node| declaration_type = Some(node))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = ('l2: {
+                let mark = (self.token_index, tokens.len(), declaration_type.is_some(), variable_declaration.is_some(), function_declaration.is_some());
+                if (self.parse_variable_declaration().map(|node| variable_declaration = Some(node))).is_ok() {
+                    break 'l2 Ok(());
+                }
+                self.token_index = mark.0; tokens.truncate(mark.1); declaration_type = declaration_type.filter(|_| mark.2); variable_declaration = variable_declaration.filter(|_| mark.3); function_declaration = function_declaration.filter(|_| mark.4);
+                if (self.parse_function_declaration().map(|node| function_declaration = Some(node))).is_ok() {
+                    break 'l2 Ok(());
+                }
+                self.token_index = mark.0; tokens.truncate(mark.1); declaration_type = declaration_type.filter(|_| mark.2); variable_declaration = variable_declaration.filter(|_| mark.3); function_declaration = function_declaration.filter(|_| mark.4);
+                Err(self.error_here(EBNF))
+            }) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(Declaration {
+            span: self.span_from(start),
+            tokens,
+            declaration_type: Box::new(declaration_type.unwrap()),
+            variable_declaration: variable_declaration.map(Box::new),
+            function_declaration: function_declaration.map(Box::new),
+        })
+    }
+
+    pub fn parse_main_declaration(&mut self) -> Result<MainDeclaration, SyntaxError> {
+        const EBNF: &str = "MainDeclaration := void main ( ) Block";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut block: Option<Block> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = (self.terminal(TokenType::Keyword, "void", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Keyword, "main", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Operator, "(", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Operator, ")", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.parse_block().map(|node| block = Some(node))) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(MainDeclaration {
+            span: self.span_from(start),
+            tokens,
+            block: Box::new(block.unwrap()),
+        })
+    }
+
+    pub fn parse_function_definition(&mut self) -> Result<FunctionDefinition, SyntaxError> {
+        const EBNF: &str = "FunctionDefinition := DeclarationType ParameterBlock Block";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut declaration_type: Option<DeclarationType> = None;
+        let mut parameter_block: Option<ParameterBlock> = None;
+        let mut block: Option<Block> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = (self.parse_declaration_type().map(|node| declaration_type = Some(node))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.parse_parameter_block().map(|node| parameter_block = Some(node))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.parse_block().map(|node| block = Some(node))) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(FunctionDefinition {
+            span: self.span_from(start),
+            tokens,
+            declaration_type: Box::new(declaration_type.unwrap()),
+            parameter_block: Box::new(parameter_block.unwrap()),
+            block: Box::new(block.unwrap()),
+        })
+    }
+
+    pub fn parse_declaration_type(&mut self) -> Result<DeclarationType, SyntaxError> {
+        const EBNF: &str = "DeclarationType := DataType Identifier";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut data_type: Option<DataType> = None;
+        let mut identifier: Option<Token> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = (self.parse_data_type().map(|node| data_type = Some(node))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Identifier, "", EBNF).map(|token| { identifier = Some(token.clone()); tokens.push(token) })) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(DeclarationType {
+            span: self.span_from(start),
+            tokens,
+            data_type: Box::new(data_type.unwrap()),
+            identifier: identifier.unwrap(),
+        })
+    }
+
+    pub fn parse_variable_declaration(&mut self) -> Result<VariableDeclaration, SyntaxError> {
+        const EBNF: &str = "VariableDeclaration := [= Expression] ;";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut expression: Option<Expression> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = ({
+                let mark = (self.token_index, tokens.len(), expression.is_some());
+                if ('l2: {
+                    if let Err(e) = (self.terminal(TokenType::Operator, "=", EBNF).map(|token| tokens.push(token))) {
+                        break 'l2 Err(e);
+                    }
+                    if let Err(e) = (self.parse_expression().map(|node| expression = Some(node))) {
+                        break 'l2 Err(e);
+                    }
+                    Ok(())
+                }).is_err() {
+                    self.token_index = mark.0; tokens.truncate(mark.1); expression = expression.filter(|_| mark.2);
+                }
+                Ok::<(), SyntaxError>(())
+            }) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Operator, ";", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(VariableDeclaration {
+            span: self.span_from(start),
+            tokens,
+            expression: expression.map(Box::new),
+        })
+    }
+
+    pub fn parse_function_declaration(&mut self) -> Result<FunctionDeclaration, SyntaxError> {
+        const EBNF: &str = "FunctionDeclaration := ParameterBlock ;";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut parameter_block: Option<ParameterBlock> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = (self.parse_parameter_block().map(|node| parameter_block = Some(node))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Operator, ";", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(FunctionDeclaration {
+            span: self.span_from(start),
+            tokens,
+            parameter_block: Box::new(parameter_block.unwrap()),
+        })
+    }
+
+    pub fn parse_block(&mut self) -> Result<Block, SyntaxError> {
+        const EBNF: &str = "Block := { {Declaration} {Statement} {FunctionDefinition} }";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut declarations: Vec<Declaration> = Vec::new();
+        let mut statements: Vec<Statement> = Vec::new();
+        let mut function_definitions: Vec<FunctionDefinition> = Vec::new();
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = (self.terminal(TokenType::Operator, "{", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = ({
+                loop {
+                    let mark = (self.token_index, tokens.len(), declarations.len(), statements.len(), function_definitions.len());
+                    // stop on failure, or if nothing was consumed
+                    if (self.parse_declaration().map(|node| declarations.push(node))).is_err() || self.token_index == mark.0 {
+                        self.token_index = mark.0; tokens.truncate(mark.1); declarations.truncate(mark.2); statements.truncate(mark.3); function_definitions.truncate(mark.4);
+                        break;
+                    }
+                }
+                Ok::<(), SyntaxError>(())
+            }) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = ({
+                loop {
+                    let mark = (self.token_index, tokens.len(), declarations.len(), statements.len(), function_definitions.len());
+                    // stop on failure, or if nothing was consumed
+                    if (self.parse_statement().map(|node| statements.push(node))).is_err() || self.token_index == mark.0 {
+                        self.token_index = mark.0; tokens.truncate(mark.1); declarations.truncate(mark.2); statements.truncate(mark.3); function_definitions.truncate(mark.4);
+                        break;
+                    }
+                }
+                Ok::<(), SyntaxError>(())
+            }) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = ({
+                loop {
+                    let mark = (self.token_index, tokens.len(), declarations.len(), statements.len(), function_definitions.len());
+                    // stop on failure, or if nothing was consumed
+                    if (self.parse_function_definition().map(|node| function_definitions.push(node))).is_err() || self.token_index == mark.0 {
+                        self.token_index = mark.0; tokens.truncate(mark.1); declarations.truncate(mark.2); statements.truncate(mark.3); function_definitions.truncate(mark.4);
+                        break;
+                    }
+                }
+                Ok::<(), SyntaxError>(())
+            }) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Operator, "}", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(Block {
+            span: self.span_from(start),
+            tokens,
+            declarations,
+            statements,
+            function_definitions,
+        })
+    }
+
+    pub fn parse_parameter_block(&mut self) -> Result<ParameterBlock, SyntaxError> {
+        const EBNF: &str = "ParameterBlock := ( [Parameter {, Parameter}] )";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut parameters: Vec<Parameter> = Vec::new();
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = (self.terminal(TokenType::Operator, "(", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = ({
+                let mark = (self.token_index, tokens.len(), parameters.len());
+                if ('l2: {
+                    if let Err(e) = (self.parse_parameter().map(|node| parameters.push(node))) {
+                        break 'l2 Err(e);
+                    }
+                    if let Err(e) = ({
+                        loop {
+                            let mark = (self.token_index, tokens.len(), parameters.len());
+                            // stop on failure, or if nothing was consumed
+                            if ('l3: {
+                                if let Err(e) = (self.terminal(TokenType::Operator, ",", EBNF).map(|token| tokens.push(token))) {
+                                    break 'l3 Err(e);
+                                }
+                                if let Err(e) = (self.parse_parameter().map(|node| parameters.push(node))) {
+                                    break 'l3 Err(e);
+                                }
+                                Ok(())
+                            }).is_err() || self.token_index == mark.0 {
+                                self.token_index = mark.0; tokens.truncate(mark.1); parameters.truncate(mark.2);
+                                break;
+                            }
+                        }
+                        Ok::<(), SyntaxError>(())
+                    }) {
+                        break 'l2 Err(e);
+                    }
+                    Ok(())
+                }).is_err() {
+                    self.token_index = mark.0; tokens.truncate(mark.1); parameters.truncate(mark.2);
+                }
+                Ok::<(), SyntaxError>(())
+            }) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Operator, ")", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(ParameterBlock {
+            span: self.span_from(start),
+            tokens,
+            parameters,
+        })
+    }
+
+    pub fn parse_data_type(&mut self) -> Result<DataType, SyntaxError> {
+        const EBNF: &str = "DataType := IntegerType | FloatType";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut integer_type: Option<IntegerType> = None;
+        let mut float_type: Option<FloatType> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            let mark = (self.token_index, tokens.len(), integer_type.is_some(), float_type.is_some());
+            if (self.parse_integer_type().map(|node| integer_type = Some(node))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1); integer_type = integer_type.filter(|_| mark.2); float_type = float_type.filter(|_| mark.3);
+            if (self.parse_float_type().map(|node| float_type = Some(node))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1); integer_type = integer_type.filter(|_| mark.2); float_type = float_type.filter(|_| mark.3);
+            Err(self.error_here(EBNF))
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(DataType {
+            span: self.span_from(start),
+            tokens,
+            integer_type: integer_type.map(Box::new),
+            float_type: float_type.map(Box::new),
+        })
+    }
+
+    pub fn parse_constant(&mut self) -> Result<Constant, SyntaxError> {
+        const EBNF: &str = "Constant := IntConstant | FloatConstant";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut int_constant: Option<Token> = None;
+        let mut float_constant: Option<Token> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            let mark = (self.token_index, tokens.len(), int_constant.is_some(), float_constant.is_some());
+            if (self.terminal(TokenType::IntConstant, "", EBNF).map(|token| { int_constant = Some(token.clone()); tokens.push(token) })).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1); int_constant = int_constant.filter(|_| mark.2); float_constant = float_constant.filter(|_| mark.3);
+            if (self.terminal(TokenType::FloatConstant, "", EBNF).map(|token| { float_constant = Some(token.clone()); tokens.push(token) })).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1); int_constant = int_constant.filter(|_| mark.2); float_constant = float_constant.filter(|_| mark.3);
+            Err(self.error_here(EBNF))
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(Constant {
+            span: self.span_from(start),
+            tokens,
+            int_constant,
+            float_constant,
+        })
+    }
+
+    pub fn parse_statement(&mut self) -> Result<Statement, SyntaxError> {
+        const EBNF: &str = "Statement := Assignment | WhileLoop | IfStatement | ReturnStatement | (Expression ;)";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut assignment: Option<Assignment> = None;
+        let mut while_loop: Option<WhileLoop> = None;
+        let mut if_statement: Option<IfStatement> = None;
+        let mut return_statement: Option<ReturnStatement> = None;
+        let mut expression: Option<Expression> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            let mark = (self.token_index, tokens.len(), assignment.is_some(), while_loop.is_some(), if_statement.is_some(), return_statement.is_some(), expression.is_some());
+            if (self.parse_assignment().map(|node| assignment = Some(node))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1); assignment = assignment.filter(|_| mark.2); while_loop = while_loop.filter(|_| mark.3); if_statement = if_statement.filter(|_| mark.4); return_statement = return_statement.filter(|_| mark.5); expression = expression.filter(|_| mark.6);
+            if (self.parse_while_loop().map(|node| while_loop = Some(node))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1); assignment = assignment.filter(|_| mark.2); while_loop = while_loop.filter(|_| mark.3); if_statement = if_statement.filter(|_| mark.4); return_statement = return_statement.filter(|_| mark.5); expression = expression.filter(|_| mark.6);
+            if (self.parse_if_statement().map(|node| if_statement = Some(node))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1); assignment = assignment.filter(|_| mark.2); while_loop = while_loop.filter(|_| mark.3); if_statement = if_statement.filter(|_| mark.4); return_statement = return_statement.filter(|_| mark.5); expression = expression.filter(|_| mark.6);
+            if (self.parse_return_statement().map(|node| return_statement = Some(node))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1); assignment = assignment.filter(|_| mark.2); while_loop = while_loop.filter(|_| mark.3); if_statement = if_statement.filter(|_| mark.4); return_statement = return_statement.filter(|_| mark.5); expression = expression.filter(|_| mark.6);
+            if ('l2: {
+                if let Err(e) = (self.parse_expression().map(|node| expression = Some(node))) {
+                    break 'l2 Err(e);
+                }
+                if let Err(e) = (self.terminal(TokenType::Operator, ";", EBNF).map(|token| tokens.push(token))) {
+                    break 'l2 Err(e);
+                }
+                Ok(())
+            }).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1); assignment = assignment.filter(|_| mark.2); while_loop = while_loop.filter(|_| mark.3); if_statement = if_statement.filter(|_| mark.4); return_statement = return_statement.filter(|_| mark.5); expression = expression.filter(|_| mark.6);
+            Err(self.error_here(EBNF))
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(Statement {
+            span: self.span_from(start),
+            tokens,
+            assignment: assignment.map(Box::new),
+            while_loop: while_loop.map(Box::new),
+            if_statement: if_statement.map(Box::new),
+            return_statement: return_statement.map(Box::new),
+            expression: expression.map(Box::new),
+        })
+    }
+
+    pub fn parse_parameter(&mut self) -> Result<Parameter, SyntaxError> {
+        const EBNF: &str = "Parameter := DataType Identifier";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut data_type: Option<DataType> = None;
+        let mut identifier: Option<Token> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = (self.parse_data_type().map(|node| data_type = Some(node))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Identifier, "", EBNF).map(|token| { identifier = Some(token.clone()); tokens.push(token) })) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(Parameter {
+            span: self.span_from(start),
+            tokens,
+            data_type: Box::new(data_type.unwrap()),
+            identifier: identifier.unwrap(),
+        })
+    }
+
+    pub fn parse_integer_type(&mut self) -> Result<IntegerType, SyntaxError> {
+        const EBNF: &str = "IntegerType := [unsigned] ( char | short | int | long )";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = ({
+                let mark = (self.token_index, tokens.len());
+                if (self.terminal(TokenType::Keyword, "unsigned", EBNF).map(|token| tokens.push(token))).is_err() {
+                    self.token_index = mark.0; tokens.truncate(mark.1);
+                }
+                Ok::<(), SyntaxError>(())
+            }) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = ('l2: {
+                let mark = (self.token_index, tokens.len());
+                if (self.terminal(TokenType::Keyword, "char", EBNF).map(|token| tokens.push(token))).is_ok() {
+                    break 'l2 Ok(());
+                }
+                self.token_index = mark.0; tokens.truncate(mark.1);
+                if (self.terminal(TokenType::Keyword, "short", EBNF).map(|token| tokens.push(token))).is_ok() {
+                    break 'l2 Ok(());
+                }
+                self.token_index = mark.0; tokens.truncate(mark.1);
+                if (self.terminal(TokenType::Keyword, "int", EBNF).map(|token| tokens.push(token))).is_ok() {
+                    break 'l2 Ok(());
+                }
+                self.token_index = mark.0; tokens.truncate(mark.1);
+                if (self.terminal(TokenType::Keyword, "long", EBNF).map(|token| tokens.push(token))).is_ok() {
+                    break 'l2 Ok(());
+                }
+                self.token_index = mark.0; tokens.truncate(mark.1);
+                Err(self.error_here(EBNF))
+            }) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(IntegerType {
+            span: self.span_from(start),
+            tokens,
+        })
+    }
+
+    pub fn parse_float_type(&mut self) -> Result<FloatType, SyntaxError> {
+        const EBNF: &str = "FloatType := float | double";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let res: Result<(), SyntaxError> = 'l1: {
+            let mark = (self.token_index, tokens.len());
+            if (self.terminal(TokenType::Keyword, "float", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Keyword, "double", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            Err(self.error_here(EBNF))
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(FloatType {
+            span: self.span_from(start),
+            tokens,
+        })
+    }
+
+    pub fn parse_assignment(&mut self) -> Result<Assignment, SyntaxError> {
+        const EBNF: &str = "Assignment := Identifier = {Identifier =} Expression ;";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut identifiers: Vec<Token> = Vec::new();
+        let mut expression: Option<Expression> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = (self.terminal(TokenType::Identifier, "", EBNF).map(|token| { identifiers.push(token.clone()); tokens.push(token) })) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Operator, "=", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = ({
+                loop {
+                    let mark = (self.token_index, tokens.len(), identifiers.len(), expression.is_some());
+                    // stop on failure, or if nothing was consumed
+                    if ('l2: {
+                        if let Err(e) = (self.terminal(TokenType::Identifier, "", EBNF).map(|token| { identifiers.push(token.clone()); tokens.push(token) })) {
+                            break 'l2 Err(e);
+                        }
+                        if let Err(e) = (self.terminal(TokenType::Operator, "=", EBNF).map(|token| tokens.push(token))) {
+                            break 'l2 Err(e);
+                        }
+                        Ok(())
+                    }).is_err() || self.token_index == mark.0 {
+                        self.token_index = mark.0; tokens.truncate(mark.1); identifiers.truncate(mark.2); expression = expression.filter(|_| mark.3);
+                        break;
+                    }
+                }
+                Ok::<(), SyntaxError>(())
+            }) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.parse_expression().map(|node| expression = Some(node))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Operator, ";", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(Assignment {
+            span: self.span_from(start),
+            tokens,
+            identifiers,
+            expression: Box::new(expression.unwrap()),
+        })
+    }
+
+    pub fn parse_while_loop(&mut self) -> Result<WhileLoop, SyntaxError> {
+        const EBNF: &str = "WhileLoop := while ( Expression ) Block";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut expression: Option<Expression> = None;
+        let mut block: Option<Block> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = (self.terminal(TokenType::Keyword, "while", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Operator, "(", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.parse_expression().map(|node| expression = Some(node))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Operator, ")", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.parse_block().map(|node| block = Some(node))) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(WhileLoop {
+            span: self.span_from(start),
+            tokens,
+            expression: Box::new(expression.unwrap()),
+            block: Box::new(block.unwrap()),
+        })
+    }
+
+    pub fn parse_if_statement(&mut self) -> Result<IfStatement, SyntaxError> {
+        const EBNF: &str = "IfStatement := if ( Expression ) Block";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut expression: Option<Expression> = None;
+        let mut block: Option<Block> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = (self.terminal(TokenType::Keyword, "if", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Operator, "(", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.parse_expression().map(|node| expression = Some(node))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Operator, ")", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.parse_block().map(|node| block = Some(node))) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(IfStatement {
+            span: self.span_from(start),
+            tokens,
+            expression: Box::new(expression.unwrap()),
+            block: Box::new(block.unwrap()),
+        })
+    }
+
+    pub fn parse_return_statement(&mut self) -> Result<ReturnStatement, SyntaxError> {
+        const EBNF: &str = "ReturnStatement := return Expression ;";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut expression: Option<Expression> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = (self.terminal(TokenType::Keyword, "return", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.parse_expression().map(|node| expression = Some(node))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.terminal(TokenType::Operator, ";", EBNF).map(|token| tokens.push(token))) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(ReturnStatement {
+            span: self.span_from(start),
+            tokens,
+            expression: Box::new(expression.unwrap()),
+        })
+    }
+
+    pub fn parse_expression(&mut self) -> Result<Expression, SyntaxError> {
+        const EBNF: &str = "Expression := { PrefixOperator } Factor { BinaryOperator { PrefixOperator } Factor }";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut prefix_operators: Vec<PrefixOperator> = Vec::new();
+        let mut factors: Vec<Factor> = Vec::new();
+        let mut binary_operators: Vec<BinaryOperator> = Vec::new();
+        let res: Result<(), SyntaxError> = 'l1: {
+            if let Err(e) = ({
+                loop {
+                    let mark = (self.token_index, tokens.len(), prefix_operators.len(), factors.len(), binary_operators.len());
+                    // stop on failure, or if nothing was consumed
+                    if (self.parse_prefix_operator().map(|node| prefix_operators.push(node))).is_err() || self.token_index == mark.0 {
+                        self.token_index = mark.0; tokens.truncate(mark.1); prefix_operators.truncate(mark.2); factors.truncate(mark.3); binary_operators.truncate(mark.4);
+                        break;
+                    }
+                }
+                Ok::<(), SyntaxError>(())
+            }) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = (self.parse_factor().map(|node| factors.push(node))) {
+                break 'l1 Err(e);
+            }
+            if let Err(e) = ({
+                loop {
+                    let mark = (self.token_index, tokens.len(), prefix_operators.len(), factors.len(), binary_operators.len());
+                    // stop on failure, or if nothing was consumed
+                    if ('l2: {
+                        if let Err(e) = (self.parse_binary_operator().map(|node| binary_operators.push(node))) {
+                            break 'l2 Err(e);
+                        }
+                        if let Err(e) = ({
+                            loop {
+                                let mark = (self.token_index, tokens.len(), prefix_operators.len(), factors.len(), binary_operators.len());
+                                // stop on failure, or if nothing was consumed
+                                if (self.parse_prefix_operator().map(|node| prefix_operators.push(node))).is_err() || self.token_index == mark.0 {
+                                    self.token_index = mark.0; tokens.truncate(mark.1); prefix_operators.truncate(mark.2); factors.truncate(mark.3); binary_operators.truncate(mark.4);
+                                    break;
+                                }
+                            }
+                            Ok::<(), SyntaxError>(())
+                        }) {
+                            break 'l2 Err(e);
+                        }
+                        if let Err(e) = (self.parse_factor().map(|node| factors.push(node))) {
+                            break 'l2 Err(e);
+                        }
+                        Ok(())
+                    }).is_err() || self.token_index == mark.0 {
+                        self.token_index = mark.0; tokens.truncate(mark.1); prefix_operators.truncate(mark.2); factors.truncate(mark.3); binary_operators.truncate(mark.4);
+                        break;
+                    }
+                }
+                Ok::<(), SyntaxError>(())
+            }) {
+                break 'l1 Err(e);
+            }
+            Ok(())
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(Expression {
+            span: self.span_from(start),
+            tokens,
+            prefix_operators,
+            factors,
+            binary_operators,
+        })
+    }
+
+    pub fn parse_factor(&mut self) -> Result<Factor, SyntaxError> {
+        const EBNF: &str = "Factor := ( ( Expression ) ) | Constant | ( Identifier [ ( [ Expression {, Expression} ] ) ] )";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut expressions: Vec<Expression> = Vec::new();
+        let mut constant: Option<Constant> = None;
+        let mut identifier: Option<Token> = None;
+        let res: Result<(), SyntaxError> = 'l1: {
+            let mark = (self.token_index, tokens.len(), expressions.len(), constant.is_some(), identifier.is_some());
+            if ('l2: {
+                if let Err(e) = (self.terminal(TokenType::Operator, "(", EBNF).map(|token| tokens.push(token))) {
+                    break 'l2 Err(e);
+                }
+                if let Err(e) = (self.parse_expression().map(|node| expressions.push(node))) {
+                    break 'l2 Err(e);
+                }
+                if let Err(e) = (self.terminal(TokenType::Operator, ")", EBNF).map(|token| tokens.push(token))) {
+                    break 'l2 Err(e);
+                }
+                Ok(())
+            }).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1); expressions.truncate(mark.2); constant = constant.filter(|_| mark.3); identifier = identifier.filter(|_| mark.4);
+            if (self.parse_constant().map(|node| constant = Some(node))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1); expressions.truncate(mark.2); constant = constant.filter(|_| mark.3); identifier = identifier.filter(|_| mark.4);
+            if ('l3: {
+                if let Err(e) = (self.terminal(TokenType::Identifier, "", EBNF).map(|token| { identifier = Some(token.clone()); tokens.push(token) })) {
+                    break 'l3 Err(e);
+                }
+                if let Err(e) = ({
+                    let mark = (self.token_index, tokens.len(), expressions.len(), constant.is_some(), identifier.is_some());
+                    if ('l4: {
+                        if let Err(e) = (self.terminal(TokenType::Operator, "(", EBNF).map(|token| tokens.push(token))) {
+                            break 'l4 Err(e);
+                        }
+                        if let Err(e) = ({
+                            let mark = (self.token_index, tokens.len(), expressions.len(), constant.is_some(), identifier.is_some());
+                            if ('l5: {
+                                if let Err(e) = (self.parse_expression().map(|node| expressions.push(node))) {
+                                    break 'l5 Err(e);
+                                }
+                                if let Err(e) = ({
+                                    loop {
+                                        let mark = (self.token_index, tokens.len(), expressions.len(), constant.is_some(), identifier.is_some());
+                                        // stop on failure, or if nothing was consumed
+                                        if ('l6: {
+                                            if let Err(e) = (self.terminal(TokenType::Operator, ",", EBNF).map(|token| tokens.push(token))) {
+                                                break 'l6 Err(e);
+                                            }
+                                            if let Err(e) = (self.parse_expression().map(|node| expressions.push(node))) {
+                                                break 'l6 Err(e);
+                                            }
+                                            Ok(())
+                                        }).is_err() || self.token_index == mark.0 {
+                                            self.token_index = mark.0; tokens.truncate(mark.1); expressions.truncate(mark.2); constant = constant.filter(|_| mark.3); identifier = identifier.filter(|_| mark.4);
+                                            break;
+                                        }
+                                    }
+                                    Ok::<(), SyntaxError>(())
+                                }) {
+                                    break 'l5 Err(e);
+                                }
+                                Ok(())
+                            }).is_err() {
+                                self.token_index = mark.0; tokens.truncate(mark.1); expressions.truncate(mark.2); constant = constant.filter(|_| mark.3); identifier = identifier.filter(|_| mark.4);
+                            }
+                            Ok::<(), SyntaxError>(())
+                        }) {
+                            break 'l4 Err(e);
+                        }
+                        if let Err(e) = (self.terminal(TokenType::Operator, ")", EBNF).map(|token| tokens.push(token))) {
+                            break 'l4 Err(e);
+                        }
+                        Ok(())
+                    }).is_err() {
+                        self.token_index = mark.0; tokens.truncate(mark.1); expressions.truncate(mark.2); constant = constant.filter(|_| mark.3); identifier = identifier.filter(|_| mark.4);
+                    }
+                    Ok::<(), SyntaxError>(())
+                }) {
+                    break 'l3 Err(e);
+                }
+                Ok(())
+            }).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1); expressions.truncate(mark.2); constant = constant.filter(|_| mark.3); identifier = identifier.filter(|_| mark.4);
+            Err(self.error_here(EBNF))
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(Factor {
+            span: self.span_from(start),
+            tokens,
+            expressions,
+            constant: constant.map(Box::new),
+            identifier,
+        })
+    }
+
+    pub fn parse_prefix_operator(&mut self) -> Result<PrefixOperator, SyntaxError> {
+        const EBNF: &str = "PrefixOperator := - | ! | ~";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let res: Result<(), SyntaxError> = 'l1: {
+            let mark = (self.token_index, tokens.len());
+            if (self.terminal(TokenType::Operator, "-", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "!", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "~", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            Err(self.error_here(EBNF))
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(PrefixOperator {
+            span: self.span_from(start),
+            tokens,
+        })
+    }
+
+    pub fn parse_binary_operator(&mut self) -> Result<BinaryOperator, SyntaxError> {
+        const EBNF: &str = "BinaryOperator := = | ( || ) | ( && ) | | | ^ | & | ( == ) | ( != ) | < | > | ( <= ) | ( >= ) | + | - | * | / | %";
+        let start = self.token_index;
+        let mut tokens: Vec<Token> = Vec::new();
+        let res: Result<(), SyntaxError> = 'l1: {
+            let mark = (self.token_index, tokens.len());
+            if (self.terminal(TokenType::Operator, "=", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "||", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "&&", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "|", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "^", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "&", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "==", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "!=", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "<", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, ">", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "<=", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, ">=", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "+", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "-", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "*", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "/", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            if (self.terminal(TokenType::Operator, "%", EBNF).map(|token| tokens.push(token))).is_ok() {
+                break 'l1 Ok(());
+            }
+            self.token_index = mark.0; tokens.truncate(mark.1);
+            Err(self.error_here(EBNF))
+        };
+        if let Err(e) = res {
+            self.token_index = start;
+            return Err(e);
+        }
+        Ok(BinaryOperator {
+            span: self.span_from(start),
+            tokens,
+        })
+    }
+}
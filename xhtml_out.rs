@@ -1,18 +1,44 @@
 use std::fs;
+use std::io;
+use std::path::Path;
 
 //here we are parsing the .x files into xhtml file
 //following the given EBNF grammar. 
 
 use crate::stage3::Parser;
 
+use crate::stage2_scanner::Scanner;
+
 use crate::stage2_token::{Token, TokenType};
 
+//the start of the document up to the title, XHTML 1.0 and HTML5
+
+const XHTML_HEAD: &str = r#"
+<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd">
+<html xmlns="http://www.w3.org/1999/xhtml" xml:lang="en">
+<head>
+"#;
+
+const HTML_HEAD: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8" />
+"#;
+
 //outputting the xhmtl file 
 
 pub struct XHTML {
     
     parser: Parser,
     
+    //the comments, highlighted between the tokens
+    
+    comments: Vec<Token>,
+    
+    //the input file, run() writes the output next to it
+    
+    xfilename: String,
+    
     //result
     
     res: String,
@@ -25,33 +51,62 @@ pub struct XHTML {
 impl XHTML {
     pub fn new(xfilename: &str) -> XHTML {
         
+        let contents = fs::read_to_string(xfilename).expect("Error occurred when reading the input file!");
+        
+        XHTML::from_source(xfilename, &contents)
+    }
+    
+    
+    //the same for contents that are already in memory, xfilename only names them
+    pub fn from_source(xfilename: &str, contents: &str) -> XHTML {
+        
+        let mut scanner = Scanner::from_source(xfilename, contents);
+        
+        scanner.run();
+        
         XHTML {
-            parser: Parser::new(xfilename),
+            parser: Parser::from_tokens(scanner.all_tokens.clone()),
+            
+            comments: scanner.comments.clone(),
+            
+            xfilename: xfilename.to_string(),
             
             res: "".to_string()
         }
     }
     
+    //the xhtml text of the tokens, without writing a file
+    pub fn render(&mut self) -> String {
+        
+        self.for_result(XHTML_HEAD)
+    }
+    
+    //the same as an HTML5 document
+    pub fn render_html(&mut self) -> String {
+        
+        self.for_result(HTML_HEAD)
+    }
+    
     
     //online resources for titling reference
 
-    fn for_result(&mut self) -> String {
-        let mut result = 
-r#"
-<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd">
-<html xmlns="http://www.w3.org/1999/xhtml" xml:lang="en">
-<head>
-<title>X Formatted file</title>
+    fn for_result(&mut self, head: &str) -> String {
+        let mut res = head.to_string() + 
+r#"<title>X Formatted file</title>
 </head>
 <body bgcolor="navy" text="orange" link="orange" vlink="orange">
 <font face="Courier New">
-"#.to_string();
+"#;
 
         let mut line_num = 0;
         
         let mut char_pos = 0;
         
-        for token in self.parser.all_tokens.iter() {
+        let mut tokens: Vec<&Token> = self.parser.all_tokens.iter().chain(self.comments.iter()).collect();
+        
+        tokens.sort_by_key(|token| (token.line_num, token.char_pos));
+        
+        for token in tokens {
             
             
             {
@@ -84,28 +139,36 @@ r#"
             //match different token types 
             
 
+            //< > and & in the token text would break the markup
+            let text = escape(&token.text);
+            
             match token.token_type {
                 
                 TokenType::Keyword | TokenType::Operator => {
                     
-                    res += &format!("<font color=\"white\"><b>{}</b></font>", token.text);
+                    res += &format!("<font color=\"white\"><b>{}</b></font>", text);
                 },
                 TokenType::IntConstant | TokenType::FloatConstant => {
                     
-                    res += &format!("<font color=\"aqua\"><b>{}</b></font>", token.text);
+                    res += &format!("<font color=\"aqua\"><b>{}</b></font>", text);
                 },
                 
                 TokenType::Identifier => {
                     
-                    res += &format!("<font color=\"yellow\">{}</font>", token.text);
+                    res += &format!("<font color=\"yellow\">{}</font>", text);
+                },
+                
+                TokenType::Comment => {
+                    
+                    res += &format!("<font color=\"silver\"><i>{}</i></font>", text);
                 },
                 
                 _ => {
-                    res += &format!("<font color=\"red\">{}</font>", token.text);
+                    res += &format!("<font color=\"red\">{}</font>", text);
                 }
             }
             line_num = token.line_num;
-            char_pos = token.char_pos + token.text.len() as i32;
+            char_pos = token.char_pos + token.text.chars().count() as i32;
             
             
         }
@@ -118,28 +181,48 @@ r#"
 "#;
         //clone the result to the saved buffer file 
             
-        self.res = result.clone();
+        self.res = res.clone();
         
-        return res;
+        res
     }
 
+    //writes exampleN.xhtml next to exampleN.x
     pub fn run(&mut self) {
         
-        self.parser.run();
+        let xhtml_filename = Path::new(&self.xfilename).with_extension("xhtml");
+        
+        self.write_to(&xhtml_filename).expect("error when writing the file!");
+    }
+    
+    //writes the XHTML, or HTML5 for a .html file, to the given file
+    pub fn write_to(&mut self, xhtml_filename: &Path) -> io::Result<()> {
         
-        let res = self.for_result();
+        let res = if xhtml_filename.extension().is_some_and(|extension| extension == "html") {
+            self.render_html()
+        } else {
+            self.render()
+        };
         
-        fs::write("example.xhtml", result).expect("error when writing the file!");
+        fs::write(xhtml_filename, res)
     }
 }
 
-//worked
-
-pub fn test_run() {
+//the XHTML escapes of the characters that are markup
+pub fn escape(text: &str) -> String {
     
-    let mut xhtml = XHTML::new("example1.x");
-    
-    xhtml.run();
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
 }
 
-pub fn test() {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_example1() {
+        
+        //example1.xhtml is the expected output checked in next to example1.x
+        let expected = fs::read_to_string("example1.xhtml").expect("Error occurred when reading the input file!");
+        
+        assert_eq!(XHTML::new("example1.x").render(), expected);
+    }
+}